@@ -12,6 +12,8 @@ pub type Real = f64;
 pub type Vec2 = Vector2<Real>;
 pub type Vec3 = Vector3<Real>;
 pub type Vec5 = Vector5<Real>;
+pub type Vec6 = Vector6<Real>;
+pub type Vec8 = VectorN<Real, U8>;
 
 pub type P2 = Point2<Real>;
 pub type P3 = Point3<Real>;
@@ -20,11 +22,15 @@ pub type Mat2 = Matrix2<Real>;
 pub type Mat3 = Matrix3<Real>;
 pub type Mat4 = Matrix4<Real>;
 pub type Mat5 = Matrix5<Real>;
+pub type Mat6 = Matrix6<Real>;
 pub type Mat8 = MatrixMN<Real, U8, U8>;
 
 pub type Mat2x5 =Matrix2x5<Real>;
 pub type Mat5x2 =Matrix5x2<Real>;
 
+pub type Mat2x3 =Matrix2x3<Real>;
+pub type Mat3x2 =Matrix3x2<Real>;
+
 pub type Mat5x8 = MatrixMN<Real, U5, U8>;
 pub type Mat8x5 = MatrixMN<Real, U8, U5>;
 
@@ -43,9 +49,25 @@ def_constant!{usize;
     eT = 5
 }
 
+// indices of the free (global) parameters
+def_constant!{usize;
+    eFREE_POS0 = 0,
+    eFREE_POS1 = 1,
+    eFREE_POS2 = 2,
+    eFREE_TIME = 3,
+    eFREE_DIR0 = 4,
+    eFREE_DIR1 = 5,
+    eFREE_DIR2 = 6,
+    eFREE_QOP = 7
+}
+
 // f64 constants
 def_constant!{Real;
-    DOT_PRODUCT_EPSILON = 0.0005
+    DOT_PRODUCT_EPSILON = 0.0005,
+    // below this sin(theta) a direction is along the global z axis and phi is not defined
+    SIN_THETA_EPSILON = 1e-10,
+    // above this |cos(theta)| the curvilinear frame is built from the global x axis
+    CURVILINEAR_PROJECTION_TOLERANCE = 0.999995
 }
 
 
@@ -41,11 +41,15 @@ macro_rules! impl_from {
 }
 
 use super::config::*;
+use super::geometry::GeometryId;
 
 #[derive(Debug)]
 pub enum Error{
     Matrix(MatrixError),
-    Sensor(SensorError)
+    Sensor(SensorError),
+    Geometry(GeometryError),
+    Filter(FilterError),
+    Alignment(AlignmentError)
 }
 
 #[derive(Debug)]
@@ -55,7 +59,40 @@ pub enum MatrixError {
 
 #[derive(Debug)]
 pub enum SensorError {
-    OutsideSensorBounds(P2)
+    OutsideSensorBounds(P2),
+    InvalidDirection(Vec3),
+    InvalidBounds(&'static str),
+    UnknownGeometryId(GeometryId),
+    MissingGeometryId(usize),
+    WrongSeedSurface(GeometryId),
+    NotOnSurface(P3)
+}
+
+#[derive(Debug)]
+pub enum GeometryError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Toml(String),
+    UnknownFormat(String),
+    InvalidPlacement(&'static str),
+    DuplicateId(GeometryId),
+    Matrix(MatrixError),
+    Sensor(SensorError)
+}
+
+#[derive(Debug)]
+pub enum FilterError {
+    LengthMismatch,
+    NotEnoughMeasurements,
+    MissingCovariance,
+    Matrix(MatrixError),
+    Sensor(SensorError)
+}
+
+#[derive(Debug)]
+pub enum AlignmentError {
+    LengthMismatch,
+    NoFixedSurface
 }
 
 // this function is only here to ensure that all `std::From` trait implementations 
@@ -68,4 +105,21 @@ fn init_from() {
     
     //SensorError
     impl_from!(SensorError, Error, Error::Sensor);
+
+    //GeometryError
+    impl_from!(GeometryError, Error, Error::Geometry);
+    impl_from!(MatrixError, GeometryError, GeometryError::Matrix);
+    impl_from!(SensorError, GeometryError, GeometryError::Sensor);
+    use std::io::Error as IoError;
+    use serde_json::Error as JsonError;
+    impl_from!(IoError, GeometryError, GeometryError::Io);
+    impl_from!(JsonError, GeometryError, GeometryError::Json);
+
+    //FilterError
+    impl_from!(FilterError, Error, Error::Filter);
+    impl_from!(MatrixError, FilterError, FilterError::Matrix);
+    impl_from!(SensorError, FilterError, FilterError::Sensor);
+
+    //AlignmentError
+    impl_from!(AlignmentError, Error, Error::Alignment);
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+pub mod obj;
+pub mod phoenix;
+
+use super::config::*;
+use super::geometry::traits::{Transform, Plane};
+use super::generate_data::structs::KFData;
+
+/// Global positions of track states given in the local frame of each sensor. Sensors and
+/// states are matched by their index.
+pub fn global_points<T: Transform>(sensors: &[T], states: &[Vec5]) -> Vec<P3> {
+    sensors.iter()
+        .zip(states.iter())
+        .map(|(sensor, state)| sensor.to_global(P3::new(state[eLOC_0], state[eLOC_1], 0.)))
+        .collect()
+}
+
+/// Global positions of the truth track of a generated event, starting from its origin
+pub fn truth_points<T: Transform + Plane>(data: &KFData<T>) -> Vec<P3> {
+    let hits = data.sensors.iter()
+        .zip(data.truth_hits.iter())
+        .map(|(sensor, hit)| sensor.to_global(P3::new(hit.x, hit.y, 0.)));
+
+    std::iter::once(data.start).chain(hits).collect()
+}
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::super::config::*;
+use super::super::geometry::traits::{Identified, Outline};
+
+/// Builds a Wavefront OBJ file of sensors and tracks that can be opened in any 3D viewer.
+/// Sensors are written as polygon faces and tracks as polylines.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::geometry::Rectangle;
+/// use kalman_rs::export::obj::ObjExporter;
+///
+/// let sensor = Rectangle::new(2., 2., Mat4::identity(), Mat4::identity()).unwrap();
+///
+/// let mut exporter = ObjExporter::new();
+/// exporter.add_surface(&sensor);
+/// exporter.add_track("truth", vec![P3::new(0., 0., -1.), P3::new(0., 0., 1.)]);
+///
+/// let obj = exporter.to_obj();
+/// assert!(obj.contains("f 1 2 3 4"));
+/// assert!(obj.contains("l 5 6"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObjExporter {
+    /// write every sensor and track as its own object. Otherwise all the sensors are in a
+    /// single `surfaces` object and all the tracks in a single `tracks` object.
+    pub separate_objects: bool,
+    /// number of straight segments used for curved edges
+    pub arc_segments: usize,
+
+    surfaces: Vec<(String, Vec<P3>)>,
+    tracks: Vec<(String, Vec<P3>)>
+}
+
+impl Default for ObjExporter {
+    fn default() -> Self {
+        ObjExporter {separate_objects: false, arc_segments: 16, surfaces: Vec::new(), tracks: Vec::new()}
+    }
+}
+
+impl ObjExporter {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sensor as a single face. The object is named after the id of the sensor.
+    pub fn add_surface<T: Outline + Identified>(&mut self, sensor: &T) {
+        let id = sensor.geometry_id();
+        let name = format!("surface_{}_{}_{}", id.volume(), id.layer(), id.sensitive());
+
+        self.surfaces.push((name, sensor.global_outline(self.arc_segments)));
+    }
+
+    pub fn add_surfaces<'a, T, I>(&mut self, sensors: I)
+        where T: 'a + Outline + Identified, I: IntoIterator<Item = &'a T> {
+        sensors.into_iter().for_each(|sensor| self.add_surface(sensor));
+    }
+
+    /// Adds a track going through `points` in order. Spaces in the name are replaced since
+    /// they are not allowed in object names.
+    pub fn add_track(&mut self, name: &str, points: Vec<P3>) {
+        let name = name.split_whitespace().collect::<Vec<_>>().join("_");
+        self.tracks.push((name, points));
+    }
+
+    /// Contents of the OBJ file
+    pub fn to_obj(&self) -> String {
+        let mut obj = String::from("# kalman_rs export\n");
+        let mut vertex_count = 0;
+
+        // writing to a String never fails
+        let mut write_object = |obj: &mut String, keyword: &str, name: Option<&str>, points: &[P3]| {
+            if let Some(name) = name {
+                writeln!(obj, "o {}", name).unwrap();
+            }
+
+            for point in points {
+                writeln!(obj, "v {} {} {}", point.x, point.y, point.z).unwrap();
+            }
+
+            let indices = (1..=points.len())
+                .map(|i| (vertex_count + i).to_string())
+                .collect::<Vec<_>>();
+            writeln!(obj, "{} {}", keyword, indices.join(" ")).unwrap();
+
+            vertex_count += points.len();
+        };
+
+        // a face needs three vertices and a line two
+        let kinds = [(&self.surfaces, "f", "surfaces", 3), (&self.tracks, "l", "tracks", 2)];
+
+        for (objects, keyword, group, min_points) in &kinds {
+            let objects = objects.iter().filter(|(_, points)| points.len() >= *min_points);
+
+            for (i, (name, points)) in objects.enumerate() {
+                let name =
+                    if self.separate_objects {Some(name.as_str())}
+                    else if i == 0 {Some(*group)}
+                    else {None};
+
+                write_object(&mut obj, keyword, name, points);
+            }
+        }
+
+        obj
+    }
+
+    /// Writes the OBJ file to `path`
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        fs::write(path, self.to_obj())
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::truth_points;
+use super::super::config::*;
+use super::super::geometry::id::GeometryId;
+use super::super::geometry::traits::{Transform, Plane, Identified};
+use super::super::generate_data::structs::KFData;
+use super::super::filter::utils::SuperData;
+
+/// A measurement drawn as a point
+#[derive(Debug, Clone, Serialize)]
+pub struct PhoenixHit {
+    pub pos: [Real; 3],
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub geometry_id: GeometryId
+}
+
+/// Fitted parameters of a track on one of its surfaces
+#[derive(Debug, Clone, Serialize)]
+pub struct PhoenixTrackState {
+    pub geometry_id: GeometryId,
+    pub pos: [Real; 3],
+    /// (loc_0, loc_1, phi, theta, q/p) in the local frame of the surface
+    pub params: [Real; 5],
+    /// covariance of the parameters, row by row
+    pub covariance: [[Real; 5]; 5]
+}
+
+/// A track drawn as a line through `pos`
+#[derive(Debug, Clone, Serialize)]
+pub struct PhoenixTrack {
+    pub pos: Vec<[Real; 3]>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<PhoenixTrackState>
+}
+
+/// One event of the file. Hits and tracks are grouped in named collections.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhoenixEvent {
+    #[serde(rename = "event number")]
+    pub event_number: u64,
+    #[serde(rename = "run number")]
+    pub run_number: u64,
+    #[serde(rename = "Hits")]
+    pub hits: BTreeMap<String, Vec<PhoenixHit>>,
+    #[serde(rename = "Tracks")]
+    pub tracks: BTreeMap<String, Vec<PhoenixTrack>>
+}
+
+/// Builds event data files in the JSON format read by the Phoenix web event display. Every
+/// event has the smeared measurements in the `Measurements` hit collection, and the truth and
+/// fitted tracks in the `Truth` and `Fitted` track collections.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::generate_data::setup::generate_track;
+/// use kalman_rs::filter::linear;
+/// use kalman_rs::export::phoenix::PhoenixExporter;
+/// use rand::{SeedableRng, rngs::SmallRng};
+/// use rand_distr::Normal;
+///
+/// let rng = SmallRng::seed_from_u64(0);
+/// let data = generate_track(5, 1., None, rng, 0.01, Normal::new(3., 1.5).unwrap(), Normal::new(0., 1.).unwrap());
+/// let fit = linear::run(&data.start, &data.cov, &data.smear_hits, &data.sensors, None);
+///
+/// let mut exporter = PhoenixExporter::new();
+/// exporter.add_event("event_0", 0, &data, &fit);
+///
+/// let json = exporter.to_json().unwrap();
+/// assert!(json.contains("\"event number\":0"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PhoenixExporter {
+    /// run number written to every event
+    pub run_number: u64,
+    events: BTreeMap<String, PhoenixEvent>
+}
+
+impl PhoenixExporter {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a generated event and the result of fitting it. The states of the fit are
+    /// matched to the sensors of the event by their geometry id, sensors without a fitted
+    /// state are left out of the fitted track. An event with the same name is replaced.
+    pub fn add_event<T: Transform + Plane + Identified>(&mut self, name: &str, event_number: u64, truth: &KFData<T>, fit: &SuperData) {
+        let hits = truth.sensors.iter()
+            .zip(truth.smear_hits.iter())
+            .map(|(sensor, hit)| PhoenixHit {
+                pos: array(&sensor.to_global(P3::new(hit.x, hit.y, 0.))),
+                kind: "Point",
+                geometry_id: sensor.geometry_id()
+            })
+            .collect();
+
+        let truth_track = PhoenixTrack {pos: truth_points(truth).iter().map(array).collect(), states: Vec::new()};
+
+        // the states are stored in the order they were fitted, not in the order of the sensors
+        let smoothed = &fit.smth;
+        let (points, states): (Vec<P3>, Vec<PhoenixTrackState>) = truth.sensors.iter()
+            .filter_map(|sensor| {
+                let index = smoothed.index_of(sensor.geometry_id())?;
+                let state = smoothed.state_vec.get(index)?;
+                let covariance = smoothed.cov_mat.get(index)?;
+
+                let point = sensor.to_global(P3::new(state[eLOC_0], state[eLOC_1], 0.));
+
+                let mut rows = [[0.; 5]; 5];
+                for (i, row) in rows.iter_mut().enumerate() {
+                    for (j, value) in row.iter_mut().enumerate() {
+                        *value = covariance[(i, j)];
+                    }
+                }
+
+                let track_state = PhoenixTrackState {
+                    geometry_id: sensor.geometry_id(),
+                    pos: array(&point),
+                    params: [state[eLOC_0], state[eLOC_1], state[ePHI], state[eTHETA], state[eQOP]],
+                    covariance: rows
+                };
+
+                Some((point, track_state))
+            })
+            .unzip();
+
+        let fitted_track = PhoenixTrack {pos: points.iter().map(array).collect(), states};
+
+        let mut event = PhoenixEvent {
+            event_number,
+            run_number: self.run_number,
+            hits: BTreeMap::new(),
+            tracks: BTreeMap::new()
+        };
+        event.hits.insert(String::from("Measurements"), hits);
+        event.tracks.insert(String::from("Truth"), vec![truth_track]);
+        event.tracks.insert(String::from("Fitted"), vec![fitted_track]);
+
+        self.events.insert(name.to_string(), event);
+    }
+
+    /// Fetches an event by name
+    pub fn event(&self, name: &str) -> Option<&PhoenixEvent> {
+        self.events.get(name)
+    }
+
+    /// Contents of the event data file
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.events)
+    }
+
+    /// Writes the event data file to `path`
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        fs::write(path, self.to_json()?)
+    }
+}
+
+fn array(point: &P3) -> [Real; 3] {
+    [point.x, point.y, point.z]
+}
@@ -0,0 +1,347 @@
+use nalgebra as na;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::angles::Angles;
+use super::utils::SuperData;
+use super::super::geometry::alignment::{Aligned, AlignmentDelta, AlignmentStore};
+use super::super::geometry::tracking_geometry::TrackingGeometry;
+use super::super::geometry::surface::Surface;
+use super::super::geometry::traits::{Transform, Plane, Identified};
+use super::super::geometry::GeometryId;
+use super::super::config::*;
+use super::super::error::*;
+
+type Mat2x4 = na::MatrixMN<Real, U2, U4>;
+type Mat2x6 = na::MatrixMN<Real, U2, U6>;
+type Mat4x6 = na::MatrixMN<Real, U4, U6>;
+type Vec4 = na::Vector4<Real>;
+
+/// Step used for the numerical derivatives of the predicted hits
+const DERIVATIVE_STEP: Real = 1e-6;
+
+/// Correction found for one surface, with the covariance of the six alignment parameters
+/// (translation x, y, z then rotation x, y, z, as in `AlignmentDelta`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignmentCorrection {
+    pub delta: AlignmentDelta,
+    pub covariance: Mat6
+}
+
+impl AlignmentCorrection {
+    /// Standard deviation of each alignment parameter
+    pub fn uncertainty(&self) -> AlignmentDelta {
+        let sigma = self.covariance.diagonal().map(Real::sqrt);
+        AlignmentDelta::new(Vec3::new(sigma[0], sigma[1], sigma[2]), Vec3::new(sigma[3], sigma[4], sigma[5]))
+    }
+}
+
+/// Result of `GlobalAlignment::solve`
+#[derive(Debug, Clone)]
+pub struct AlignmentSolution {
+    corrections: BTreeMap<GeometryId, AlignmentCorrection>,
+    /// chi squared of the refitted tracks at the nominal placement
+    pub chi_squared_before: Real,
+    /// chi squared of the refitted tracks after the corrections
+    pub chi_squared_after: Real,
+    /// number of degrees of freedom after the corrections
+    pub ndf: usize
+}
+
+impl AlignmentSolution {
+    pub fn get(&self, id: GeometryId) -> Option<&AlignmentCorrection> {
+        self.corrections.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (GeometryId, &AlignmentCorrection)> {
+        self.corrections.iter().map(|(id, correction)| (*id, correction))
+    }
+
+    pub fn len(&self) -> usize {
+        self.corrections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.corrections.is_empty()
+    }
+
+    /// The corrections as an `AlignmentStore` that can be applied to the nominal geometry
+    pub fn to_store(&self) -> AlignmentStore {
+        let mut store = AlignmentStore::new();
+        self.corrections.iter().for_each(|(id, correction)| {store.insert(*id, correction.delta);});
+        store
+    }
+}
+
+
+/// Track based alignment with a global chi squared fit (as done by Millepede). Every fitted
+/// track is refitted as a straight line with four local parameters (the position on its
+/// first surface and the two slopes in the frame of that surface) together with six alignment parameters for each
+/// surface. The local parameters of each track are eliminated as the track is added, so the
+/// global linear system only grows with the number of surfaces.
+///
+/// The corrections are linearized around the placement in the geometry. For large
+/// misalignments apply the solution and align again.
+#[derive(Debug, Clone)]
+pub struct GlobalAlignment<'a> {
+    geometry: &'a TrackingGeometry,
+    fixed: BTreeSet<GeometryId>,
+
+    // blocks of the global linear system, only the pairs of surfaces that share a track
+    matrix: BTreeMap<(GeometryId, GeometryId), Mat6>,
+    vector: BTreeMap<GeometryId, Vec6>,
+    chi_squared: Real,
+    measurements: usize,
+    tracks: usize
+}
+
+impl <'a> GlobalAlignment<'a> {
+
+    pub fn new(geometry: &'a TrackingGeometry) -> Self {
+        GlobalAlignment {
+            geometry,
+            fixed: BTreeSet::new(),
+            matrix: BTreeMap::new(),
+            vector: BTreeMap::new(),
+            chi_squared: 0.,
+            measurements: 0,
+            tracks: 0
+        }
+    }
+
+    /// Keeps a surface at its placement. At least a few reference surfaces need to be fixed,
+    /// otherwise moving the whole detector together with the tracks does not change the
+    /// chi squared and the system can not be solved. Has to be called before adding tracks.
+    pub fn fix(&mut self, id: GeometryId) {
+        self.fixed.insert(id);
+    }
+
+    pub fn is_fixed(&self, id: GeometryId) -> bool {
+        self.fixed.contains(&id)
+    }
+
+    /// Number of tracks added so far
+    pub fn tracks(&self) -> usize {
+        self.tracks
+    }
+
+    /// Adds a fitted track. The measurements are recovered from the smoothed residuals and
+    /// state vectors of `track`, `covariances` are the covariances of those measurements.
+    /// The track needs at least two measurements and the ids of its surfaces (see
+    /// `SuperData::set_geometry_ids`). Returns `Err(AlignmentError::LengthMismatch)` if the
+    /// track data and covariances are not the same length, and
+    /// `Err(AlignmentError::NoFixedSurface)` if no surface was fixed yet. Tracks that return an
+    /// error are not added.
+    pub fn add_track(&mut self, track: &SuperData, covariances: &[Mat2]) -> Result<(), Error> {
+        let smth = &track.smth;
+        let length = smth.state_vec.len();
+
+        if (smth.res_vec.len() != length) || (smth.geometry_ids.len() != length) || (covariances.len() != length) {
+            return Err(AlignmentError::LengthMismatch.into())
+        }
+
+        if self.fixed.is_empty() {
+            return Err(AlignmentError::NoFixedSurface.into())
+        }
+
+        let surfaces = smth.geometry_ids.iter()
+            .map(|id| self.geometry.surface(*id).ok_or(SensorError::UnknownGeometryId(*id)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (reference, parameters) = StraightTrack::new(surfaces[0], &smth.state_vec[0])?;
+
+        let mut local_matrix = Mat4::zeros();
+        let mut local_vector = Vec4::zeros();
+        let mut mixed = BTreeMap::<GeometryId, Mat4x6>::new();
+        let mut chi_squared = 0.;
+
+        // blocks of this track, only added to the global system once the track is accepted
+        let mut matrix = BTreeMap::<(GeometryId, GeometryId), Mat6>::new();
+        let mut vector = BTreeMap::<GeometryId, Vec6>::new();
+
+        for (i, surface) in surfaces.iter().enumerate() {
+            let state = &smth.state_vec[i];
+            let measurement = smth.res_vec[i] + Vec2::new(state[eLOC_0], state[eLOC_1]);
+            let weight = covariances[i].try_inverse().ok_or(MatrixError::NonInvertible)?;
+
+            let predicted = reference.predicted_hit(*surface, &parameters)?;
+            let residual = surface.align_measurement(&measurement, &predicted.coords) - predicted.coords;
+
+            let local = reference.local_derivatives(surface, &parameters)?;
+            local_matrix += local.transpose() * weight * local;
+            local_vector += local.transpose() * weight * residual;
+            chi_squared += (residual.transpose() * weight * residual)[0];
+
+            let id = surface.geometry_id();
+            if self.fixed.contains(&id) {
+                continue
+            }
+
+            let global = reference.alignment_derivatives(surface, &parameters)?;
+
+            *matrix.entry((id, id)).or_insert_with(Mat6::zeros) += global.transpose() * weight * global;
+            *vector.entry(id).or_insert_with(Vec6::zeros) += global.transpose() * weight * residual;
+            *mixed.entry(id).or_insert_with(Mat4x6::zeros) += local.transpose() * weight * global;
+        }
+
+        // eliminate the local parameters of the track
+        let local_inverse = local_matrix.try_inverse().ok_or(MatrixError::NonInvertible)?;
+
+        for (id_a, block_a) in &mixed {
+            let projected = block_a.transpose() * local_inverse;
+
+            for (id_b, block_b) in &mixed {
+                *matrix.entry((*id_a, *id_b)).or_insert_with(Mat6::zeros) -= projected * block_b;
+            }
+
+            *vector.entry(*id_a).or_insert_with(Vec6::zeros) -= projected * local_vector;
+        }
+
+        for (ids, block) in matrix {
+            *self.matrix.entry(ids).or_insert_with(Mat6::zeros) += block;
+        }
+        for (id, block) in vector {
+            *self.vector.entry(id).or_insert_with(Vec6::zeros) += block;
+        }
+
+        self.chi_squared += chi_squared - (local_vector.transpose() * local_inverse * local_vector)[0];
+        self.measurements += length;
+        self.tracks += 1;
+
+        Ok(())
+    }
+
+    /// Solves the global linear system for the corrections of every surface that is not
+    /// fixed and has at least one hit. Returns `Err(MatrixError::NonInvertible)` if the
+    /// tracks do not constrain all the alignment parameters.
+    pub fn solve(&self) -> Result<AlignmentSolution, MatrixError> {
+        let free = self.vector.keys().collect::<Vec<_>>();
+        let rows = free.iter().enumerate().map(|(i, id)| (**id, 6 * i)).collect::<BTreeMap<_, _>>();
+        let size = 6 * free.len();
+
+        let mut matrix = na::DMatrix::zeros(size, size);
+        let mut vector = na::DVector::zeros(size);
+
+        for ((id_a, id_b), block) in &self.matrix {
+            matrix.fixed_slice_mut::<U6, U6>(rows[id_a], rows[id_b]).copy_from(block);
+        }
+        for (id, block) in &self.vector {
+            vector.fixed_slice_mut::<U6, U1>(rows[id], 0).copy_from(block);
+        }
+
+        let covariance = matrix.cholesky().ok_or(MatrixError::NonInvertible)?.inverse();
+        let solution = &covariance * &vector;
+
+        let corrections = free.iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let delta = AlignmentDelta::new(
+                    Vec3::new(solution[6 * i], solution[6 * i + 1], solution[6 * i + 2]),
+                    Vec3::new(solution[6 * i + 3], solution[6 * i + 4], solution[6 * i + 5])
+                );
+                let covariance = covariance.fixed_slice::<U6, U6>(6 * i, 6 * i).into_owned();
+
+                (**id, AlignmentCorrection {delta, covariance})
+            })
+            .collect();
+
+        let dof = 2 * self.measurements;
+        let parameters = (4 * self.tracks) + size;
+
+        Ok(AlignmentSolution {
+            corrections,
+            chi_squared_before: self.chi_squared,
+            chi_squared_after: self.chi_squared - vector.dot(&solution),
+            ndf: dof.saturating_sub(parameters)
+        })
+    }
+}
+
+
+/// Parametrization of the straight tracks that are refitted: the local position on the first
+/// surface of the track and the slopes of the direction along the local x and y axes of that
+/// surface. Unlike (phi, theta) this has no singularity for tracks along the z axis.
+struct StraightTrack<'s> {
+    reference: &'s Surface,
+    axes: Mat3      // local x, local y and the local z axis pointing along the track
+}
+
+impl <'s> StraightTrack<'s> {
+
+    /// Parametrization on `reference` and the parameters of a (phi, theta) state on it
+    fn new(reference: &'s Surface, state: &Vec5) -> Result<(Self, Vec4), SensorError> {
+        let rotation = reference.rotation_to_global().fixed_slice::<U3, U3>(0, 0).into_owned();
+        let direction = Angles::new_from_angles(state[ePHI], state[eTHETA]).direction;
+        let local = rotation.transpose() * direction;
+
+        if local.z.abs() < Real::EPSILON {
+            return Err(SensorError::InvalidDirection(direction))
+        }
+
+        let mut axes = rotation;
+        axes.set_column(2, &(rotation.column(2) * local.z.signum()));
+
+        let parameters = Vec4::new(state[eLOC_0], state[eLOC_1], local.x / local.z.abs(), local.y / local.z.abs());
+
+        Ok((StraightTrack {reference, axes}, parameters))
+    }
+
+    /// Local position where the track with `parameters` crosses a sensor
+    fn predicted_hit<T: Transform + Plane>(&self, sensor: &T, parameters: &Vec4) -> Result<P2, SensorError> {
+        let position = self.reference.to_global(P3::new(parameters[0], parameters[1], 0.));
+        let direction = self.axes * Vec3::new(parameters[2], parameters[3], 1.);
+
+        let normal = sensor.plane_normal_vec();
+        let denominator = normal.dot(&direction);
+
+        if denominator.abs() < Real::EPSILON {
+            return Err(SensorError::InvalidDirection(direction))
+        }
+
+        let path_length = (sensor.global_center() - position).dot(normal) / denominator;
+
+        Ok(sensor.to_local(position + (direction * path_length)))
+    }
+
+    /// Derivatives of the predicted hit with respect to the track parameters
+    fn local_derivatives(&self, sensor: &Surface, parameters: &Vec4) -> Result<Mat2x4, SensorError> {
+        let mut derivatives = Mat2x4::zeros();
+
+        for k in 0..4 {
+            let mut step = Vec4::zeros();
+            step[k] = DERIVATIVE_STEP;
+
+            let forward = self.predicted_hit(sensor, &(parameters + step))?;
+            let backward = self.predicted_hit(sensor, &(parameters - step))?;
+            let difference = sensor.align_measurement(&forward.coords, &backward.coords) - backward.coords;
+
+            derivatives.set_column(k, &(difference / (2. * DERIVATIVE_STEP)));
+        }
+
+        Ok(derivatives)
+    }
+
+    /// Derivatives of the predicted hit with respect to the alignment parameters of the sensor.
+    /// The track parameters are given on the nominal reference surface, so they do not change
+    /// when the sensor moves.
+    fn alignment_derivatives(&self, sensor: &Surface, parameters: &Vec4) -> Result<Mat2x6, SensorError> {
+        let mut derivatives = Mat2x6::zeros();
+
+        for k in 0..6 {
+            let moved = |sign: Real| {
+                let mut step = Vec6::zeros();
+                step[k] = DERIVATIVE_STEP * sign;
+
+                let delta = AlignmentDelta::new(Vec3::new(step[0], step[1], step[2]), Vec3::new(step[3], step[4], step[5]));
+                self.predicted_hit(&Aligned::new(sensor.clone(), delta), parameters)
+            };
+
+            let forward = moved(1.)?;
+            let backward = moved(-1.)?;
+            let difference = sensor.align_measurement(&forward.coords, &backward.coords) - backward.coords;
+
+            derivatives.set_column(k, &(difference / (2. * DERIVATIVE_STEP)));
+        }
+
+        Ok(derivatives)
+    }
+}
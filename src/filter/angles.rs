@@ -1,4 +1,5 @@
 use super::super::config::*;
+pub use super::super::geometry::utils::wrap_phi;
 
 // TODO: Options enums for new values that are calculated: sin_phi_over_cos_theta... etc
 #[derive(PartialOrd, PartialEq, Debug)]
@@ -14,13 +15,15 @@ pub struct Angles{
 }
 
 impl Angles {
+    /// Angles of a unit direction. Phi of a direction along the global z axis is not defined,
+    /// it is taken to be 0.
     pub fn new_from_unit_direction(tx: Real, ty: Real, tz: Real) -> Self {
         let cos_theta = tz;
-        let sin_theta = ((tx* tx) + (ty*ty)).sqrt();
+        let sin_theta = tx.hypot(ty);
         
-        let inverse_sin_theta = 1./sin_theta;
-        let cos_phi = tx * inverse_sin_theta;
-        let sin_phi = ty * inverse_sin_theta;
+        let (cos_phi, sin_phi) =
+            if sin_theta > 0. {(tx / sin_theta, ty / sin_theta)}
+            else {(1., 0.)};
 
         
         Angles {
@@ -56,4 +59,55 @@ impl Angles {
             tz: tz
         }
     }
-}
\ No newline at end of file
+
+    pub fn tx(&self) -> Real {
+        self.tx
+    }
+
+    pub fn ty(&self) -> Real {
+        self.ty
+    }
+
+    pub fn tz(&self) -> Real {
+        self.tz
+    }
+
+    /// Whether the direction is too close to the global z axis for phi to be defined
+    pub fn is_axial(&self) -> bool {
+        self.sin_theta.abs() < SIN_THETA_EPSILON
+    }
+
+    /// sin(theta) kept at least `SIN_THETA_EPSILON` away from 0, for the derivatives of phi and
+    /// theta which divide by it
+    pub fn guarded_sin_theta(&self) -> Real {
+        if self.is_axial() {SIN_THETA_EPSILON.copysign(self.sin_theta)}
+        else {self.sin_theta}
+    }
+}
+
+/// Difference `a - b` of two state vectors. Phi is periodic, the phi difference is the
+/// shortest turn from `b` to `a` in (-pi, pi].
+pub fn state_difference(a: &Vec5, b: &Vec5) -> Vec5 {
+    let mut difference = a - b;
+    difference[ePHI] = wrap_phi(difference[ePHI]);
+    difference
+}
+
+/// Brings the angles of a state vector into range: phi into (-pi, pi] and theta into [0, pi].
+/// A theta beyond a pole is the direction on the other side of the pole, so theta is reflected
+/// and phi turned by pi.
+pub fn normalize_state(state: &Vec5) -> Vec5 {
+    let mut normalized = *state;
+
+    let theta = state[eTHETA].rem_euclid(2. * PI);
+    if theta > PI {
+        normalized[eTHETA] = 2. * PI - theta;
+        normalized[ePHI] += PI;
+    }
+    else {
+        normalized[eTHETA] = theta;
+    }
+
+    normalized[ePHI] = wrap_phi(normalized[ePHI]);
+    normalized
+}
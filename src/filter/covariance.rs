@@ -0,0 +1,271 @@
+use super::super::config::*;
+use super::super::error::SensorError;
+use super::super::geometry::traits::{Transform, Plane};
+use super::angles::Angles;
+use super::jacobian::{local_to_global_jac, global_to_local_jac};
+
+/*
+
+    Transformations of covariance matrices between the bound parameters of a surface
+    (loc0, loc1, phi, theta, q/p) and the free parameters (x, y, z, t, tx, ty, tz, q/p).
+    Bound parameters do not carry time, so the time row / column of free covariances made
+    here are zero.
+
+    Three kinds of bound frames are supported:
+        - planar sensors (anything that is `Transform + Plane`)
+        - the curvilinear frame: a plane through the track perpendicular to its direction
+        - the perigee frame: (d0, z0) of the point of closest approach to a line along the
+          global z axis through a reference point
+
+*/
+
+fn local_position(state_vec: &Vec5) -> P2 {
+    P2::new(state_vec[eLOC_0], state_vec[eLOC_1])
+}
+
+/// Jacobian from the bound parameters on `sensor` to the free parameters
+pub fn bound_to_free_jacobian<T: Transform + Plane>(sensor: &T, state_vec: &Vec5) -> Mat8x5 {
+    let angles = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]);
+    local_to_global_jac(&angles, &sensor.local_to_global_derivative(&local_position(state_vec)))
+}
+
+/// Jacobian from the free parameters to the bound parameters on `sensor`. A change of the free
+/// position moves the track along its direction back onto the sensor, so the jacobian is
+/// undefined for tracks parallel to the sensor. It is also undefined for tracks along the global
+/// z axis, where any change of the direction changes phi arbitrarily.
+pub fn free_to_bound_jacobian<T: Transform + Plane>(sensor: &T, state_vec: &Vec5) -> Result<Mat5x8, SensorError> {
+    let angles = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]);
+    let normal = sensor.plane_normal_vec();
+    let normal_direction = normal.dot(&angles.direction);
+
+    if (normal_direction.abs() < Real::EPSILON) || angles.is_axial() {
+        return Err(SensorError::InvalidDirection(angles.direction))
+    }
+
+    // derivative of the path length back to the sensor with respect to the free position
+    let path_derivative = normal.transpose() / (-normal_direction);
+    let position_derivative = sensor.global_to_local_derivative(&local_position(state_vec)) * (Mat3::identity() + (angles.direction * path_derivative));
+
+    Ok(global_to_local_jac(&angles, &position_derivative))
+}
+
+/// Free covariance of the bound state `state_vec` on `sensor`
+pub fn bound_to_free<T: Transform + Plane>(sensor: &T, state_vec: &Vec5, covariance: &Mat5) -> Mat8 {
+    let jacobian = bound_to_free_jacobian(sensor, state_vec);
+    jacobian * covariance * jacobian.transpose()
+}
+
+/// Bound covariance on `sensor` of a free covariance at the bound state `state_vec`
+pub fn free_to_bound<T: Transform + Plane>(sensor: &T, state_vec: &Vec5, covariance: &Mat8) -> Result<Mat5, SensorError> {
+    let jacobian = free_to_bound_jacobian(sensor, state_vec)?;
+    Ok(jacobian * covariance * jacobian.transpose())
+}
+
+/// Expresses the bound state `state_vec` on `start` in the frame of `end`. Both surfaces
+/// have to go through the position of the state, otherwise `SensorError::NotOnSurface` is
+/// returned. Returns the state vector and covariance on `end`.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::covariance;
+/// use kalman_rs::geometry::Rectangle;
+///
+/// let translation = Mat4::new_translation(&Vec3::new(0., 0., 1.));
+/// let start = Rectangle::new(5., 5., translation, Mat4::identity()).unwrap();
+/// let end = Rectangle::new(5., 5., translation, Mat4::from_axis_angle(&Vec3::z_axis(), PI / 2.)).unwrap();
+///
+/// let state = Vec5::new(1., 0., 0., 0.5, 1.);
+/// let covariance = Mat5::from_diagonal(&Vec5::new(4., 1., 0.1, 0.1, 0.1));
+///
+/// // the local axes of `end` are turned by 90 degrees
+/// let (end_state, end_covariance) = covariance::bound_to_bound(&start, &end, &state, &covariance).unwrap();
+///
+/// assert!((end_state[eLOC_1] + 1.).abs() < 1e-12);
+/// assert!((end_covariance[(eLOC_0, eLOC_0)] - 1.).abs() < 1e-12);
+/// assert!((end_covariance[(eLOC_1, eLOC_1)] - 4.).abs() < 1e-12);
+/// ```
+pub fn bound_to_bound<T: Transform + Plane, U: Transform + Plane>(
+    start: &T,
+    end: &U,
+    state_vec: &Vec5,
+    covariance: &Mat5
+    ) -> Result<(Vec5, Mat5), SensorError> {
+
+    let global = start.to_global(P3::new(state_vec[eLOC_0], state_vec[eLOC_1], 0.));
+
+    let normal = end.plane_normal_vec();
+    if ((global - end.global_center()).dot(normal) / normal.norm()).abs() > DOT_PRODUCT_EPSILON {
+        return Err(SensorError::NotOnSurface(global))
+    }
+
+    let local = end.to_local(global);
+    let mut end_state = *state_vec;
+    end_state[eLOC_0] = local.x;
+    end_state[eLOC_1] = local.y;
+
+    let jacobian = free_to_bound_jacobian(end, &end_state)? * bound_to_free_jacobian(start, state_vec);
+
+    Ok((end_state, jacobian * covariance * jacobian.transpose()))
+}
+
+
+/// Jacobian of the free parameters transported a distance `distance` along a straight line.
+/// Only the position changes, by `distance` times the change of the direction.
+pub fn free_transport_jacobian(distance: Real) -> Mat8 {
+    let mut jacobian = Mat8::identity();
+    jacobian.fixed_slice_mut::<U3, U3>(0, 4).copy_from(&(Mat3::identity() * distance));
+    jacobian
+}
+
+
+/// Axes (U, V) of the curvilinear frame of a track with direction (phi, theta). U lies in the
+/// global x-y plane, V completes the right handed frame (U, V, direction). Close to the global
+/// z axis, where U would turn with the badly defined phi, U is perpendicular to the global x
+/// axis instead.
+pub fn curvilinear_axes(phi: Real, theta: Real) -> (Vec3, Vec3) {
+    let angles = Angles::new_from_angles(phi, theta);
+
+    if angles.cos_theta.abs() > CURVILINEAR_PROJECTION_TOLERANCE {
+        let u = Vec3::x().cross(&angles.direction).normalize();
+        return (u, angles.direction.cross(&u))
+    }
+
+    let u = Vec3::new(-angles.sin_phi, angles.cos_phi, 0.);
+    let v = Vec3::new(-angles.cos_phi * angles.cos_theta, -angles.sin_phi * angles.cos_theta, angles.sin_theta);
+
+    (u, v)
+}
+
+/// Jacobian from the curvilinear parameters of a track with direction (phi, theta) to the free
+/// parameters
+pub fn curvilinear_to_free_jacobian(phi: Real, theta: Real) -> Mat8x5 {
+    let (u, v) = curvilinear_axes(phi, theta);
+    local_to_global_jac(&Angles::new_from_angles(phi, theta), &Mat3x2::from_columns(&[u, v]))
+}
+
+/// Jacobian from the free parameters to the curvilinear parameters of a track with direction
+/// (phi, theta). The curvilinear plane is perpendicular to the track, so moving along the track
+/// does not change the local position.
+pub fn free_to_curvilinear_jacobian(phi: Real, theta: Real) -> Mat5x8 {
+    let (u, v) = curvilinear_axes(phi, theta);
+    global_to_local_jac(&Angles::new_from_angles(phi, theta), &Mat2x3::from_rows(&[u.transpose(), v.transpose()]))
+}
+
+/// Free covariance of a curvilinear covariance for a track with direction (phi, theta)
+pub fn curvilinear_to_free(phi: Real, theta: Real, covariance: &Mat5) -> Mat8 {
+    let jacobian = curvilinear_to_free_jacobian(phi, theta);
+    jacobian * covariance * jacobian.transpose()
+}
+
+/// Curvilinear covariance of a free covariance for a track with direction (phi, theta)
+pub fn free_to_curvilinear(phi: Real, theta: Real, covariance: &Mat8) -> Mat5 {
+    let jacobian = free_to_curvilinear_jacobian(phi, theta);
+    jacobian * covariance * jacobian.transpose()
+}
+
+/// Curvilinear covariance at the position of the bound state `state_vec` on `sensor`
+pub fn bound_to_curvilinear<T: Transform + Plane>(sensor: &T, state_vec: &Vec5, covariance: &Mat5) -> Mat5 {
+    let jacobian = free_to_curvilinear_jacobian(state_vec[ePHI], state_vec[eTHETA]) * bound_to_free_jacobian(sensor, state_vec);
+    jacobian * covariance * jacobian.transpose()
+}
+
+
+/// Position and direction of the perigee state `state_vec` = (d0, z0, phi, theta, q/p) around
+/// `reference`. d0 is measured along `global z x direction`.
+pub fn perigee_to_global(reference: &P3, state_vec: &Vec5) -> (P3, Vec3) {
+    let angles = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]);
+    let d0_axis = Vec3::new(-angles.sin_phi, angles.cos_phi, 0.);
+
+    let position = reference + (d0_axis * state_vec[eLOC_0]) + (Vec3::z() * state_vec[eLOC_1]);
+
+    (position, angles.direction)
+}
+
+/// Perigee state of the straight track through `position` along `direction` around the line
+/// parallel to the global z axis through `reference`. Tracks parallel to the line have no
+/// point of closest approach.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::covariance;
+///
+/// // a track along global x, passing 2 units from the beamline
+/// let state = covariance::global_to_perigee(&P3::origin(), &P3::new(5., 2., 1.), &Vec3::x(), 1.).unwrap();
+///
+/// assert!((state[eLOC_0] - 2.).abs() < 1e-12);
+/// assert!((state[eLOC_1] - 1.).abs() < 1e-12);
+/// assert!(state[ePHI].abs() < 1e-12);
+/// ```
+pub fn global_to_perigee(reference: &P3, position: &P3, direction: &Vec3, qop: Real) -> Result<Vec5, SensorError> {
+    let direction = direction.normalize();
+    let transverse = direction - (Vec3::z() * direction.z);
+    let sin_theta_squared = transverse.norm_squared();
+
+    if sin_theta_squared < Real::EPSILON {
+        return Err(SensorError::InvalidDirection(direction))
+    }
+
+    // point of closest approach relative to the reference
+    let relative = position - reference;
+    let closest = relative - (direction * (relative.dot(&transverse) / sin_theta_squared));
+
+    let phi = direction.y.atan2(direction.x);
+    let d0_axis = Vec3::new(-phi.sin(), phi.cos(), 0.);
+
+    Ok(Vec5::new(closest.dot(&d0_axis), closest.z, phi, direction.z.acos(), qop))
+}
+
+/// Jacobian from the perigee parameters to the free parameters at the point of closest approach
+pub fn perigee_to_free_jacobian(state_vec: &Vec5) -> Mat8x5 {
+    let angles = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]);
+    let d0_axis = Vec3::new(-angles.sin_phi, angles.cos_phi, 0.);
+
+    let mut jacobian = local_to_global_jac(&angles, &Mat3x2::from_columns(&[d0_axis, Vec3::z()]));
+
+    // the d0 axis turns with the direction of the track
+    jacobian[(0, ePHI)] = -state_vec[eLOC_0] * angles.cos_phi;
+    jacobian[(1, ePHI)] = -state_vec[eLOC_0] * angles.sin_phi;
+
+    jacobian
+}
+
+/// Jacobian from the free parameters at the point of closest approach to the perigee parameters.
+/// Changes of the free parameters move the point of closest approach along the track.
+pub fn free_to_perigee_jacobian(state_vec: &Vec5) -> Result<Mat5x8, SensorError> {
+    let angles = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]);
+    let sin_theta_squared = angles.sin_theta * angles.sin_theta;
+
+    if sin_theta_squared < Real::EPSILON {
+        return Err(SensorError::InvalidDirection(angles.direction))
+    }
+
+    let d0_axis = Vec3::new(-angles.sin_phi, angles.cos_phi, 0.);
+
+    // derivatives of the path length to the new point of closest approach
+    let path_position = (angles.direction - (Vec3::z() * angles.cos_theta)).transpose() / (-sin_theta_squared);
+    let path_direction = d0_axis.transpose() * (-state_vec[eLOC_0] / sin_theta_squared);
+
+    // moving along the track only changes z0 since the d0 axis is perpendicular to the track
+    let position_derivative = Mat2x3::from_rows(&[d0_axis.transpose(), Vec3::z().transpose() + (path_position * angles.cos_theta)]);
+
+    let mut jacobian = global_to_local_jac(&angles, &position_derivative);
+    let mut z0_direction = jacobian.fixed_slice_mut::<U1, U3>(eLOC_1, 4);
+    z0_direction += path_direction * angles.cos_theta;
+
+    Ok(jacobian)
+}
+
+/// Free covariance of the perigee state `state_vec`
+pub fn perigee_to_free(state_vec: &Vec5, covariance: &Mat5) -> Mat8 {
+    let jacobian = perigee_to_free_jacobian(state_vec);
+    jacobian * covariance * jacobian.transpose()
+}
+
+/// Perigee covariance of a free covariance at the point of closest approach of the perigee
+/// state `state_vec`
+pub fn free_to_perigee(state_vec: &Vec5, covariance: &Mat8) -> Result<Mat5, SensorError> {
+    let jacobian = free_to_perigee_jacobian(state_vec)?;
+    Ok(jacobian * covariance * jacobian.transpose())
+}
@@ -0,0 +1,192 @@
+use super::super::config::*;
+use super::super::error::{Error, SensorError};
+use super::super::geometry::bounds::BoundaryCheck;
+use super::super::geometry::intersection::{Intersect, IntersectionStatus, NavigationDirection};
+use super::super::geometry::material::MaterialSlab;
+use super::super::geometry::navigator::Navigator;
+use super::super::geometry::tracking_geometry::TrackingGeometry;
+use super::super::geometry::traits::{Transform, Plane};
+use super::angles::Angles;
+use super::covariance;
+use super::utils::Data;
+
+/// Configuration of the extrapolation of fitted states to other surfaces
+#[derive(Debug, Clone)]
+pub struct ExtrapolationOptions {
+    /// tolerance used to decide if the track hits the target inside of its bounds
+    pub boundary_check: BoundaryCheck,
+    /// extrapolate along or against the momentum
+    pub navigation: NavigationDirection,
+    /// add multiple scattering in the material crossed on the way to the target
+    pub multiple_scattering: bool
+}
+
+impl Default for ExtrapolationOptions {
+    fn default() -> Self {
+        ExtrapolationOptions {boundary_check: BoundaryCheck::Strict, navigation: NavigationDirection::Forward, multiple_scattering: false}
+    }
+}
+
+/// Parameters of a track propagated onto a target surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extrapolation {
+    /// bound state vector on the target
+    pub state_vec: Vec5,
+    pub cov_mat: Mat5,
+    /// distance travelled in the navigation direction
+    pub path_length: Real,
+    pub global: P3,
+    /// whether the track hits the target inside of its bounds
+    pub status: IntersectionStatus,
+    /// signed distance to the closest edge of the target, negative inside of the bounds
+    pub distance_to_boundary: Real
+}
+
+impl Extrapolation {
+    pub fn local(&self) -> P2 {
+        P2::new(self.state_vec[eLOC_0], self.state_vec[eLOC_1])
+    }
+
+    pub fn inside(&self) -> bool {
+        self.status == IntersectionStatus::Inside
+    }
+}
+
+
+/// Width of the scattering angle distribution projected on a plane (Highland formula) for a
+/// particle with charge over momentum `qop` (in 1 / GeV) crossing `slab`. The particle is
+/// assumed to move at the speed of light.
+pub fn highland_angle(slab: &MaterialSlab, qop: Real) -> Real {
+    let thickness = slab.thickness_in_x0();
+
+    if thickness <= 0. {
+        return 0.
+    }
+
+    0.0136 * qop.abs() * thickness.sqrt() * (1. + 0.038 * thickness.ln()).max(0.)
+}
+
+/// Propagates the bound state `state_vec` on `start` along a straight line onto `target`.
+/// A track that hits the plane of the target outside of its bounds is still propagated, its
+/// `status` is `OutsideBounds`. Returns `SensorError::InvalidDirection` if the target can not
+/// be reached in the navigation direction.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::extrapolation::{self, ExtrapolationOptions};
+/// use kalman_rs::geometry::Rectangle;
+///
+/// let start = Rectangle::new(5., 5., Mat4::identity(), Mat4::identity()).unwrap();
+/// let target = Rectangle::new(2., 2., Mat4::new_translation(&Vec3::new(0., 0., 10.)), Mat4::identity()).unwrap();
+///
+/// let state = Vec5::new(0., 0., 0., (0.2 as Real).atan(), 1.);
+/// let result = extrapolation::extrapolate(&start, &state, &Mat5::identity(), &target, &ExtrapolationOptions::default()).unwrap();
+///
+/// // the track leaves the target through its edge at x = 1
+/// assert!((result.state_vec[eLOC_0] - 2.).abs() < 1e-12);
+/// assert!(!result.inside());
+/// assert!((result.distance_to_boundary - 1.).abs() < 1e-12);
+/// ```
+pub fn extrapolate<T: Transform + Plane, U: Transform + Plane>(
+    start: &T,
+    state_vec: &Vec5,
+    cov_mat: &Mat5,
+    target: &U,
+    options: &ExtrapolationOptions
+    ) -> Result<Extrapolation, SensorError> {
+
+    extrapolate_through(start, state_vec, cov_mat, target, &[], options)
+}
+
+/// Propagates state `index` of `data` (e.g. `&track.smth`) onto `target`. The sensor of the
+/// state is looked up in `geometry` with the ids of the data (see `SuperData::set_geometry_ids`),
+/// a state without an id returns `Err(SensorError::MissingGeometryId)`. With
+/// `multiple_scattering` the material of the surfaces of `geometry` crossed between the
+/// sensor and the target is included, the material of the sensor and the target are not.
+pub fn extrapolate_track<U: Transform + Plane>(
+    data: &Data,
+    index: usize,
+    geometry: &TrackingGeometry,
+    target: &U,
+    options: &ExtrapolationOptions
+    ) -> Result<Extrapolation, Error> {
+
+    let id = *data.geometry_ids.get(index).ok_or(SensorError::MissingGeometryId(index))?;
+    let start = geometry.surface(id).ok_or(SensorError::UnknownGeometryId(id))?;
+
+    let state_vec = &data.state_vec[index];
+    let cov_mat = &data.cov_mat[index];
+
+    let material =
+        if options.multiple_scattering {
+            let direction = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]).direction * options.navigation.sign();
+            let global = start.to_global(P3::new(state_vec[eLOC_0], state_vec[eLOC_1], 0.));
+
+            Navigator::new(geometry).crossings(&global, &direction).into_iter()
+                .filter(|crossing| crossing.geometry_id != id)
+                .filter_map(|crossing| Some((crossing.path_length, crossing.material?)))
+                .collect()
+        }
+        else {Vec::new()};
+
+    Ok(extrapolate_through(start, state_vec, cov_mat, target, &material, options)?)
+}
+
+/// Propagation onto `target` with scattering in `material`: slabs at a path length in the
+/// navigation direction, ordered by path length. Slabs beyond the target are ignored.
+fn extrapolate_through<T: Transform + Plane, U: Transform + Plane>(
+    start: &T,
+    state_vec: &Vec5,
+    cov_mat: &Mat5,
+    target: &U,
+    material: &[(Real, MaterialSlab)],
+    options: &ExtrapolationOptions
+    ) -> Result<Extrapolation, SensorError> {
+
+    let direction = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]).direction;
+    let start_global = start.to_global(P3::new(state_vec[eLOC_0], state_vec[eLOC_1], 0.));
+
+    let intersection =
+        match target.intersect_with_check(&start_global, &direction, options.navigation, &options.boundary_check).into_iter().find(|i| i.path_length >= 0.) {
+            Some(intersection) => intersection,
+            None => return Err(SensorError::InvalidDirection(direction))
+        };
+
+    let mut end_state = *state_vec;
+    end_state[eLOC_0] = intersection.local.x;
+    end_state[eLOC_1] = intersection.local.y;
+
+    // transport the free covariance from scatterer to scatterer, the distances along the
+    // momentum are negative when navigating backward
+    let sign = options.navigation.sign();
+    let mut free_cov = covariance::bound_to_free(start, state_vec, cov_mat);
+    let mut travelled = 0.;
+
+    let scatterers = material.iter()
+        .filter(|(path_length, _)| (*path_length > 0.) && (*path_length < intersection.path_length - DOT_PRODUCT_EPSILON));
+
+    for (path_length, slab) in scatterers {
+        let transport = covariance::free_transport_jacobian((path_length - travelled) * sign);
+        free_cov = transport * free_cov * transport.transpose();
+
+        // the direction is deflected perpendicular to itself
+        let angle = highland_angle(slab, state_vec[eQOP]);
+        let mut direction_cov = free_cov.fixed_slice_mut::<U3, U3>(4, 4);
+        direction_cov += (Mat3::identity() - (direction * direction.transpose())) * (angle * angle);
+
+        travelled = *path_length;
+    }
+
+    let transport = covariance::free_transport_jacobian((intersection.path_length - travelled) * sign);
+    free_cov = transport * free_cov * transport.transpose();
+
+    Ok(Extrapolation {
+        state_vec: end_state,
+        cov_mat: covariance::free_to_bound(target, &end_state, &free_cov)?,
+        path_length: intersection.path_length,
+        global: intersection.position,
+        status: intersection.status,
+        distance_to_boundary: target.distance_to_boundary(&intersection.local)
+    })
+}
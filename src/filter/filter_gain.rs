@@ -1,5 +1,51 @@
 use nalgebra as na;
 use super::super::config::*;
+use super::super::error::{FilterError, MatrixError};
+use super::parameters::BoundTrackParameters;
+use super::{angles, prediction};
+
+/// Parameters after the update with a measurement, with the filtered residual, its
+/// covariance and the chi squared increment
+#[derive(Debug, Clone)]
+pub struct FilteredParameters {
+    pub parameters: BoundTrackParameters,
+    pub residual_vec: Vec2,
+    pub residual_mat: Mat2,
+    pub chi_squared: Real
+}
+
+/// Updates `predicted` with a measurement of its local position and its covariance. Polar
+/// measurements have to be on the same side of phi = +-pi as the prediction (see
+/// `Transform::align_measurement`). Returns `Err(FilterError::MissingCovariance)` if the
+/// prediction has no covariance and `Err(FilterError::Matrix)` if a residual covariance can
+/// not be inverted.
+pub fn update(
+    predicted: &BoundTrackParameters,
+    measurement: &Vec2,
+    measurement_covariance: &Mat2
+    ) -> Result<FilteredParameters, FilterError> {
+
+    let pred_covariance = predicted.covariance().ok_or(FilterError::MissingCovariance)?;
+    let sensor_mapping_mat = BoundTrackParameters::projector();
+
+    let pred_residual_vec = prediction::residual_vec(measurement, &sensor_mapping_mat, predicted.vector());
+    let pred_residual_mat = prediction::residual_mat(measurement_covariance, &sensor_mapping_mat, pred_covariance);
+    let kalman_gain = pred_covariance * sensor_mapping_mat.transpose() * pred_residual_mat.try_inverse().ok_or(MatrixError::NonInvertible)?;
+
+    let state_vec = state_vector(predicted.vector(), &kalman_gain, measurement, &sensor_mapping_mat);
+    let covariance = covariance_matrix(&kalman_gain, &sensor_mapping_mat, pred_covariance);
+
+    let residual_vec = residual_vec(&sensor_mapping_mat, &kalman_gain, &pred_residual_vec);
+    let residual_mat = residual_mat(measurement_covariance, &sensor_mapping_mat, &covariance);
+    let chi_squared = (residual_vec.transpose() * residual_mat.try_inverse().ok_or(MatrixError::NonInvertible)? * residual_vec)[0];
+
+    Ok(FilteredParameters {
+        parameters: BoundTrackParameters::from_vector(predicted.surface(), state_vec, Some(covariance)),
+        residual_vec,
+        residual_mat,
+        chi_squared
+    })
+}
 
 
 pub fn state_vector( 
@@ -11,7 +57,9 @@ pub fn state_vector(
 
     let parens = measurement - (sensor_mapping_mat * pred_state_vec);
     let kalman_product = kalman_gain * parens;
-    return pred_state_vec + kalman_product;
+
+    // the update can move phi across pi or theta across a pole
+    angles::normalize_state(&(pred_state_vec + kalman_product))
 }
 
 
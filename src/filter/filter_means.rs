@@ -1,5 +1,6 @@
 use nalgebra as na;
 use super::super::config::*;
+use super::angles;
 
 pub fn state_vector(
     filt_covariance_mat : &Mat5,    // filt C
@@ -13,7 +14,7 @@ pub fn state_vector(
     let product_one = pred_covariance_mat.try_inverse().expect("could not invert pred cov mat") * pred_state_vec;
     let product_two = sensor_mapping_mat.transpose() * G * measurement_vec;
 
-    return filt_covariance_mat * (product_one + product_two)
+    angles::normalize_state(&(filt_covariance_mat * (product_one + product_two)))
 }
 
 
@@ -39,7 +40,7 @@ pub fn chi_squared_increment(
 
     let first_term = residual_vec.transpose() * G * residual_vec;
 
-    let second_term_3 = (state_vector - extrap_state_vector);
+    let second_term_3 = angles::state_difference(state_vector, extrap_state_vector);
     let second_term_2 = pred_covariance_mat.try_inverse().unwrap();
     let second_term_1 = second_term_3.transpose();
     
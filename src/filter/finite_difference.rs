@@ -0,0 +1,153 @@
+use std::fmt;
+
+use itertools::iproduct;
+
+use super::super::config::*;
+use super::super::error::SensorError;
+use super::super::geometry::bounds::BoundaryCheck;
+use super::super::geometry::intersection::NavigationDirection;
+use super::super::geometry::traits::{Transform, Plane};
+use super::{angles, jacobian, prediction};
+
+/// Names of the bound parameters, indexed like the state vector
+const PARAMETER_NAMES: [&str; 5] = ["loc0", "loc1", "phi", "theta", "q/p"];
+
+/// Configuration of the numerical transport jacobians
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiniteDifferenceOptions {
+    /// step of the central difference of each bound parameter, indexed like the state vector
+    pub steps: Vec5,
+    /// largest difference between an analytic and a numerical element accepted by the
+    /// comparison, relative to the element (absolute for elements smaller than 1)
+    pub tolerance: Real
+}
+
+impl Default for FiniteDifferenceOptions {
+    fn default() -> Self {
+        FiniteDifferenceOptions {steps: Vec5::new(1e-5, 1e-5, 1e-7, 1e-7, 1e-5), tolerance: 1e-5}
+    }
+}
+
+/// Analytic and numerical transport jacobians of the same step
+#[derive(Debug, Clone, PartialEq)]
+pub struct JacobianComparison {
+    pub analytic: Mat5,
+    pub numerical: Mat5,
+    pub tolerance: Real
+}
+
+impl JacobianComparison {
+    pub fn new(analytic: Mat5, numerical: Mat5, tolerance: Real) -> Self {
+        JacobianComparison {analytic, numerical, tolerance}
+    }
+
+    /// Difference of every element relative to the numerical one (absolute for elements
+    /// smaller than 1)
+    pub fn relative_difference(&self) -> Mat5 {
+        (self.analytic - self.numerical).zip_map(&self.numerical, |difference, numerical| difference.abs() / numerical.abs().max(1.))
+    }
+
+    /// Largest relative difference and its (row, column)
+    pub fn worst_element(&self) -> (Real, (usize, usize)) {
+        let difference = self.relative_difference();
+        let index = difference.iamax_full();
+
+        (difference[index], index)
+    }
+
+    /// Checks that every element is within the tolerance
+    pub fn agrees(&self) -> bool {
+        self.worst_element().0 <= self.tolerance
+    }
+
+    /// Elements beyond the tolerance as (row, column, analytic, numerical)
+    pub fn disagreements(&self) -> Vec<(usize, usize, Real, Real)> {
+        let difference = self.relative_difference();
+
+        iproduct!(0..5, 0..5)
+            .filter(|(row, column)| difference[(*row, *column)] > self.tolerance)
+            .map(|(row, column)| (row, column, self.analytic[(row, column)], self.numerical[(row, column)]))
+            .collect()
+    }
+}
+
+impl fmt::Display for JacobianComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (worst, (row, column)) = self.worst_element();
+        writeln!(f, "largest difference {} in d {} / d {} (tolerance {})", worst, PARAMETER_NAMES[row], PARAMETER_NAMES[column], self.tolerance)?;
+
+        for (row, column, analytic, numerical) in self.disagreements() {
+            writeln!(f, "d {} / d {}: analytic {}, numerical {}", PARAMETER_NAMES[row], PARAMETER_NAMES[column], analytic, numerical)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Bound to bound transport jacobian of `propagator` at `state_vec` from central differences
+/// with a step of `steps[i]` in parameter `i`. The propagator maps a bound state on the start
+/// surface to the bound state on the end surface, any error of the shifted propagations is
+/// returned. Differences of phi are taken across the pi boundary.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::finite_difference;
+///
+/// // loc0 moves with tan(theta) over a distance of 2
+/// let propagator = |state: &Vec5| Ok(Vec5::new(state[eLOC_0] + 2. * state[eTHETA].tan(), state[eLOC_1], state[ePHI], state[eTHETA], state[eQOP]));
+///
+/// let state = Vec5::new(0., 0., 0., 0.5, 1.);
+/// let jacobian = finite_difference::transport_jacobian(propagator, &state, &Vec5::repeat(1e-6)).unwrap();
+///
+/// assert!((jacobian[(eLOC_0, eTHETA)] - 2. / (0.5 as Real).cos().powi(2)).abs() < 1e-8);
+/// ```
+pub fn transport_jacobian<F>(propagator: F, state_vec: &Vec5, steps: &Vec5) -> Result<Mat5, SensorError>
+    where F: Fn(&Vec5) -> Result<Vec5, SensorError> {
+
+    let mut jacobian = Mat5::zeros();
+
+    for i in 0..5 {
+        let mut step = Vec5::zeros();
+        step[i] = steps[i];
+
+        let forward = propagator(&(state_vec + step))?;
+        let backward = propagator(&(state_vec - step))?;
+
+        jacobian.set_column(i, &(angles::state_difference(&forward, &backward) / (2. * steps[i])));
+    }
+
+    Ok(jacobian)
+}
+
+/// Straight line propagation of bound states from `start` onto the plane of `end`. Shifted
+/// states are not checked against the bounds of `end`.
+pub fn linear_propagator<'a, T: Transform + Plane>(
+    start: &'a T,
+    end: &'a T,
+    navigation: NavigationDirection
+    ) -> impl Fn(&Vec5) -> Result<Vec5, SensorError> + 'a {
+
+    move |state_vec| {
+        prediction::linear_state_vector_in_direction(start, end, state_vec, &BoundaryCheck::Absolute(Real::INFINITY), navigation)
+            .map(|(state_vec, _)| state_vec)
+    }
+}
+
+/// Compares `jacobian::linear_in_direction` from `start` to `end` at `state_vec` with the
+/// numerical jacobian of the straight line propagation
+pub fn check_linear<T: Transform + Plane>(
+    state_vec: &Vec5,
+    start: &T,
+    end: &T,
+    navigation: NavigationDirection,
+    options: &FiniteDifferenceOptions
+    ) -> Result<JacobianComparison, SensorError> {
+
+    let (_, distance) = prediction::linear_state_vector_in_direction(start, end, state_vec, &BoundaryCheck::Absolute(Real::INFINITY), navigation)?;
+
+    let analytic = jacobian::linear_in_direction(state_vec, distance, start, end, navigation);
+    let numerical = transport_jacobian(linear_propagator(start, end, navigation), state_vec, &options.steps)?;
+
+    Ok(JacobianComparison::new(analytic, numerical, options.tolerance))
+}
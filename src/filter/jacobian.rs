@@ -1,5 +1,8 @@
 use super::super::config;
 use super::super::geometry::traits::{Transform, Plane};
+use super::super::geometry::Straw;
+use super::super::geometry::intersection::NavigationDirection;
+use super::super::error::*;
 
 use config::*;
 use nalgebra as na;
@@ -17,51 +20,145 @@ pub fn linear<T: Transform + Plane>(
     end_sensor: &T
     ) -> Mat5{
 
+    linear_in_direction(prev_state_vec, distance, start_sensor, end_sensor, NavigationDirection::Forward)
+}
 
-    get_unchecked!{
-        prev_state_vec[ePHI] => phi,
-        prev_state_vec[eTHETA] => theta
-    }
+/// Calculate the jacobian between sensors for a linear case, where `distance` is the path
+/// length in the navigation direction. A `Backward` navigation transports the state against
+/// the direction of its momentum.
+pub fn linear_in_direction<T: Transform + Plane>(
+    prev_state_vec: &Vec5,
+    distance: Real,
+    start_sensor: &T,
+    end_sensor: &T,
+    navigation: NavigationDirection
+    ) -> Mat5{
+
+    // distance along the momentum
+    let distance = distance * navigation.sign();
 
 
     // struct containing all the required sin / cos of phi / theta
-    let mut angles = angles::Angles::new_from_angles(*phi, *theta);
+    let mut angles = angles::Angles::new_from_angles(prev_state_vec[ePHI], prev_state_vec[eTHETA]);
+
+    // local positions on both sensors are needed since the derivatives of non-cartesian
+    // local frames (discs) depend on where the track crosses the sensor
+    let start_local = P2::new(prev_state_vec[eLOC_0], prev_state_vec[eLOC_1]);
+    let start_global = start_sensor.to_global(P3::new(start_local.x, start_local.y, 0.));
+    let end_local = end_sensor.to_local(start_global + (angles.direction * distance));
+
+    // a change of the global position moves the track along its direction back onto the end
+    // sensor, which changes where it crosses the sensor
+    let normal = end_sensor.plane_normal_vec();
+    let path_derivative = normal.transpose() / (-normal.dot(&angles.direction));
 
-    // dbg!{&angles};
+    let start_derivative = start_sensor.local_to_global_derivative(&start_local);
+    let end_derivative = end_sensor.global_to_local_derivative(&end_local) * (Mat3::identity() + (angles.direction * path_derivative));
+
+    linear_from_derivatives(&mut angles, distance, &start_derivative, &Mat3x2::zeros(), &end_derivative, &Mat2x3::zeros())
+}
 
-    let loc_2_glob : Mat8x5 = local_to_global_jac(&angles, end_sensor.rotation_to_global() );
-    let glob_2_loc : Mat5x8= global_to_local_jac(&angles, start_sensor.rotation_to_local() ); 
 
-    let transport_jac: Mat8 = linear_transport_jac(&mut angles, distance);
+/// Calculate the jacobian between straws for a linear case. The local frame of a straw is
+/// defined by the track direction, so the jacobian fails if the track is parallel to a wire.
+pub fn linear_straw(
+    prev_state_vec: &Vec5,
+    distance: Real,
+    start_straw: &Straw,
+    end_straw: &Straw
+    ) -> Result<Mat5, SensorError> {
+
+    let mut angles = angles::Angles::new_from_angles(prev_state_vec[ePHI], prev_state_vec[eTHETA]);
+
+    // phi and theta can not describe a direction along the z axis
+    if angles.is_axial() {
+        return Err(SensorError::InvalidDirection(angles.direction))
+    }
+
+    // a change of the global position moves the point of closest approach to the end wire
+    // along the track
+    let direction = angles.direction;
+    let wire_projection = direction.dot(&end_straw.wire_direction);
+    let path_derivative = (direction - (end_straw.wire_direction * wire_projection)).transpose() / (-(1. - (wire_projection * wire_projection)));
+
+    let (start_derivative, end_derivative) =
+        match (start_straw.local_to_global_derivative(&direction), end_straw.global_to_local_derivative(&direction)) {
+            (Some(start), Some(end)) => (start, end * (Mat3::identity() + (direction * path_derivative))),
+            _ => return Err(SensorError::InvalidDirection(direction))
+        };
+
+    // the signed distance axis of the start straw turns with the direction, which moves the
+    // start position of tracks away from the wire
+    let axis = start_derivative.column(0).into_owned();
+    let axis_derivative = (Mat3::identity() - (axis * axis.transpose())) * start_straw.wire_direction.cross_matrix() / start_straw.wire_direction.cross(&direction).norm();
+    let direction_derivative = Mat3x2::new(-angles.sin_theta * angles.sin_phi, angles.cos_theta * angles.cos_phi,
+                                            angles.sin_theta * angles.cos_phi, angles.cos_theta * angles.sin_phi,
+                                            0., -angles.sin_theta);
+    let start_frame_derivative = axis_derivative * direction_derivative * prev_state_vec[eLOC_0];
+
+    // turning the track around the end point also moves the point of closest approach along
+    // the track for tracks away from the wire
+    let start_global = start_straw.to_global(&P2::new(prev_state_vec[eLOC_0], prev_state_vec[eLOC_1]), &direction).ok_or(SensorError::InvalidDirection(direction))?;
+    let end_local = end_straw.to_local(&(start_global + (direction * distance)), &direction);
+    let end_axis = end_straw.measurement_axis(&direction).ok_or(SensorError::InvalidDirection(direction))?;
+    let end_frame_derivative = Mat2x3::from_rows(&[Vec3::zeros().transpose(), end_axis.transpose() * (-end_local.x * wire_projection / (1. - (wire_projection * wire_projection)))]);
+
+    Ok(linear_from_derivatives(&mut angles, distance, &start_derivative, &start_frame_derivative, &end_derivative, &end_frame_derivative))
+}
+
+
+/// Chains local => global on the start surface, the linear transport, and global => local
+/// on the end surface given the position derivatives of both surfaces. For surfaces whose local
+/// frame depends on the direction, `start_frame_derivative` is the derivative of the start
+/// position with respect to (phi, theta) and `end_frame_derivative` the derivative of the end
+/// local position with respect to the direction.
+fn linear_from_derivatives(
+    angles: &mut angles::Angles,
+    distance: Real,
+    start_derivative: &Mat3x2,
+    start_frame_derivative: &Mat3x2,
+    end_derivative: &Mat2x3,
+    end_frame_derivative: &Mat2x3
+    ) -> Mat5 {
+
+    let mut loc_2_glob : Mat8x5 = local_to_global_jac(angles, start_derivative);
+    let mut start_frame_slice = loc_2_glob.fixed_slice_mut::<U3, U2>(0, ePHI);
+    start_frame_slice += start_frame_derivative;
+
+    let mut glob_2_loc : Mat5x8= global_to_local_jac(angles, end_derivative); 
+    let mut end_frame_slice = glob_2_loc.fixed_slice_mut::<U2, U3>(0, 4);
+    end_frame_slice += end_frame_derivative;
+
+    let transport_jac: Mat8 = linear_transport_jac(angles, distance);
 
     // print!{"IN JACOBIAN", loc_2_glob, glob_2_loc, transport_jac};
 
-    return glob_2_loc * transport_jac * loc_2_glob
-    
+    glob_2_loc * transport_jac * loc_2_glob
 }
 
 
-/// Local => global jacobian used for both linear and constant magnetic field situaitons
+/// Global => local jacobian used for both linear and constant magnetic field situaitons.
+/// Maps the free parameters (x, y, z, t, tx, ty, tz, q/p) onto the bound parameters of a
+/// surface, the position rows are given by `position_derivative`.
 /// https://gitlab.cern.ch/acts/acts-core/blob/master/Core/include/Acts/Surfaces/detail/Surface.ipp#L82-106
-fn global_to_local_jac(
+pub fn global_to_local_jac(
     trig_angles: &angles::Angles,
-    rotation_mat: &Mat4
+    position_derivative: &Mat2x3       // d(local) / d(global position) of the sensor
     ) -> Mat5x8 {
 
     let mut global_to_local_jacobian = Mat5x8::zeros();
 
     let mut g2l_slice = global_to_local_jacobian.fixed_slice_mut::<U2, U3>(0,0);
-    
-    let rot_mat_transposed = rotation_mat.transpose();
-    let rot_slice = rot_mat_transposed.fixed_slice::<U2, U3>(0,0);
-
-    g2l_slice.copy_from(&rot_slice);
+    g2l_slice.copy_from(position_derivative);
 
 
-    let inv_sin_theta = 1. / trig_angles.sin_theta;
+    // phi and theta are singular along the z axis, the guard keeps the derivatives finite. The
+    // fitters do not transport such states, they return `SensorError::InvalidDirection`
+    let sin_theta = trig_angles.guarded_sin_theta();
+    let inv_sin_theta = 1. / sin_theta;
 
-    let sin_phi_over_sin_theta = trig_angles.sin_phi / trig_angles.sin_theta;
-    let cos_phi_over_sin_theta = trig_angles.cos_phi / trig_angles.sin_theta;
+    let sin_phi_over_sin_theta = trig_angles.sin_phi / sin_theta;
+    let cos_phi_over_sin_theta = trig_angles.cos_phi / sin_theta;
 
 
 
@@ -79,28 +176,31 @@ fn global_to_local_jac(
 
 }
 
-/// global => local jacobian used for both linear and constant magnetic field situaitons
+/// Local => global jacobian used for both linear and constant magnetic field situaitons.
+/// Maps the bound parameters of a surface onto the free parameters (x, y, z, t, tx, ty, tz, q/p),
+/// the position rows are given by `position_derivative`.
 /// https://gitlab.cern.ch/acts/acts-core/blob/master/Core/include/Acts/Surfaces/detail/Surface.ipp#L46-80
-fn local_to_global_jac(
+pub fn local_to_global_jac(
     trig_angles: &angles::Angles,
-    rotation_mat: &Mat4
+    position_derivative: &Mat3x2       // d(global position) / d(local) of the sensor
     ) -> Mat8x5{
 
     let mut local_to_global_jacobian = Mat8x5::zeros();
 
     let mut l2g_slice = local_to_global_jacobian.fixed_slice_mut::<U3, U2>(0,0);
-    let rot_slice = rotation_mat.fixed_slice::<U3, U2>(0,0);
+    l2g_slice.copy_from(position_derivative);
 
-    l2g_slice.copy_from(&rot_slice);
+    // guarded like in `global_to_local_jac` so phi is carried through a transport along the z axis
+    let sin_theta = trig_angles.guarded_sin_theta();
 
     // add values into transport jacobian
     change_mat_val!{
         local_to_global_jacobian;
-        [4, ePHI] => (-trig_angles.sin_theta) * trig_angles.sin_phi,
+        [4, ePHI] => (-sin_theta) * trig_angles.sin_phi,
         [4, eTHETA] => trig_angles.cos_theta * trig_angles.cos_phi,
-        [5, ePHI] =>  trig_angles.sin_theta * trig_angles.cos_phi,
+        [5, ePHI] =>  sin_theta * trig_angles.cos_phi,
         [5, eTHETA] => trig_angles.cos_theta * trig_angles.sin_phi,
-        [6, eTHETA] =>  -trig_angles.sin_theta,
+        [6, eTHETA] =>  -sin_theta,
         [7, eQOP] => 1.
     }
 
@@ -108,8 +208,10 @@ fn local_to_global_jac(
     local_to_global_jacobian
 }
 
+/// Straight line transport of the free parameters over `distance` along the momentum: the
+/// position moves by `distance` times the change of the direction
 fn linear_transport_jac(
-    trig_angles: &mut angles::Angles,
+    _trig_angles: &mut angles::Angles,
     distance: Real
     ) -> Mat8{
 
@@ -117,9 +219,9 @@ fn linear_transport_jac(
     let mut secondary= Mat8::zeros();
 
     change_mat_val!{secondary;
-        [0, 0] => distance * trig_angles.tx,
-        [1,1] => distance * trig_angles.ty,
-        [2,2] => distance * trig_angles.tz
+        [0, 4] => distance,
+        [1, 5] => distance,
+        [2, 6] => distance
         // since the other values across the diagonal are 1 and we transport_jac is a identity matrix we leave it here
     }
 
@@ -147,7 +249,7 @@ pub fn linear_state_derivative(
 
     let mut ang = angles::Angles::new_from_angles(*phi, *theta);
 
-    let inv_sin_theta = 1./ang.sin_theta;
+    let inv_sin_theta = 1./ang.guarded_sin_theta();
 
     /*
 
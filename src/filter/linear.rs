@@ -3,248 +3,328 @@ use super::super::config::*;
 use super::prediction;
 use super::filter_gain;
 use super::smoothing;
-use super::jacobian;
+use super::angles;
 
 use std::iter;
+use std::collections::HashMap;
 
-use super::super::geometry::Rectangle;
-use super::super::geometry::traits::{Plane, Transform};
+use super::super::geometry::traits::{Plane, Transform, Identified};
+use super::measurement::Measurement;
+use super::super::geometry::navigator::Navigator;
+use super::super::geometry::bounds::BoundaryCheck;
+use super::super::geometry::intersection::NavigationDirection;
 
 use super::super::error::*;
 use super::utils::{SuperData, Data};
+use super::parameters::BoundTrackParameters;
+use super::finite_difference::{self, FiniteDifferenceOptions, JacobianComparison};
 
 #[macro_use]
 use super::macros;
 
-/// Monolithic function to handle linear KF calculations
+/// Configuration of the linear KF
+#[derive(Debug, Clone)]
+pub struct FitterOptions {
+    /// tolerance used when checking that a prediction lands on the next sensor. A
+    /// `BoundaryCheck::Covariance` uses the predicted covariance of the local coordinates,
+    /// its own `covariance` is ignored.
+    pub boundary_check: BoundaryCheck,
+    /// The inputs are always ordered along the momentum. `Backward` fits them from the last
+    /// sensor to the first, propagating against the momentum.
+    pub navigation: NavigationDirection,
+    /// Debugging of the transport: every analytic jacobian is compared with a numerical one,
+    /// the comparisons are returned in `SuperData::jacobian_checks`. This is slow.
+    pub jacobian_check: Option<FiniteDifferenceOptions>
+}
+
+impl Default for FitterOptions {
+    fn default() -> Self {
+        FitterOptions {boundary_check: BoundaryCheck::Strict, navigation: NavigationDirection::Forward, jacobian_check: None}
+    }
+}
+
+/// Monolithic function to handle linear KF calculations with the default options. Panics
+/// with the error of the fit if it fails, use `run_with_options` to handle the error instead.
 #[allow(dead_code)] 
-pub fn run(
+pub fn run<T: Transform + Plane + Identified>(
     start_location: &P3,                         // start loc used to predict initial filtered state vec
     measurement_noise_covariance_vector: &Vec<Mat2>,  // vector of V from fruhwirth paper
     measurements_vector: &Vec<Vec2>,            // vector of all the measurements that were registered
-    sensor_vector: &Vec<Rectangle>,             // the geometric sensors that correspond to each hit ,
+    sensor_vector: &Vec<T>,                     // the geometric sensors that correspond to each hit ,
     intitial_seed_vec: Option<&Vec5>
     )  -> SuperData{
 
-    let meas_map_mat = Mat2x5::new(1. , 0. , 0. , 0. , 0. ,
-                                   0. , 1. , 0. , 0. , 0. );
-    
-    if (measurement_noise_covariance_vector.len() == measurements_vector.len()) && (measurements_vector.len() == sensor_vector.len()) {}
-    else {
-        panic!("vector lengths need to be the same length")
-    }
-    let input_length = measurements_vector.len() - 1;
+    let options = FitterOptions::default();
 
-    store_vec!{
-        input_length; // since we have n sensors, we should have n filtered values
-        
-        jacobian_iter: Mat5,
-
-        // storage for predicted  values
-        // these can all be deleted later when we only return SmoothedData 
-        // instead of a combination of pred / filtered / smoothed all together
-        predicted_state_vec_iter : Vec5,
-        predicted_cov_mat_iter : Mat5,
-        predicted_res_mat_iter : Mat2,
-        predicted_res_vec_iter: Vec2,
-
-        // storage for filtered values
-        filter_res_mat_iter: Mat2,
-        filter_res_vec_iter: Vec2,
-        chi_squared_iter: Real,
-
-        // storage for smoothed values
-        smoothed_state_vec_iter : Vec5,
-        smoothed_cov_mat_iter : Mat5,
-        smoothed_res_mat_iter : Mat2,
-        smoothed_res_vec_iter: Vec2
+    match run_with_options(start_location, measurement_noise_covariance_vector, measurements_vector, sensor_vector, intitial_seed_vec, &options) {
+        Ok(data) => data,
+        Err(e) => panic!("linear fit failed: {:?}", e)
     }
-    store_vec!{input_length+1; // these vectors require initial seeded values so we initialize to len+1
-        filter_state_vec_iter: Vec5,
-        filter_cov_mat_iter: Mat5
+}
+
+/// Linear KF calculations with user specified options. Instead of panicking, a prediction that
+/// lands outside of the next sensor (beyond the tolerance of `options.boundary_check`) returns
+/// `SensorError::OutsideSensorBounds`, and a state along the global z axis, where phi is
+/// not defined, returns `SensorError::InvalidDirection`, both wrapped in `FilterError::Sensor`.
+/// Inputs of different lengths return `Err(FilterError::LengthMismatch)`, fewer than two
+/// measurements return `Err(FilterError::NotEnoughMeasurements)`.
+pub fn run_with_options<T: Transform + Plane + Identified>(
+    start_location: &P3,
+    measurement_noise_covariance_vector: &Vec<Mat2>,
+    measurements_vector: &Vec<Vec2>,
+    sensor_vector: &Vec<T>,
+    intitial_seed_vec: Option<&Vec5>,
+    options: &FitterOptions
+    ) -> Result<SuperData, FilterError> {
+
+    fit(start_location, measurement_noise_covariance_vector, measurements_vector, sensor_vector, intitial_seed_vec, super::utils::seed_covariance(), options)
+}
+
+/// Linear KF calculations seeded with track parameters on the first fitted sensor (the last
+/// one when fitting backward). The covariance of the seed is used if it is known, otherwise
+/// the default seed covariance. Returns `Err(SensorError::WrongSeedSurface)` if the seed is
+/// on another sensor.
+pub fn run_with_seed<T: Transform + Plane + Identified>(
+    seed: &BoundTrackParameters,
+    measurement_noise_covariance_vector: &Vec<Mat2>,
+    measurements_vector: &Vec<Vec2>,
+    sensor_vector: &Vec<T>,
+    options: &FitterOptions
+    ) -> Result<SuperData, FilterError> {
+
+    let first_sensor =
+        match options.navigation {
+            NavigationDirection::Forward => sensor_vector.first(),
+            NavigationDirection::Backward => sensor_vector.last()
+        };
+
+    if first_sensor.map(|sensor| sensor.geometry_id()) != Some(seed.surface()) {
+        return Err(SensorError::WrongSeedSurface(seed.surface()).into())
     }
+
+    let seed_covariance = seed.covariance().cloned().unwrap_or_else(super::utils::seed_covariance);
+
+    // the start location is only used to make a seed
+    fit(&P3::origin(), measurement_noise_covariance_vector, measurements_vector, sensor_vector, Some(seed.vector()), seed_covariance, options)
+}
+
+fn fit<T: Transform + Plane + Identified>(
+    start_location: &P3,
+    measurement_noise_covariance_vector: &Vec<Mat2>,
+    measurements_vector: &Vec<Vec2>,
+    sensor_vector: &Vec<T>,
+    intitial_seed_vec: Option<&Vec5>,
+    seed_covariance: Mat5,
+    options: &FitterOptions
+    ) -> Result<SuperData, FilterError> {
+
+    let meas_map_mat = BoundTrackParameters::projector();
     
-    // fetch the first sensor
-    get_unchecked!{
-        sensor_vector[0]=> first_sensor,
-        measurements_vector[0] => first_hit
-        }
+    if (measurement_noise_covariance_vector.len() != measurements_vector.len()) || (measurements_vector.len() != sensor_vector.len()) {
+        return Err(FilterError::LengthMismatch)
+    }
+    // a track is predicted from its first measurement onto the next
+    if measurements_vector.len() < 2 {
+        return Err(FilterError::NotEnoughMeasurements)
+    }
+    let input_length = measurements_vector.len() - 1;
+
+    // index of the inputs in the order they are fitted
+    let order =
+        match options.navigation {
+            NavigationDirection::Forward => (0..=input_length).collect::<Vec<_>>(),
+            NavigationDirection::Backward => (0..=input_length).rev().collect()
+        };
 
-    let mut previous_state_vec =
+    let first_sensor = &sensor_vector[order[0]];
+
+    let seed_vec =
     if let Some(state_vec) = intitial_seed_vec {
-        state_vec.clone()
+        *state_vec
     }
     else{
         // calculate some seeded values (seeding improvement suggestions welcome)
-        super::utils::seed_state_vec_from_sensor(&start_location, first_sensor,first_hit)
-    };
-    let mut previous_covariance = super::utils::seed_covariance();
-    // Store the seeded values in their respective iterators
-    push!(
-        previous_covariance => filter_cov_mat_iter, 
-        previous_state_vec => filter_state_vec_iter,
-
-        previous_state_vec => predicted_state_vec_iter,
-        previous_covariance => predicted_cov_mat_iter
-    );
+        let mut seed = super::utils::seed_state_vec_from_sensor(start_location, first_sensor, &measurements_vector[order[0]]);
 
+        // the seed points from the start location to the sensor, against the momentum
+        // when fitting backward
+        if options.navigation == NavigationDirection::Backward {
+            seed[ePHI] = angles::wrap_phi(seed[ePHI] + PI);
+            seed[eTHETA] = PI - seed[eTHETA];
+        }
 
+        seed
+    };
+    let seed = BoundTrackParameters::from_vector(first_sensor.geometry_id(), seed_vec, Some(seed_covariance));
+
+    // the seed is stored as both the first predicted and filtered parameters, the residuals
+    // start at the second sensor
+    let mut predicted = vec![seed.clone()];
+    let mut filtered = vec![seed];
+    let mut jacobians = Vec::with_capacity(input_length);
+
+    let mut pred_res_mat = Vec::with_capacity(input_length);
+    let mut pred_res_vec = Vec::with_capacity(input_length);
+    let mut filt_res_mat = Vec::with_capacity(input_length);
+    let mut filt_res_vec = Vec::with_capacity(input_length);
+
+    // filled when debugging the transport jacobians
+    let mut jacobian_checks = Vec::new();
+
+    for i in 0..input_length {
+        let previous = &filtered[i];
+        let curr_sensor = &sensor_vector[order[i]];
+
+        // the prediction is made onto the next sensor, so its V / m_k are the ones
+        // used in the filtering step
+        let next_sensor = &sensor_vector[order[i+1]];
+        let curr_v = &measurement_noise_covariance_vector[order[i+1]];
+
+        // phi and theta can not describe the direction of a state along the z axis, the
+        // jacobian of such a state would only be kept finite by the sin(theta) guard
+        let direction_angles = angles::Angles::new_from_angles(previous.phi(), previous.theta());
+        if direction_angles.is_axial() {
+            return Err(SensorError::InvalidDirection(direction_angles.direction).into())
+        }
 
-    for i in 0..input_length{
+        //predictions, they are checked against the bounds once their covariance is known
+        let prediction =
+            prediction::linear_parameters(curr_sensor, next_sensor, previous, &BoundaryCheck::Absolute(Real::INFINITY), options.navigation)?;
 
-        // fetch the next values of V / m_k / current sensor
-        get_unchecked!{i;
-            measurement_noise_covariance_vector => curr_v,
-            measurements_vector=> curr_m_k,
-            sensor_vector => curr_sensor
-        }
-        
-        get_unchecked!{
-            sensor_vector[i+1] => next_sensor
+        if let Some(check) = &options.jacobian_check {
+            let propagator = finite_difference::linear_propagator(curr_sensor, next_sensor, options.navigation);
+            let numerical = finite_difference::transport_jacobian(propagator, previous.vector(), &check.steps)?;
+            jacobian_checks.push(JacobianComparison::new(prediction.jacobian, numerical, check.tolerance));
         }
 
-        //predictions
-        let (pred_state_vec, distance_between) = 
-            match prediction::linear_state_vector(curr_sensor, next_sensor, &previous_state_vec) {
-                Ok(x) => x,
-                Err(e) => {
-                    dbg!{e};
-                    panic!{"out of bounds panic happened"}
-                }
-            };
+        let pred = prediction.parameters;
+        let pred_cov_mat = pred.covariance().ok_or(FilterError::MissingCovariance)?;
 
-        let jacobian = jacobian::linear(&previous_state_vec, distance_between, curr_sensor, next_sensor);
+        let boundary_check = options.boundary_check.with_covariance(&pred.local_covariance().ok_or(FilterError::MissingCovariance)?);
+        if !next_sensor.inside_with_tolerance(&pred.local(), &boundary_check) {
+            return Err(SensorError::OutsideSensorBounds(pred.local()).into())
+        }
 
-        let pred_cov_mat = prediction::covariance_matrix(&jacobian, &previous_covariance);
-        let pred_residual_mat = prediction::residual_mat(curr_v, &meas_map_mat, &pred_cov_mat);
-        let pred_residual_vec = prediction::residual_vec(&curr_m_k, &meas_map_mat, &pred_state_vec);
+        // a polar measurement is taken on the same side of phi = +-pi as the prediction
+        let curr_m_k = next_sensor.align_measurement(&measurements_vector[order[i+1]], &pred.local().coords);
 
+        pred_res_mat.push(prediction::residual_mat(curr_v, &meas_map_mat, pred_cov_mat));
+        pred_res_vec.push(prediction::residual_vec(&curr_m_k, &meas_map_mat, pred.vector()));
 
         //filtering
-        let kalman_gain = filter_gain::kalman_gain(&pred_cov_mat, &meas_map_mat, curr_v);
-        let filter_state_vec = filter_gain::state_vector(&pred_state_vec, &kalman_gain, curr_m_k, &meas_map_mat);
-        let filter_cov_mat = filter_gain::covariance_matrix(&kalman_gain, &meas_map_mat, &pred_cov_mat);
-        let filter_residual_vec = filter_gain::residual_vec(&meas_map_mat, &kalman_gain, &pred_residual_vec);
-        let filter_residual_mat = filter_gain::residual_mat(curr_v, &meas_map_mat, &filter_cov_mat);
-        let chi_squared_inc = filter_gain::chi_squared_increment(&filter_residual_vec, &filter_residual_mat);
-
-
-
-        // store all the filtered values in their respective iterators
-        push!{
-            filter_state_vec =>filter_state_vec_iter,
-            jacobian => jacobian_iter,
-            filter_cov_mat => filter_cov_mat_iter,
-            filter_residual_mat => filter_res_mat_iter,
-            filter_residual_vec => filter_res_vec_iter,
-            chi_squared_inc => chi_squared_iter
-        }
+        let filt = filter_gain::update(&pred, &curr_m_k, curr_v)?;
 
+        filt_res_mat.push(filt.residual_mat);
+        filt_res_vec.push(filt.residual_vec);
+        jacobians.push(prediction.jacobian);
 
-        // We store this for making histograms on data later. This can be deleted later for performace
-        push!{ 
-            pred_state_vec => predicted_state_vec_iter,
-            pred_cov_mat => predicted_cov_mat_iter,
-            pred_residual_mat => predicted_res_mat_iter,
-            pred_residual_vec =>predicted_res_vec_iter
-        }
-        
-        // store current filtered values as the "previous" to be used in the
-        // prediction calculations in the next iteration
-        previous_covariance = filter_cov_mat;
-        previous_state_vec = filter_state_vec;
-
-    }
-
-    // Clone the last value of filtered and insert it into smoothed. This is required 
-    // (at least for filter_state_vec_iter and filter_cov_mat_iter) since they are required 
-    // as both the previous filtered values and previous smoothed values
-    push!{clone: input_length-1;
-        filter_state_vec_iter => smoothed_state_vec_iter,
-        filter_cov_mat_iter => smoothed_cov_mat_iter,
-        filter_res_mat_iter => smoothed_res_mat_iter,
-        filter_res_vec_iter => smoothed_res_vec_iter
+        predicted.push(pred);
+        filtered.push(filt.parameters);
     }
 
-
-    // print!{"\n"}
+    // the smoothing starts from the last filtered parameters, it is stored from the last
+    // sensor to the first and reversed at the end
+    let mut smoothed = vec![filtered[input_length].clone()];
+    let mut smth_res_mat = vec![filt_res_mat[input_length-1]];
+    let mut smth_res_vec = vec![filt_res_vec[input_length-1]];
 
     for i in (0..input_length).rev() {
-        
-        //
-        // initializing variables
-        // 
-        
-        // fetch the current variables 
-        get_unchecked!{i;
-            filter_state_vec_iter => curr_filt_state_vec,
-            filter_cov_mat_iter => curr_filt_cov_mat,
-            measurement_noise_covariance_vector => curr_v,
-            measurements_vector =>curr_measurement,
-            jacobian_iter => curr_jacobian
-        }
-
-
-        // since we move backwards, previous variables are at i+1
-        get_unchecked!{i+1;
-            filter_state_vec_iter => prev_filt_state_vec,
-            filter_cov_mat_iter => prev_filt_cov_mat
-        }
+        // since we move backwards, the smoothed parameters of the next sensor were pushed
+        // in the last iteration. They are compared to the prediction onto that sensor, which
+        // includes the transport from i
+        let smth = smoothing::smooth(&filtered[i], &jacobians[i], &predicted[i+1], &smoothed[input_length-(i+1)])?;
+        let smth_cov_mat = smth.covariance().ok_or(FilterError::MissingCovariance)?;
+
+        let curr_sensor = &sensor_vector[order[i]];
+        let curr_v = &measurement_noise_covariance_vector[order[i]];
+        let curr_measurement = curr_sensor.align_measurement(&measurements_vector[order[i]], &smth.local().coords);
+
+        smth_res_mat.push(smoothing::residual_mat(curr_v, &meas_map_mat, smth_cov_mat));
+        smth_res_vec.push(smoothing::residual_vec(&curr_measurement, &meas_map_mat, smth.vector()));
+        smoothed.push(smth);
+    }
 
-        // grab variables pushed in the last iteration
-        // (i+1) since input_length is based on the function argument lengths
-        get_unchecked!{input_length -(i+1);                         //TODO: double check this indexing
-            smoothed_state_vec_iter => prev_smth_state_vec,
-            smoothed_cov_mat_iter => prev_smth_cov_mat
+    smoothed.reverse();
+    smth_res_mat.reverse();
+    smth_res_vec.reverse();
+
+    // the ids of the sensors are kept with the parameters
+    let smth = Data::from_parameters(&smoothed, smth_res_mat, smth_res_vec);
+    let filt = Data::from_parameters(&filtered, filt_res_mat, filt_res_vec);
+    let pred = Data::from_parameters(&predicted, pred_res_mat, pred_res_vec);
+
+    let mut data = SuperData::new(smth, filt, pred);
+    data.jacobian_checks = jacobian_checks;
+
+    Ok(data)
+}
+
+/// Linear KF calculations on measurements that reference their sensor by id. The measurements
+/// are used in the order they are given, `sensors` can be in any order. Returns
+/// `Err(SensorError::UnknownGeometryId)` if a measurement is on a sensor that is not in `sensors`.
+pub fn run_measurements<T: Transform + Plane + Identified + Clone>(
+    start_location: &P3,
+    measurements: &[Measurement],
+    sensors: &[T],
+    intitial_seed_vec: Option<&Vec5>,
+    options: &FitterOptions
+    ) -> Result<SuperData, FilterError> {
+
+    let sensor_map = sensors.iter()
+        .map(|sensor| (sensor.geometry_id(), sensor))
+        .collect::<HashMap<_, _>>();
+
+    let sensor_vector = measurements.iter()
+        .map(|measurement| {
+            match sensor_map.get(&measurement.geometry_id) {
+                Some(sensor) => Ok((*sensor).clone()),
+                None => Err(SensorError::UnknownGeometryId(measurement.geometry_id))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let covariance_vector = measurements.iter().map(|measurement| measurement.covariance).collect();
+    let hit_vector = measurements.iter().map(|measurement| measurement.local).collect();
+
+    run_with_options(start_location, &covariance_vector, &hit_vector, &sensor_vector, intitial_seed_vec, options)
+}
+
+/// Linear KF calculations on an unordered set of measurements. The measurements are sorted in
+/// the order that a straight track from `start_location` along `start_direction` crosses their
+/// sensors in the geometry of `navigator`. Measurements on sensors that are not in the geometry
+/// return `Err(SensorError::UnknownGeometryId)`, measurements on sensors that are parallel to
+/// the track return `Err(SensorError::InvalidDirection)`.
+pub fn run_navigated(
+    start_location: &P3,
+    start_direction: &Vec3,
+    measurements: &[Measurement],
+    navigator: &Navigator,
+    intitial_seed_vec: Option<&Vec5>,
+    options: &FitterOptions
+    ) -> Result<SuperData, FilterError> {
+
+    let ids = measurements.iter().map(|measurement| measurement.geometry_id).collect::<Vec<_>>();
+    let crossings = navigator.order_surfaces(start_location, start_direction, &ids);
+
+    let missing = ids.iter().find(|id| crossings.iter().all(|crossing| crossing.geometry_id != **id));
+    if let Some(id) = missing {
+        return match navigator.geometry().surface(*id) {
+            Some(_) => Err(SensorError::InvalidDirection(*start_direction).into()),
+            None => Err(SensorError::UnknownGeometryId(*id).into())
         }
+    }
 
-        // print!{i, input_length-(i+1)}
-
-        // 
-        // smoothing calculations
-        //
-
-        // NOTE: the next calculations assume that x^n references the next state vector and x^k references the previous 
-        // state vector. I am uncertain as to what the actual answer is as andi still has not gotten back to me about it.
-        let gain_matrix = smoothing::gain_matrix(curr_filt_cov_mat, &curr_jacobian, prev_filt_cov_mat); 
-        let smoothed_state_vec = smoothing::state_vector(curr_filt_state_vec, &gain_matrix, prev_smth_state_vec, prev_filt_state_vec);
-        let smoothed_cov_mat = smoothing::covariance_matrix(curr_filt_cov_mat, &gain_matrix,prev_smth_cov_mat, prev_filt_cov_mat);
-        let smoothed_res_mat = smoothing::residual_mat(curr_v, &meas_map_mat, &smoothed_cov_mat);
-        let smoothed_res_vec = smoothing::residual_vec(curr_measurement, &meas_map_mat, &smoothed_state_vec);
-
-
-        //
-        //  group push variables to vectors
-        //
-        push!{
-            smoothed_state_vec => smoothed_state_vec_iter,
-            smoothed_cov_mat => smoothed_cov_mat_iter,
-            smoothed_res_mat => smoothed_res_mat_iter,
-            smoothed_res_vec => smoothed_res_vec_iter
-        }
+    // measurements in crossing order, several measurements on one sensor stay together
+    let mut ordered = measurements.to_vec();
+    ordered.sort_by(|a, b| {
+        let position = |m: &Measurement| crossings.iter().position(|c| c.geometry_id == m.geometry_id);
+        position(a).cmp(&position(b))
+    });
 
-    }
+    let sensors = crossings.iter()
+        .filter_map(|crossing| navigator.geometry().surface(crossing.geometry_id))
+        .cloned()
+        .collect::<Vec<_>>();
 
-    // put all data into a struct that will contain all the methods to return 
-    // the data back to c++
-    let smth =  Data::new(smoothed_state_vec_iter.into_iter().rev().collect(),
-                                smoothed_cov_mat_iter.into_iter().rev().collect(),
-                                smoothed_res_mat_iter.into_iter().rev().collect(),
-                                smoothed_res_vec_iter.into_iter().rev().collect());
-
-    let filt = Data::new(
-        filter_state_vec_iter,
-        filter_cov_mat_iter,
-        filter_res_mat_iter,
-        filter_res_vec_iter
-    );
-
-    let pred = Data::new(
-        predicted_state_vec_iter,
-        predicted_cov_mat_iter,
-        predicted_res_mat_iter,
-        predicted_res_vec_iter
-    );
-
-    SuperData::new(smth, filt, pred)
-}
\ No newline at end of file
+    run_measurements(start_location, &ordered, &sensors, intitial_seed_vec, options)
+}
@@ -0,0 +1,16 @@
+use super::super::config::*;
+use super::super::geometry::GeometryId;
+
+/// A hit in the local frame of the sensor it was registered on
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub geometry_id: GeometryId,    // sensor the hit is on
+    pub local: Vec2,                // m_k
+    pub covariance: Mat2            // V
+}
+
+impl Measurement {
+    pub fn new(geometry_id: GeometryId, local: Vec2, covariance: Mat2) -> Self {
+        Measurement {geometry_id, local, covariance}
+    }
+}
@@ -4,9 +4,17 @@ pub mod macros;
 pub mod angles;
 pub mod linear;
 pub mod jacobian;
+pub mod finite_difference;
+pub mod covariance;
+pub mod perigee;
+pub mod extrapolation;
+pub mod parameters;
 pub mod utils;
+pub mod measurement;
 
 pub mod prediction;
 pub mod filter_gain;
 pub mod filter_means;
-pub mod smoothing;
\ No newline at end of file
+pub mod smoothing;
+pub mod straw;
+pub mod alignment;
\ No newline at end of file
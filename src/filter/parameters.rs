@@ -0,0 +1,313 @@
+use super::super::config::*;
+use super::super::error::SensorError;
+use super::super::geometry::traits::{Transform, Plane, Identified};
+use super::super::geometry::GeometryId;
+use super::angles::{self, Angles};
+use super::covariance;
+
+/// Track parameters bound to a surface: (loc0, loc1, phi, theta, q/p) with an optional 5x5
+/// covariance, stored as a `Vec5` indexed by `eLOC_0`, `eLOC_1`, `ePHI`, `eTHETA` and `eQOP`.
+/// Phi is kept in (-pi, pi]. q/p is in 1 / GeV.
+///
+/// The steps of the linear fitter work on bound parameters: the prediction onto the next
+/// sensor (`prediction::linear_parameters`), the update with a measurement
+/// (`filter_gain::update`) and the smoothing (`smoothing::smooth`). The fitted states are
+/// stored as `Vec5` state vectors and can be read back as bound parameters (`Data::parameters`).
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::parameters::BoundTrackParameters;
+/// use kalman_rs::geometry::GeometryId;
+///
+/// let mut parameters = BoundTrackParameters::new(GeometryId::new(1, 2, 3), P2::new(0.5, -0.5), 3. * PI / 2., PI / 2., 0.1, None);
+///
+/// assert!((parameters.phi() + PI / 2.).abs() < 1e-12);
+/// assert!((parameters.momentum() - 10.).abs() < 1e-12);
+/// assert_eq!(parameters.vector()[eLOC_0], parameters.loc0());
+///
+/// parameters.set_phi(PI + 0.1);
+/// assert!((parameters.phi() - (0.1 - PI)).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundTrackParameters {
+    surface: GeometryId,
+    vector: Vec5,
+    covariance: Option<Mat5>
+}
+
+impl BoundTrackParameters {
+
+    pub fn new(surface: GeometryId, local: P2, phi: Real, theta: Real, qop: Real, covariance: Option<Mat5>) -> Self {
+        BoundTrackParameters::from_vector(surface, Vec5::new(local.x, local.y, phi, theta, qop), covariance)
+    }
+
+    /// Parameters from a (loc0, loc1, phi, theta, q/p) state vector
+    pub fn from_vector(surface: GeometryId, mut vector: Vec5, covariance: Option<Mat5>) -> Self {
+        vector[ePHI] = angles::wrap_phi(vector[ePHI]);
+        BoundTrackParameters {surface, vector, covariance}
+    }
+
+    /// Id of the surface the parameters are bound to
+    pub fn surface(&self) -> GeometryId {
+        self.surface
+    }
+
+    /// (loc0, loc1, phi, theta, q/p)
+    pub fn vector(&self) -> &Vec5 {
+        &self.vector
+    }
+
+    pub fn covariance(&self) -> Option<&Mat5> {
+        self.covariance.as_ref()
+    }
+
+    pub fn set_covariance(&mut self, covariance: Option<Mat5>) {
+        self.covariance = covariance;
+    }
+
+    /// Covariance of the local position
+    pub fn local_covariance(&self) -> Option<Mat2> {
+        self.covariance.map(|covariance| covariance.fixed_slice::<U2, U2>(eLOC_0, eLOC_0).into_owned())
+    }
+
+    /// Projection of the parameters onto their local position (H), as used for measurements
+    pub fn projector() -> Mat2x5 {
+        Mat2x5::new(1., 0., 0., 0., 0.,
+                    0., 1., 0., 0., 0.)
+    }
+
+    pub fn loc0(&self) -> Real {
+        self.vector[eLOC_0]
+    }
+
+    pub fn loc1(&self) -> Real {
+        self.vector[eLOC_1]
+    }
+
+    pub fn local(&self) -> P2 {
+        P2::new(self.loc0(), self.loc1())
+    }
+
+    pub fn set_local(&mut self, local: &P2) {
+        self.vector[eLOC_0] = local.x;
+        self.vector[eLOC_1] = local.y;
+    }
+
+    pub fn phi(&self) -> Real {
+        self.vector[ePHI]
+    }
+
+    /// Sets phi, wrapped into (-pi, pi]
+    pub fn set_phi(&mut self, phi: Real) {
+        self.vector[ePHI] = angles::wrap_phi(phi);
+    }
+
+    pub fn theta(&self) -> Real {
+        self.vector[eTHETA]
+    }
+
+    pub fn set_theta(&mut self, theta: Real) {
+        self.vector[eTHETA] = theta;
+    }
+
+    pub fn qop(&self) -> Real {
+        self.vector[eQOP]
+    }
+
+    pub fn set_qop(&mut self, qop: Real) {
+        self.vector[eQOP] = qop;
+    }
+
+    /// Unit vector along the momentum in the global frame
+    pub fn direction(&self) -> Vec3 {
+        Angles::new_from_angles(self.phi(), self.theta()).direction
+    }
+
+    /// Sign of the charge
+    pub fn charge(&self) -> Real {
+        self.qop().signum()
+    }
+
+    /// Absolute momentum in GeV
+    pub fn momentum(&self) -> Real {
+        1. / self.qop().abs()
+    }
+
+    /// Momentum vector (px, py, pz) in GeV
+    pub fn momentum_vector(&self) -> Vec3 {
+        self.direction() * self.momentum()
+    }
+
+    /// Momentum transverse to the global z axis
+    pub fn transverse_momentum(&self) -> Real {
+        self.momentum() * self.theta().sin().abs()
+    }
+
+    /// Pseudorapidity, -ln(tan(theta / 2))
+    pub fn eta(&self) -> Real {
+        -(self.theta() / 2.).tan().ln()
+    }
+
+    /// Position in the global frame, `sensor` is the surface of the parameters
+    pub fn position<T: Transform>(&self, sensor: &T) -> P3 {
+        sensor.to_global(P3::new(self.loc0(), self.loc1(), 0.))
+    }
+
+    /// Global parameters, `sensor` is the surface of the parameters
+    pub fn to_free<T: Transform + Plane>(&self, sensor: &T) -> FreeParameters {
+        FreeParameters::from_bound(sensor, &self.vector, self.covariance.as_ref())
+    }
+
+    /// Global parameters `free` bound to `sensor`, see `FreeParameters::to_bound`
+    pub fn from_free<T: Transform + Plane + Identified>(free: &FreeParameters, sensor: &T) -> Result<Self, SensorError> {
+        let (state_vec, covariance) = free.to_bound(sensor)?;
+        Ok(BoundTrackParameters::from_vector(sensor.geometry_id(), state_vec, covariance))
+    }
+}
+
+impl AsRef<Vec5> for BoundTrackParameters {
+    fn as_ref(&self) -> &Vec5 {
+        &self.vector
+    }
+}
+
+
+/// Track parameters in the global frame: position, time, unit direction and q/p, stored as
+/// (x, y, z, t, tx, ty, tz, q/p) with an optional 8x8 covariance. q/p is in 1 / GeV.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::parameters::FreeParameters;
+///
+/// // a negative track with 2 GeV momentum at 45 degrees to the beam axis
+/// let direction = Vec3::new(1., 0., 1.);
+/// let parameters = FreeParameters::new(P3::origin(), 0., &direction, -0.5, None);
+///
+/// assert_eq!(parameters.charge(), -1.);
+/// assert!((parameters.transverse_momentum() - (2. as Real).sqrt()).abs() < 1e-12);
+/// assert!((parameters.eta() - 0.881373587019543).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreeParameters {
+    vector: Vec8,
+    covariance: Option<Mat8>
+}
+
+impl FreeParameters {
+
+    /// Parameters at `position` moving along `direction`, which does not need to be normalized
+    pub fn new(position: P3, time: Real, direction: &Vec3, qop: Real, covariance: Option<Mat8>) -> Self {
+        let direction = direction.normalize();
+
+        let mut vector = Vec8::zeros();
+        vector.fixed_rows_mut::<U3>(eFREE_POS0).copy_from(&position.coords);
+        vector[eFREE_TIME] = time;
+        vector.fixed_rows_mut::<U3>(eFREE_DIR0).copy_from(&direction);
+        vector[eFREE_QOP] = qop;
+
+        FreeParameters {vector, covariance}
+    }
+
+    /// Parameters from the (x, y, z, t, tx, ty, tz, q/p) vector
+    pub fn from_vector(vector: Vec8, covariance: Option<Mat8>) -> Self {
+        FreeParameters {vector, covariance}
+    }
+
+    /// Global parameters of the bound state `state_vec` on `sensor`. Bound states carry no
+    /// time, so the time and its covariance are zero.
+    pub fn from_bound<T: Transform + Plane>(sensor: &T, state_vec: &Vec5, covariance: Option<&Mat5>) -> Self {
+        let position = sensor.to_global(P3::new(state_vec[eLOC_0], state_vec[eLOC_1], 0.));
+        let direction = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]).direction;
+        let covariance = covariance.map(|covariance| covariance::bound_to_free(sensor, state_vec, covariance));
+
+        FreeParameters::new(position, 0., &direction, state_vec[eQOP], covariance)
+    }
+
+    /// Bound state vector and covariance on `sensor`. The position has to be on the sensor,
+    /// otherwise `SensorError::NotOnSurface` is returned.
+    pub fn to_bound<T: Transform + Plane>(&self, sensor: &T) -> Result<(Vec5, Option<Mat5>), SensorError> {
+        let position = self.position();
+
+        let normal = sensor.plane_normal_vec();
+        if ((position - sensor.global_center()).dot(normal) / normal.norm()).abs() > DOT_PRODUCT_EPSILON {
+            return Err(SensorError::NotOnSurface(position))
+        }
+
+        let local = sensor.to_local(position);
+        let state_vec = Vec5::new(local.x, local.y, self.phi(), self.theta(), self.qop());
+
+        let covariance =
+            match &self.covariance {
+                Some(covariance) => Some(covariance::free_to_bound(sensor, &state_vec, covariance)?),
+                None => None
+            };
+
+        Ok((state_vec, covariance))
+    }
+
+    /// (x, y, z, t, tx, ty, tz, q/p)
+    pub fn vector(&self) -> &Vec8 {
+        &self.vector
+    }
+
+    pub fn covariance(&self) -> Option<&Mat8> {
+        self.covariance.as_ref()
+    }
+
+    pub fn position(&self) -> P3 {
+        P3::from(self.vector.fixed_rows::<U3>(eFREE_POS0).into_owned())
+    }
+
+    pub fn time(&self) -> Real {
+        self.vector[eFREE_TIME]
+    }
+
+    /// Unit vector along the momentum
+    pub fn direction(&self) -> Vec3 {
+        self.vector.fixed_rows::<U3>(eFREE_DIR0).into_owned()
+    }
+
+    pub fn qop(&self) -> Real {
+        self.vector[eFREE_QOP]
+    }
+
+    /// Sign of the charge
+    pub fn charge(&self) -> Real {
+        self.qop().signum()
+    }
+
+    /// Absolute momentum in GeV
+    pub fn momentum(&self) -> Real {
+        1. / self.qop().abs()
+    }
+
+    /// Momentum vector (px, py, pz) in GeV
+    pub fn momentum_vector(&self) -> Vec3 {
+        self.direction() * self.momentum()
+    }
+
+    /// Momentum transverse to the global z axis
+    pub fn transverse_momentum(&self) -> Real {
+        self.momentum_vector().xy().norm()
+    }
+
+    /// Azimuthal angle of the direction in (-pi, pi]
+    pub fn phi(&self) -> Real {
+        let direction = self.direction();
+        direction.y.atan2(direction.x)
+    }
+
+    /// Polar angle of the direction in [0, pi]
+    pub fn theta(&self) -> Real {
+        // acos(tz) loses the angle close to the z axis
+        let direction = self.direction();
+        direction.xy().norm().atan2(direction.z)
+    }
+
+    /// Pseudorapidity, -ln(tan(theta / 2))
+    pub fn eta(&self) -> Real {
+        self.direction().z.atanh()
+    }
+}
@@ -0,0 +1,157 @@
+use super::super::config::*;
+use super::super::error::{Error, FilterError, SensorError};
+use super::super::geometry::traits::{Transform, Plane};
+use super::super::geometry::tracking_geometry::TrackingGeometry;
+use super::angles::Angles;
+use super::covariance;
+use super::utils::SuperData;
+
+/// Line parallel to the global z axis through a reference point (the beam line, or a line
+/// through a vertex). Perigee parameters (d0, z0, phi, theta, q/p) describe a track at its
+/// point of closest approach to the line, relative to the reference point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerigeeSurface {
+    reference: P3
+}
+
+impl PerigeeSurface {
+
+    pub fn new(reference: P3) -> Self {
+        PerigeeSurface {reference}
+    }
+
+    /// Perigee surface around the global z axis
+    pub fn beamline() -> Self {
+        PerigeeSurface::new(P3::origin())
+    }
+
+    pub fn reference(&self) -> &P3 {
+        &self.reference
+    }
+
+    /// Signed path length along `direction` from `position` to the point of closest approach.
+    /// Tracks parallel to the line have no point of closest approach.
+    pub fn path_length(&self, position: &P3, direction: &Vec3) -> Result<Real, SensorError> {
+        let direction = direction.normalize();
+        let transverse = direction - (Vec3::z() * direction.z);
+        let sin_theta_squared = transverse.norm_squared();
+
+        if sin_theta_squared < Real::EPSILON {
+            return Err(SensorError::InvalidDirection(direction))
+        }
+
+        Ok(-(position - self.reference).dot(&transverse) / sin_theta_squared)
+    }
+
+    /// Perigee parameters of the straight track through `position` along `direction`
+    pub fn parameters(&self, position: &P3, direction: &Vec3, qop: Real) -> Result<Vec5, SensorError> {
+        covariance::global_to_perigee(&self.reference, position, direction, qop)
+    }
+
+    /// Point of closest approach and direction of the perigee parameters `state_vec`
+    pub fn to_global(&self, state_vec: &Vec5) -> (P3, Vec3) {
+        covariance::perigee_to_global(&self.reference, state_vec)
+    }
+}
+
+
+/// Track parameters and their covariance at the point of closest approach to a `PerigeeSurface`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerigeeParameters {
+    pub surface: PerigeeSurface,
+    pub state_vec: Vec5,
+    pub cov_mat: Mat5
+}
+
+impl PerigeeParameters {
+
+    /// Transverse impact parameter
+    pub fn d0(&self) -> Real {
+        self.state_vec[eLOC_0]
+    }
+
+    /// Longitudinal impact parameter, relative to the reference point
+    pub fn z0(&self) -> Real {
+        self.state_vec[eLOC_1]
+    }
+
+    /// Point of closest approach in the global frame
+    pub fn position(&self) -> P3 {
+        self.surface.to_global(&self.state_vec).0
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.surface.to_global(&self.state_vec).1
+    }
+}
+
+
+/// Extrapolates the bound state `state_vec` on `sensor` along its straight line to the point
+/// of closest approach to `surface`. The point may be on either side of the sensor.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::filter::perigee::{self, PerigeeSurface};
+/// use kalman_rs::geometry::Rectangle;
+///
+/// let translation = Mat4::new_translation(&Vec3::new(0., 0., 10.));
+/// let sensor = Rectangle::new(5., 5., translation, Mat4::identity()).unwrap();
+///
+/// // a track along the y-z diagonal, crossing the sensor at x = 1
+/// let state = Vec5::new(1., 2., PI / 2., PI / 4., 1.);
+/// let parameters = perigee::extrapolate(&sensor, &state, &(Mat5::identity() * 0.01), &PerigeeSurface::beamline()).unwrap();
+///
+/// assert!((parameters.d0() + 1.).abs() < 1e-12);
+/// assert!((parameters.z0() - 8.).abs() < 1e-12);
+/// ```
+pub fn extrapolate<T: Transform + Plane>(
+    sensor: &T,
+    state_vec: &Vec5,
+    cov_mat: &Mat5,
+    surface: &PerigeeSurface
+    ) -> Result<PerigeeParameters, SensorError> {
+
+    let direction = Angles::new_from_angles(state_vec[ePHI], state_vec[eTHETA]).direction;
+    let position = sensor.to_global(P3::new(state_vec[eLOC_0], state_vec[eLOC_1], 0.));
+
+    let distance = surface.path_length(&position, &direction)?;
+    let perigee_vec = surface.parameters(&position, &direction, state_vec[eQOP])?;
+
+    let jacobian =
+        covariance::free_to_perigee_jacobian(&perigee_vec)?
+        * covariance::free_transport_jacobian(distance)
+        * covariance::bound_to_free_jacobian(sensor, state_vec);
+
+    Ok(PerigeeParameters {surface: surface.clone(), state_vec: perigee_vec, cov_mat: jacobian * cov_mat * jacobian.transpose()})
+}
+
+/// Extrapolates the smoothed state of a fitted track that is closest to the line of `surface`,
+/// for tracks fitted in either direction. The sensors of the states are looked up in `geometry`
+/// with the ids of the track (see `SuperData::set_geometry_ids`). Returns
+/// `Err(SensorError::MissingGeometryId)` if a state has no id and
+/// `Err(FilterError::NotEnoughMeasurements)` if the track has no states.
+pub fn extrapolate_track(track: &SuperData, geometry: &TrackingGeometry, surface: &PerigeeSurface) -> Result<PerigeeParameters, Error> {
+    let smth = &track.smth;
+
+    let mut nearest = None;
+    for (index, state_vec) in smth.state_vec.iter().enumerate() {
+        let id = *smth.geometry_ids.get(index).ok_or(SensorError::MissingGeometryId(index))?;
+        let sensor = geometry.surface(id).ok_or(SensorError::UnknownGeometryId(id))?;
+
+        let offset = sensor.to_global(P3::new(state_vec[eLOC_0], state_vec[eLOC_1], 0.)) - surface.reference();
+        let distance = offset.xy().norm();
+
+        let closer = match nearest {
+            Some((_, _, nearest_distance)) => distance < nearest_distance,
+            None => true
+        };
+        if closer {
+            nearest = Some((index, sensor, distance));
+        }
+    }
+
+    let (index, sensor, _) = nearest.ok_or(FilterError::NotEnoughMeasurements)?;
+
+    Ok(extrapolate(sensor, &smth.state_vec[index], &smth.cov_mat[index], surface)?)
+}
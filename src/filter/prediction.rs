@@ -1,8 +1,11 @@
 use super::super::config::*;
 use super::super::error::*;
-use super::super::geometry::traits::{Plane, Transform};
+use super::super::geometry::traits::{Plane, Transform, Identified};
 use super::super::geometry::*;
-use super::angles;
+use super::super::geometry::bounds::BoundaryCheck;
+use super::super::geometry::intersection::{nearest_valid, Intersect, Intersection, NavigationDirection};
+use super::parameters::BoundTrackParameters;
+use super::{angles, jacobian};
 
 #[macro_use]
 use super::macros;
@@ -49,6 +52,36 @@ pub fn residual_vec(
     return diff;
 }
 
+/// Bound parameters predicted onto the next sensor, with the transport jacobian and the path
+/// length in the navigation direction
+#[derive(Debug, Clone)]
+pub struct PredictedParameters {
+    pub parameters: BoundTrackParameters,
+    pub jacobian: Mat5,
+    pub path_length: Real
+}
+
+/// Predicts straight track parameters on `start_sensor` onto `end_sensor`, propagating along
+/// (`Forward`) or against (`Backward`) the momentum. The covariance is transported if the
+/// parameters have one. Fails like `linear_state_vector_in_direction`.
+pub fn linear_parameters<T: Transform + Plane + Identified>(
+    start_sensor: &T,
+    end_sensor: &T,
+    parameters: &BoundTrackParameters,
+    boundary_check: &BoundaryCheck,
+    navigation: NavigationDirection
+    ) -> Result<PredictedParameters, SensorError> {
+
+    let crossing = linear_crossing(end_sensor, &parameters.position(start_sensor), &parameters.direction(), boundary_check, navigation)?;
+
+    let jacobian = jacobian::linear_in_direction(parameters.vector(), crossing.path_length, start_sensor, end_sensor, navigation);
+    let covariance = parameters.covariance().map(|covariance| covariance_matrix(&jacobian, covariance));
+
+    let predicted = BoundTrackParameters::new(end_sensor.geometry_id(), crossing.local, parameters.phi(), parameters.theta(), parameters.qop(), covariance);
+
+    Ok(PredictedParameters {parameters: predicted, jacobian, path_length: crossing.path_length})
+}
+
 /// Calculates the predicted location of the hit on the following sensor
 // based on this equation set https://i.imgur.com/mWC0qkj.png
 pub fn linear_state_vector<T: Transform + Plane>(
@@ -57,67 +90,106 @@ pub fn linear_state_vector<T: Transform + Plane>(
     prev_filt_state_vec: &Vec5,
     ) -> Result<(Vec5, Real), SensorError> {
 
-    // println!{"IN PREDICTION: filtered state vec:"}
-    // dbg!{prev_filt_state_vec};
-    
-    get_unchecked!{
-        prev_filt_state_vec[eLOC_0] => start_local_x_hit,
-        prev_filt_state_vec[eLOC_1] => start_local_y_hit,
-        prev_filt_state_vec[eTHETA] => theta,
-        prev_filt_state_vec[ePHI] => phi
-    }
+    linear_state_vector_with_check(start_sensor, end_sensor, prev_filt_state_vec, &BoundaryCheck::Strict)
+}
+
+/// Same as `linear_state_vector`, but predictions that land outside of the end sensor are
+/// accepted if they are within the tolerance of `boundary_check`.
+pub fn linear_state_vector_with_check<T: Transform + Plane>(
+    start_sensor: &T, 
+    end_sensor: &T, 
+    prev_filt_state_vec: &Vec5,
+    boundary_check: &BoundaryCheck
+    ) -> Result<(Vec5, Real), SensorError> {
 
-    let mut ang = angles::Angles::new_from_angles(*phi, *theta);
+    linear_state_vector_in_direction(start_sensor, end_sensor, prev_filt_state_vec, boundary_check, NavigationDirection::Forward)
+}
 
-    let start_local_point = P3::new(*start_local_x_hit, *start_local_y_hit, 0.0);
-    let start_global_point = start_sensor.to_global(start_local_point);
+/// Same as `linear_state_vector_with_check`, propagating along (`Forward`) or against
+/// (`Backward`) the momentum. The angles of the state vector are always the direction of
+/// the momentum, the returned distance is the path length in the navigation direction.
+pub fn linear_state_vector_in_direction<T: Transform + Plane>(
+    start_sensor: &T, 
+    end_sensor: &T, 
+    prev_filt_state_vec: &Vec5,
+    boundary_check: &BoundaryCheck,
+    navigation: NavigationDirection
+    ) -> Result<(Vec5, Real), SensorError> {
 
+    let direction = angles::Angles::new_from_angles(prev_filt_state_vec[ePHI], prev_filt_state_vec[eTHETA]).direction;
+    let start_global_point = start_sensor.to_global(P3::new(prev_filt_state_vec[eLOC_0], prev_filt_state_vec[eLOC_1], 0.));
 
-    // used so we can be generic over planar sensors
-    let normal = end_sensor.plane_normal_vec();
+    let crossing = linear_crossing(end_sensor, &start_global_point, &direction, boundary_check, navigation)?;
 
-    let end_plane_constant = end_sensor.plane_constant();
+    let mut new_state_vec = *prev_filt_state_vec;
+    new_state_vec[eLOC_0] = crossing.local.x;
+    new_state_vec[eLOC_1] = crossing.local.y;
 
-    // dbg!{end_plane_constant};
+    Ok((new_state_vec, crossing.path_length))
+}
 
-    // find predicted 
-    let (global_pred_point, global_distance) = 
-        linear_global_hit_estimation(
-            &normal,
-            &start_global_point,
-            &mut ang,
-            &end_plane_constant
-        );
+/// Nearest crossing of a straight track with `end_sensor` in front of the particle, the sensor
+/// can not be hit backwards
+fn linear_crossing<T: Transform + Plane>(
+    end_sensor: &T,
+    position: &P3,
+    direction: &Vec3,
+    boundary_check: &BoundaryCheck,
+    navigation: NavigationDirection
+    ) -> Result<Intersection, SensorError> {
+
+    let intersections = end_sensor.intersect_with_check(position, direction, navigation, boundary_check);
+
+    match nearest_valid(&intersections) {
+        Some(intersection) => Ok(intersection.clone()),
+        // the intersections are ordered by path length
+        None => match intersections.iter().find(|intersection| intersection.path_length >= 0.) {
+            Some(outside) => Err(SensorError::OutsideSensorBounds(outside.local)),
+            None => Err(SensorError::InvalidDirection(*direction))
+        }
+    }
+}
 
-    let local_pred_point  = end_sensor.to_local(global_pred_point);
+/// Calculates the predicted point of closest approach on the following straw. The local
+/// coordinates of the state vector on straws are (signed distance, position along the wire).
+pub fn linear_straw_state_vector(
+    start_straw: &Straw,
+    end_straw: &Straw,
+    prev_filt_state_vec: &Vec5,
+    ) -> Result<(Vec5, Real), SensorError> {
 
+    let direction = angles::Angles::new_from_angles(prev_filt_state_vec[ePHI], prev_filt_state_vec[eTHETA]).direction;
+    let start_local_point = P2::new(prev_filt_state_vec[eLOC_0], prev_filt_state_vec[eLOC_1]);
 
-    // print!{"PREDICTIONS", global_pred_point, local_pred_point}
+    let start_global_point =
+        match start_straw.to_global(&start_local_point, &direction) {
+            Some(point) => point,
+            None => return Err(SensorError::InvalidDirection(direction))
+        };
 
+    let (global_pred_point, path_length) =
+        match end_straw.point_of_closest_approach(&start_global_point, &direction) {
+            Some(pca) => pca,
+            None => return Err(SensorError::InvalidDirection(direction))
+        };
 
-    // check if the predicted point is on the sensor
-    if end_sensor.inside(&local_pred_point) {
-        // might be able to avoid cloning here
-        let mut new_state_vec = prev_filt_state_vec.clone();
+    let local_pred_point = end_straw.to_local(&global_pred_point, &direction);
 
-        change_mat_val!{new_state_vec;
-            [eLOC_0, 0] => local_pred_point.x,
-            [eLOC_1, 0] => local_pred_point.y
-        }
-        // println!{"full predicted state vector"}
-        // dbg!{&new_state_vec};
+    if end_straw.inside(&local_pred_point) {
+        let mut new_state_vec = *prev_filt_state_vec;
+        new_state_vec[eLOC_0] = local_pred_point.x;
+        new_state_vec[eLOC_1] = local_pred_point.y;
 
-        Ok((new_state_vec, global_distance))
+        Ok((new_state_vec, path_length))
     }
     else {
         Err(SensorError::OutsideSensorBounds(local_pred_point))
     }
-
 }
 
-/// Encapsultes all the global calculations for `linear_state_vector` without 
-/// needing start and end sensors. This is done so it is easier to construct unit tests
-/// for this particularly troubling part of code.
+/// Point where a straight track crosses a plane and the (unsigned) distance to it. Only
+/// works for planes and does not tell if the plane is behind the particle, use
+/// `geometry::intersection::Intersect` instead.
 pub fn linear_global_hit_estimation(
     plane_normal_vector: &Vec3,
     start_global_point: &P3,
@@ -1,8 +1,34 @@
 use super::super::config::*;
+use super::super::error::{FilterError, MatrixError};
+use super::parameters::BoundTrackParameters;
+use super::angles;
 
 #[macro_use]
 use super::macros;
 
+/// Smoothed parameters of a sensor from its filtered parameters, the jacobian of the transport
+/// onto the next sensor, the prediction onto the next sensor and the smoothed parameters there.
+/// Returns `Err(FilterError::MissingCovariance)` if one of the parameters has no covariance and
+/// `Err(FilterError::Matrix)` if the covariance of the prediction can not be inverted.
+pub fn smooth(
+    filtered: &BoundTrackParameters,
+    jacobian: &Mat5,
+    next_predicted: &BoundTrackParameters,
+    next_smoothed: &BoundTrackParameters
+    ) -> Result<BoundTrackParameters, FilterError> {
+
+    let filt_cov_mat = filtered.covariance().ok_or(FilterError::MissingCovariance)?;
+    let next_pred_cov_mat = next_predicted.covariance().ok_or(FilterError::MissingCovariance)?;
+    let next_smth_cov_mat = next_smoothed.covariance().ok_or(FilterError::MissingCovariance)?;
+
+    let gain_mat = filt_cov_mat * jacobian.transpose() * next_pred_cov_mat.try_inverse().ok_or(MatrixError::NonInvertible)?;
+
+    let state_vec = state_vector(filtered.vector(), &gain_mat, next_smoothed.vector(), next_predicted.vector());
+    let cov_mat = covariance_matrix(filt_cov_mat, &gain_mat, next_smth_cov_mat, next_pred_cov_mat);
+
+    Ok(BoundTrackParameters::from_vector(filtered.surface(), state_vec, Some(cov_mat)))
+}
+
 pub fn gain_matrix(
     curr_filt_cov_mat: &Mat5,   //filt C
     jacobian: &Mat5,            // F_k or J
@@ -22,9 +48,9 @@ pub fn state_vector(
     prev_filt_state_vec: &Vec5      // prev filt x
     ) -> Vec5 {                     // smth x
     
-    let parens = prev_smth_state_vec - prev_filt_state_vec;
+    let parens = angles::state_difference(prev_smth_state_vec, prev_filt_state_vec);
     let prod = gain_mat * parens;
-    let sum =  curr_filt_state_vec + prod;
+    let sum =  angles::normalize_state(&(curr_filt_state_vec + prod));
 
     // print!{
     //     "SMOOTHING STATE VECTOR:",
@@ -71,7 +97,7 @@ pub fn residual_vec(
     ) -> Vec2 {                     // smth r
     
     let prod = sensor_mapping_mat * curr_smth_state_vec;
-    let sum = measurement_vec + prod;
+    let diff = measurement_vec - prod;
     
-    return sum;
+    return diff;
 }
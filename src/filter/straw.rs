@@ -0,0 +1,215 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::super::geometry::{Straw, GeometryId};
+use super::super::geometry::traits::Identified;
+
+use super::{angles, prediction, smoothing, jacobian, utils};
+
+/// Result of filtering a single drift radius measurement
+#[derive(Debug, Clone)]
+pub struct DriftUpdate {
+    pub state_vec: Vec5,    // filt x
+    pub cov_mat: Mat5,      // filt C
+    pub res: Real,          // filt r
+    pub res_var: Real,      // filt R
+    pub chi_squared: Real,
+    pub sign: Real          // side of the wire the hit was assigned to (+1 / -1)
+}
+
+
+/// Kalman gain for a 1D measurement of the signed distance (H = [1, 0, 0, 0, 0])
+pub fn kalman_gain(
+    pred_covariance: &Mat5,     // C
+    variance: Real              // V
+    ) -> Vec5 {                 // K
+
+    pred_covariance.column(eLOC_0) / (variance + pred_covariance[(eLOC_0, eLOC_0)])
+}
+
+
+/// Filters the predicted state with a signed drift distance
+pub fn update(
+    pred_state_vec: &Vec5,      // pred x
+    pred_covariance: &Mat5,     // pred C
+    measurement: Real,          // m_k
+    variance: Real              // V
+    ) -> DriftUpdate {
+
+    let gain = kalman_gain(pred_covariance, variance);
+
+    let pred_residual = measurement - pred_state_vec[eLOC_0];
+    let state_vec = angles::normalize_state(&(pred_state_vec + (gain * pred_residual)));
+
+    // (I - KH) where only the first column of KH is non zero
+    let mut parens = Mat5::identity();
+    parens.column_mut(eLOC_0).copy_from(&(Vec5::x() - gain));
+
+    let cov_mat = parens * pred_covariance;
+
+    let res = measurement - state_vec[eLOC_0];
+    let res_var = variance - cov_mat[(eLOC_0, eLOC_0)];
+
+    DriftUpdate {
+        state_vec,
+        cov_mat,
+        res,
+        res_var,
+        chi_squared: (res * res) / res_var,
+        sign: if measurement < 0. {-1.} else {1.}
+    }
+}
+
+
+/// A drift tube only measures the unsigned distance to the wire. Both sides of the
+/// wire are tested and the assignment with the lowest chi squared increment is kept.
+pub fn resolve_ambiguity(
+    pred_state_vec: &Vec5,      // pred x
+    pred_covariance: &Mat5,     // pred C
+    drift_radius: Real,         // unsigned m_k
+    variance: Real              // V
+    ) -> DriftUpdate {
+
+    let right = update(pred_state_vec, pred_covariance, drift_radius.abs(), variance);
+    let left = update(pred_state_vec, pred_covariance, -drift_radius.abs(), variance);
+
+    if left.chi_squared < right.chi_squared {left}
+    else {right}
+}
+
+
+/// Storage for the states of a straw track at one stage (pred / filt / smth) of the filter
+#[derive(Debug)]
+pub struct StrawData {
+    pub state_vec: Vec<Vec5>,
+    pub cov_mat: Vec<Mat5>,
+    pub res: Vec<Real>,
+    pub res_var: Vec<Real>
+}
+
+impl StrawData {
+    fn with_capacity(capacity: usize) -> Self {
+        StrawData {
+            state_vec: Vec::with_capacity(capacity),
+            cov_mat: Vec::with_capacity(capacity),
+            res: Vec::with_capacity(capacity),
+            res_var: Vec::with_capacity(capacity)
+        }
+    }
+
+    fn push(&mut self, state_vec: Vec5, cov_mat: Mat5, res: Real, res_var: Real) {
+        self.state_vec.push(state_vec);
+        self.cov_mat.push(cov_mat);
+        self.res.push(res);
+        self.res_var.push(res_var);
+    }
+}
+
+
+/// Predicted, filtered and smoothed states of a straw track along with the
+/// side of each wire that the filter assigned the hit to and the id of each straw
+#[derive(Debug)]
+pub struct StrawSuperData {
+    pub smth: StrawData,
+    pub filt: StrawData,
+    pub pred: StrawData,
+    pub signs: Vec<Real>,
+    pub geometry_ids: Vec<GeometryId>
+}
+
+
+/// Linear kalman filter for tracks crossing a sequence of straws with unsigned drift radius
+/// measurements. `initial_seed_vec` is the state at the first straw, the sign of its first local
+/// coordinate is ignored since the ambiguity is resolved for every straw. Returns
+/// `Err(FilterError::LengthMismatch)` if the input vectors differ in length and
+/// `Err(FilterError::NotEnoughMeasurements)` if there are no straws.
+pub fn run(
+    measurement_variance_vector: &[Real],   // V of each drift radius
+    drift_radius_vector: &[Real],           // unsigned measured distance to the wire
+    straw_vector: &[Straw],                 // straws in the order that they are crossed
+    initial_seed_vec: &Vec5
+    ) -> Result<StrawSuperData, FilterError> {
+
+    let len = straw_vector.len();
+
+    if (measurement_variance_vector.len() != len) || (drift_radius_vector.len() != len) {
+        return Err(FilterError::LengthMismatch)
+    }
+    if len == 0 {
+        return Err(FilterError::NotEnoughMeasurements)
+    }
+
+    let mut pred = StrawData::with_capacity(len);
+    let mut filt = StrawData::with_capacity(len);
+    let mut jacobians : Vec<Mat5> = Vec::with_capacity(len);
+    let mut signs = Vec::with_capacity(len);
+
+    let mut pred_state_vec = *initial_seed_vec;
+    let mut pred_cov_mat = utils::seed_covariance();
+
+    for i in 0..len {
+
+        if i > 0 {
+            let prev_state_vec = &filt.state_vec[i-1];
+            let prev_cov_mat = &filt.cov_mat[i-1];
+
+            let (state_vec, distance) =
+                prediction::linear_straw_state_vector(&straw_vector[i-1], &straw_vector[i], prev_state_vec)?;
+            let jacobian = jacobian::linear_straw(prev_state_vec, distance, &straw_vector[i-1], &straw_vector[i])?;
+
+            pred_state_vec = state_vec;
+            pred_cov_mat = prediction::covariance_matrix(&jacobian, prev_cov_mat);
+            jacobians.push(jacobian);
+        }
+
+        let variance = measurement_variance_vector[i];
+        let radius = drift_radius_vector[i];
+
+        // predicted residual is taken with respect to the side of the wire the prediction is on
+        let pred_side = if pred_state_vec[eLOC_0] < 0. {-radius.abs()} else {radius.abs()};
+        pred.push(
+            pred_state_vec,
+            pred_cov_mat,
+            pred_side - pred_state_vec[eLOC_0],
+            variance + pred_cov_mat[(eLOC_0, eLOC_0)]
+        );
+
+        let filtered = resolve_ambiguity(&pred_state_vec, &pred_cov_mat, radius, variance);
+
+        signs.push(filtered.sign);
+        filt.push(filtered.state_vec, filtered.cov_mat, filtered.res, filtered.res_var);
+    }
+
+    // backwards smoothing pass
+    let mut smth = StrawData::with_capacity(len);
+
+    let last = len - 1;
+    smth.push(filt.state_vec[last], filt.cov_mat[last], filt.res[last], filt.res_var[last]);
+
+    for i in (0..last).rev() {
+        // the most recent smoothed state is the one at i+1
+        let next_smth_state_vec = smth.state_vec[last - (i+1)];
+        let next_smth_cov_mat = smth.cov_mat[last - (i+1)];
+
+        let gain_matrix = smoothing::gain_matrix(&filt.cov_mat[i], &jacobians[i], &pred.cov_mat[i+1]);
+        let state_vec = smoothing::state_vector(&filt.state_vec[i], &gain_matrix, &next_smth_state_vec, &pred.state_vec[i+1]);
+        let cov_mat = smoothing::covariance_matrix(&filt.cov_mat[i], &gain_matrix, &next_smth_cov_mat, &pred.cov_mat[i+1]);
+
+        let measurement = signs[i] * drift_radius_vector[i].abs();
+
+        smth.push(
+            state_vec,
+            cov_mat,
+            measurement - state_vec[eLOC_0],
+            measurement_variance_vector[i] - cov_mat[(eLOC_0, eLOC_0)]
+        );
+    }
+
+    smth.state_vec.reverse();
+    smth.cov_mat.reverse();
+    smth.res.reverse();
+    smth.res_var.reverse();
+
+    let geometry_ids = straw_vector.iter().map(|straw| straw.geometry_id()).collect();
+
+    Ok(StrawSuperData {smth, filt, pred, signs, geometry_ids})
+}
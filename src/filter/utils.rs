@@ -1,6 +1,9 @@
 use nalgebra as na;
 use super::super::config::*;
 use super::super::geometry::traits::{Plane, Transform};
+use super::super::geometry::GeometryId;
+use super::parameters::BoundTrackParameters;
+use super::finite_difference::JacobianComparison;
 
 #[macro_use]
 use super::macros;
@@ -68,7 +71,10 @@ pub fn seed_state_vec_from_points(
 
     let xy_projection = Vec3::new(*x, *y, 0.);
 
-    let _phi = xy_projection.angle(&x_axis);
+    // phi is not defined when heading straight along the z axis, it is taken to be 0
+    let _phi =
+        if xy_projection.norm() > 0. {xy_projection.angle(&x_axis)}
+        else {0.};
 
     // if we are in quadrants 3/4 we need to adjust for the angle in _phi
     let phi = 
@@ -119,7 +125,8 @@ pub struct Data {
     pub state_vec: Vec<Vec5>,
     pub cov_mat: Vec<Mat5>,
     pub res_mat: Vec<Mat2>,
-    pub res_vec: Vec<Vec2>
+    pub res_vec: Vec<Vec2>,
+    pub geometry_ids: Vec<GeometryId>   // surface each state is on. empty if not known
 }
 
 impl Data{
@@ -131,7 +138,36 @@ impl Data{
         return Data{state_vec: state_vec, 
                             cov_mat: cov_mat, 
                             res_mat: res_mat, 
-                            res_vec:res_vec}
+                            res_vec:res_vec,
+                            geometry_ids: Vec::new()}
+    }
+
+    /// Data of a sequence of bound parameters, `res_mat` and `res_vec` are their residuals.
+    /// Parameters without a covariance are stored with a zero covariance.
+    pub fn from_parameters(parameters: &[BoundTrackParameters], res_mat: Vec<Mat2>, res_vec: Vec<Vec2>) -> Self {
+        let mut data = Data::new(
+            parameters.iter().map(|parameters| *parameters.vector()).collect(),
+            parameters.iter().map(|parameters| parameters.covariance().cloned().unwrap_or_else(Mat5::zeros)).collect(),
+            res_mat,
+            res_vec
+        );
+        data.geometry_ids = parameters.iter().map(|parameters| parameters.surface()).collect();
+
+        data
+    }
+
+    /// Index of the state on the surface with the given id
+    pub fn index_of(&self, id: GeometryId) -> Option<usize> {
+        self.geometry_ids.iter().position(|state_id| *state_id == id)
+    }
+
+    /// Parameters of state `index` with its covariance. The surface is the default id if the
+    /// ids of the states are not known.
+    pub fn parameters(&self, index: usize) -> Option<BoundTrackParameters> {
+        let state_vec = self.state_vec.get(index)?;
+        let surface = self.geometry_ids.get(index).cloned().unwrap_or_default();
+
+        Some(BoundTrackParameters::from_vector(surface, *state_vec, self.cov_mat.get(index).cloned()))
     }
 }
 
@@ -142,14 +178,25 @@ impl Data{
 pub struct SuperData{
     pub smth: Data,
     pub filt: Data,
-    pub pred: Data
+    pub pred: Data,
+    /// analytic vs numerical transport jacobian of every prediction, only filled when the
+    /// fitter is run with `FitterOptions::jacobian_check`
+    pub jacobian_checks: Vec<JacobianComparison>
 }
 impl SuperData{
     pub fn new(smth: Data, filt: Data, pred: Data) -> Self{
         SuperData{
             smth: smth,
             filt: filt,
-            pred: pred
+            pred: pred,
+            jacobian_checks: Vec::new()
         }
     }
+
+    /// Sets the id of the surface of each state in the smoothed, filtered and predicted data
+    pub fn set_geometry_ids(&mut self, ids: Vec<GeometryId>) {
+        self.smth.geometry_ids = ids.clone();
+        self.filt.geometry_ids = ids.clone();
+        self.pred.geometry_ids = ids;
+    }
 }
\ No newline at end of file
@@ -1,8 +1,8 @@
 use nalgebra::base::Unit;
 use super::super::config::*;
 use super::super::geometry;
-use geometry::traits::{Plane, Transform};
-use geometry::Rectangle;
+use geometry::traits::{Identified, Plane, Transform};
+use geometry::{GeometryId, Rectangle};
 
 use super::super::filter;
 use filter::prediction;
@@ -13,6 +13,10 @@ use rand_distr::{Normal, Distribution};
 
 use super::structs::KFData;
 
+use geometry::navigator::{Navigator, SurfaceCrossing};
+use geometry::bvh::Helix;
+use filter::measurement::Measurement;
+
 
 pub fn generate_track<T:Distribution<Real>>(
     num_sensors: u32, 
@@ -62,12 +66,14 @@ pub fn generate_track<T:Distribution<Real>>(
             1.
         );
 
-    // generate sensors along x axis
+    // generate sensors along x axis, each sensor is its own layer of a single volume
     let mut sensor_vec = Vec::new();
     for i in 0..num_sensors {
         let ip1 = (i + 1) as Real;
         let x_loc_of_sensor = ip1 * sensor_distance;
-        sensor_vec.push(gen_sensor(x_loc_of_sensor));
+        let mut sensor = gen_sensor(x_loc_of_sensor);
+        sensor.set_geometry_id(GeometryId::new(1, (i + 1) as u16, 1));
+        sensor_vec.push(sensor);
     }
 
     // print!{sensor_vec.len()}
@@ -143,6 +149,56 @@ pub fn generate_track<T:Distribution<Real>>(
 }
 
 
+/// Generates the truth crossings of a straight track through the geometry of a navigator and
+/// the measurements on each crossed sensor, smeared by `point_std_dev` in both local coordinates.
+pub fn generate_geometry_track(
+    navigator: &Navigator,
+    start: &P3,
+    direction: &Vec3,
+    rng: &mut SmallRng,
+    point_std_dev: Real
+    ) -> (Vec<SurfaceCrossing>, Vec<Measurement>) {
+
+    let crossings = navigator.crossings(start, direction);
+    let measurements = smear_crossings(&crossings, rng, point_std_dev);
+
+    (crossings, measurements)
+}
+
+/// Same as `generate_geometry_track` for a track bending in a magnetic field. The helix is
+/// followed for `max_path` in steps of `step`.
+pub fn generate_geometry_helix(
+    navigator: &Navigator,
+    helix: &Helix,
+    max_path: Real,
+    step: Real,
+    rng: &mut SmallRng,
+    point_std_dev: Real
+    ) -> (Vec<SurfaceCrossing>, Vec<Measurement>) {
+
+    let crossings = navigator.helix_crossings(helix, max_path, step);
+    let measurements = smear_crossings(&crossings, rng, point_std_dev);
+
+    (crossings, measurements)
+}
+
+/// Smears the local position of each crossing into a measurement with a diagonal covariance
+pub fn smear_crossings(crossings: &[SurfaceCrossing], rng: &mut SmallRng, point_std_dev: Real) -> Vec<Measurement> {
+    let covariance = Mat2::identity() * point_std_dev.powi(2);
+
+    crossings.iter()
+        .map(|crossing| {
+            let x_distr = Normal::new(crossing.local.x, point_std_dev).expect("std dev err");
+            let y_distr = Normal::new(crossing.local.y, point_std_dev).expect("std dev err");
+
+            let local = Vec2::new(x_distr.sample(rng), y_distr.sample(rng));
+
+            Measurement::new(crossing.geometry_id, local, covariance)
+        })
+        .collect()
+}
+
+
 fn smear_state_vector(rng: &mut SmallRng, std_dev: Real, state_vec: &Vec5) -> Vec5{
     let mut new_vec = Vec5::zeros();
 
@@ -7,7 +7,7 @@ use setup::generate_track;
 
 use super::structs::{KFData, Residuals, State};
 
-use filter::{linear, utils::SuperData};
+use filter::{angles, linear, utils::SuperData};
 
 use rand::{thread_rng, SeedableRng};
 use rand::rngs::SmallRng;
@@ -173,6 +173,33 @@ pub fn truth_kf_output_residuals(
 
 }
 
+/// Residuals of the truth direction vs the KF output direction as (phi, theta) pairs.
+/// The phi residuals are the shortest turn between the angles, so tracks around
+/// phi = pi do not show 2 pi jumps.
+pub fn truth_kf_angle_residuals(
+    output: &[(KFData<Rectangle>, SuperData)]
+    ) -> Vec<Residuals> {
+
+    output.iter()
+        .map(|(truth_data, kf_out)| {
+            let (phi, theta) = truth_data.original_angles;
+            let truth = Vec5::new(0., 0., phi, theta, 0.);
+
+            let angle_residual = |state_vec: &Vec5| {
+                let diff = angles::state_difference(&truth, state_vec);
+                Vec2::new(diff[ePHI], diff[eTHETA])
+            };
+
+            let grouped_residuals =
+                izip!{&kf_out.smth.state_vec, &kf_out.filt.state_vec, &kf_out.pred.state_vec}
+                .map(|(smth, filt, pred)| (angle_residual(smth), angle_residual(filt), angle_residual(pred)))
+                .collect::<Vec<_>>();
+
+            Residuals::new_grouped(grouped_residuals)
+        })
+        .collect::<Vec<_>>()
+}
+
 fn vec5_to_vec2(vector_sv: Vec<Vec5>) -> Vec<Vec2> {
     vector_sv.into_iter()
         .map(|x| state_vec_to_hit_vec(x))
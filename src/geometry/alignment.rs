@@ -0,0 +1,411 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::description::{file_format, Format};
+use super::surface::Surface;
+use super::plane_surface::PlaneSurface;
+use super::disc::Disc;
+use super::tracking_geometry::TrackingGeometry;
+use super::material::SurfaceMaterial;
+use super::id::GeometryId;
+use super::traits::{Transform, Plane, Identified, Outline, HasMaterial};
+use super::super::config::*;
+use super::super::error::*;
+
+/// Small correction to the placement of a surface. Both parts are given in the local frame
+/// of the surface: the surface is first rotated about its own center and then moved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlignmentDelta {
+    /// shift of the center along the local x, y and z axes
+    pub translation: Vec3,
+    /// rotation angles about the local x, y and z axes (applied in that order)
+    pub rotation: Vec3
+}
+
+impl Default for AlignmentDelta {
+    fn default() -> Self {
+        AlignmentDelta {translation: Vec3::zeros(), rotation: Vec3::zeros()}
+    }
+}
+
+impl AlignmentDelta {
+    pub fn new(translation: Vec3, rotation: Vec3) -> Self {
+        AlignmentDelta {translation, rotation}
+    }
+
+    /// Local -> corrected local transformation
+    pub fn transform(&self) -> Aff3 {
+        Aff3::from_matrix_unchecked(Mat4::new_translation(&self.translation) * self.rotation_matrix())
+    }
+
+    /// Rotation part of the correction as a 4x4 matrix
+    pub fn rotation_matrix(&self) -> Mat4 {
+        Rot3::from_euler_angles(self.rotation.x, self.rotation.y, self.rotation.z).to_homogeneous()
+    }
+}
+
+
+/// A sensor moved by an alignment correction. The nominal sensor is left unchanged and all
+/// the transformations are corrected on the fly, so the same type can be used to simulate
+/// or reconstruct with a misaligned detector.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::geometry::Rectangle;
+/// use kalman_rs::geometry::alignment::{Aligned, AlignmentDelta};
+/// use kalman_rs::geometry::traits::*;
+///
+/// let nominal = Rectangle::new(2., 2., Mat4::identity(), Mat4::identity()).unwrap();
+///
+/// // moved 0.1 along its local x axis
+/// let delta = AlignmentDelta::new(Vec3::new(0.1, 0., 0.), Vec3::zeros());
+/// let sensor = Aligned::new(nominal, delta);
+///
+/// let global = sensor.to_global(P3::origin());
+/// assert!((global.x - 0.1).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Aligned<T: Transform + Plane> {
+    sensor: T,
+    delta: AlignmentDelta,
+
+    correction: Aff3,           // nominal global => corrected global
+    inverse_correction: Aff3,   // corrected global => nominal global
+    correction_rot: Mat3,
+
+    center_global: P3,
+    normal: Vec3,
+    to_global_rot: Mat4,
+    to_local_rot: Mat4
+}
+
+impl <T: Transform + Plane> Aligned<T> {
+
+    pub fn new(sensor: T, delta: AlignmentDelta) -> Self {
+        let mut aligned = Aligned {
+            sensor,
+            delta,
+            correction: Aff3::identity(),
+            inverse_correction: Aff3::identity(),
+            correction_rot: Mat3::identity(),
+            center_global: P3::origin(),
+            normal: Vec3::zeros(),
+            to_global_rot: Mat4::identity(),
+            to_local_rot: Mat4::identity()
+        };
+
+        aligned.set_delta(delta);
+        aligned
+    }
+
+    /// Replaces the correction of the sensor
+    pub fn set_delta(&mut self, delta: AlignmentDelta) {
+        // nominal local => global, from the placement of the sensor
+        let center = *self.sensor.global_center();
+        let rotation = *self.sensor.rotation_to_global();
+        let nominal = Aff3::from_matrix_unchecked(Mat4::new_translation(&center.coords) * rotation);
+
+        // moving in the local frame is the same as N * D * N^-1 in the global frame
+        self.correction = nominal * delta.transform() * nominal.inverse();
+        self.inverse_correction = self.correction.inverse();
+        self.correction_rot = self.correction.matrix().fixed_slice::<U3, U3>(0, 0).into_owned();
+
+        self.center_global = self.correction * center;
+        self.normal = self.correction_rot * self.sensor.plane_normal_vec();
+        self.to_global_rot = rotation * delta.rotation_matrix();
+        self.to_local_rot = self.to_global_rot.transpose();
+        self.delta = delta;
+    }
+
+    pub fn delta(&self) -> &AlignmentDelta {
+        &self.delta
+    }
+
+    /// Fetches the sensor at its nominal placement
+    pub fn nominal(&self) -> &T {
+        &self.sensor
+    }
+
+    pub fn into_nominal(self) -> T {
+        self.sensor
+    }
+}
+
+impl <T: Transform + Plane> Transform for Aligned<T> {
+
+    fn to_global(&self, input_point: P3) -> P3 {
+        self.correction * self.sensor.to_global(input_point)
+    }
+
+    fn to_local(&self, input_point: P3) -> P2 {
+        self.sensor.to_local(self.inverse_correction * input_point)
+    }
+
+    fn inside(&self, input: &P2) -> bool {
+        self.sensor.inside(input)
+    }
+
+    fn distance_to_boundary(&self, input: &P2) -> Real {
+        self.sensor.distance_to_boundary(input)
+    }
+
+    fn rotation_to_global(&self) -> &Mat4 {
+        &self.to_global_rot
+    }
+
+    fn rotation_to_local(&self) -> &Mat4 {
+        &self.to_local_rot
+    }
+
+    fn local_to_global_derivative(&self, local: &P2) -> Mat3x2 {
+        self.correction_rot * self.sensor.local_to_global_derivative(local)
+    }
+
+    fn global_to_local_derivative(&self, local: &P2) -> Mat2x3 {
+        self.sensor.global_to_local_derivative(local) * self.correction_rot.transpose()
+    }
+
+    fn align_measurement(&self, measurement: &Vec2, reference: &Vec2) -> Vec2 {
+        self.sensor.align_measurement(measurement, reference)
+    }
+}
+
+impl <T: Transform + Plane> Plane for Aligned<T> {
+
+    fn on_plane(&self, input_point: &P3) -> bool {
+        let pv = input_point - self.center_global;
+
+        self.normal.dot(&pv).abs() <= DOT_PRODUCT_EPSILON
+    }
+
+    fn plane_normal_vec(&self) -> &Vec3 {
+        &self.normal
+    }
+
+    fn plane_constant(&self) -> Real {
+        self.normal.dot(&self.center_global.coords)
+    }
+
+    fn global_center(&self) -> &P3 {
+        &self.center_global
+    }
+}
+
+impl <T: Transform + Plane + Identified> Identified for Aligned<T> {
+    fn geometry_id(&self) -> GeometryId {
+        self.sensor.geometry_id()
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        self.sensor.set_geometry_id(id);
+    }
+}
+
+impl <T: Transform + Plane + HasMaterial> HasMaterial for Aligned<T> {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        self.sensor.material()
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        self.sensor.set_material(material);
+    }
+}
+
+impl <T: Transform + Plane + Outline> Outline for Aligned<T> {
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3> {
+        self.sensor.global_outline(arc_segments).iter()
+            .map(|point| self.correction * point)
+            .collect()
+    }
+}
+
+
+/// Builds a new surface at the corrected placement. Unlike `Aligned` the result is a plain
+/// `Surface`, so it can be put in a `TrackingGeometry`.
+pub fn align_surface(surface: &Surface, delta: &AlignmentDelta) -> Result<Surface, GeometryError> {
+    let (center, rotation) = (*surface.global_center(), *surface.rotation_to_global());
+
+    let rotation_3 = rotation.fixed_slice::<U3, U3>(0, 0).into_owned();
+    let translation = Mat4::new_translation(&(center.coords + (rotation_3 * delta.translation)));
+    let rotation = rotation * delta.rotation_matrix();
+
+    let mut aligned =
+        match surface {
+            Surface::Plane(sensor) => Surface::Plane(PlaneSurface::new(sensor.bounds().clone(), translation, rotation)?),
+            Surface::Disc(sensor) => {
+                let b = sensor.bounds();
+                Surface::Disc(Disc::new(b.r_min(), b.r_max(), b.average_phi(), b.half_phi(), translation, rotation)?)
+            }
+        };
+
+    aligned.set_geometry_id(surface.geometry_id());
+    aligned.set_material(surface.material().cloned());
+
+    Ok(aligned)
+}
+
+
+/// Alignment corrections of the surfaces of a detector, stored by id. Surfaces without an
+/// entry are at their nominal placement.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlignmentStore {
+    deltas: BTreeMap<GeometryId, AlignmentDelta>
+}
+
+// file format of an `AlignmentStore`
+#[derive(Serialize, Deserialize)]
+struct AlignmentFile {
+    alignments: Vec<AlignmentEntry>
+}
+
+// the id is last since TOML writes values before tables
+#[derive(Serialize, Deserialize)]
+struct AlignmentEntry {
+    translation: [Real; 3],
+    rotation: [Real; 3],
+    id: GeometryId
+}
+
+impl AlignmentStore {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the correction of a surface, returning the previous one
+    pub fn insert(&mut self, id: GeometryId, delta: AlignmentDelta) -> Option<AlignmentDelta> {
+        self.deltas.insert(id, delta)
+    }
+
+    pub fn remove(&mut self, id: GeometryId) -> Option<AlignmentDelta> {
+        self.deltas.remove(&id)
+    }
+
+    /// Fetches the correction of a surface
+    pub fn get(&self, id: GeometryId) -> Option<&AlignmentDelta> {
+        self.deltas.get(&id)
+    }
+
+    /// Corrections sorted by id
+    pub fn iter(&self) -> impl Iterator<Item = (GeometryId, &AlignmentDelta)> {
+        self.deltas.iter().map(|(id, delta)| (*id, delta))
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Wraps a sensor with its correction. Sensors without an entry get a zero correction.
+    pub fn align<T: Transform + Plane + Identified>(&self, sensor: T) -> Aligned<T> {
+        let delta = self.get(sensor.geometry_id()).cloned().unwrap_or_default();
+        Aligned::new(sensor, delta)
+    }
+
+    /// Wraps every sensor with its correction
+    pub fn align_all<T: Transform + Plane + Identified>(&self, sensors: Vec<T>) -> Vec<Aligned<T>> {
+        sensors.into_iter().map(|sensor| self.align(sensor)).collect()
+    }
+
+    /// Geometry with every surface moved by its correction
+    pub fn align_geometry(&self, geometry: &TrackingGeometry) -> Result<TrackingGeometry, GeometryError> {
+        let surfaces = geometry.surfaces()
+            .map(|surface| {
+                match self.get(surface.geometry_id()) {
+                    Some(delta) => align_surface(surface, delta),
+                    None => Ok(surface.clone())
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TrackingGeometry::new(surfaces)
+    }
+
+    /// Reads corrections from a JSON string. Returns `Err(GeometryError::DuplicateId)` if a
+    /// surface has more than one entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::geometry::alignment::AlignmentStore;
+    /// use kalman_rs::geometry::GeometryId;
+    ///
+    /// let store = AlignmentStore::from_json(r#"{"alignments": [
+    ///     {"id": {"volume": 1, "layer": 2, "sensitive": 3}, "translation": [0.01, 0, 0], "rotation": [0, 0, 0.001]}
+    /// ]}"#).unwrap();
+    ///
+    /// assert_eq!(store.get(GeometryId::new(1, 2, 3)).unwrap().rotation.z, 0.001);
+    /// ```
+    pub fn from_json(input: &str) -> Result<Self, GeometryError> {
+        Self::from_file(serde_json::from_str(input)?)
+    }
+
+    /// Reads corrections from a TOML string
+    pub fn from_toml(input: &str) -> Result<Self, GeometryError> {
+        let file = toml::from_str(input).map_err(|e| GeometryError::Toml(e.to_string()))?;
+        Self::from_file(file)
+    }
+
+    pub fn to_json(&self) -> Result<String, GeometryError> {
+        Ok(serde_json::to_string_pretty(&self.to_file())?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, GeometryError> {
+        toml::to_string(&self.to_file()).map_err(|e| GeometryError::Toml(e.to_string()))
+    }
+
+    /// Reads corrections from a `.json` or `.toml` file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GeometryError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+
+        match file_format(path)? {
+            Format::Json => Self::from_json(&contents),
+            Format::Toml => Self::from_toml(&contents)
+        }
+    }
+
+    /// Writes the corrections to a `.json` or `.toml` file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GeometryError> {
+        let path = path.as_ref();
+
+        let contents =
+            match file_format(path)? {
+                Format::Json => self.to_json()?,
+                Format::Toml => self.to_toml()?
+            };
+
+        Ok(fs::write(path, contents)?)
+    }
+
+    fn from_file(file: AlignmentFile) -> Result<Self, GeometryError> {
+        let mut store = AlignmentStore::new();
+
+        for entry in file.alignments {
+            let delta = AlignmentDelta::new(Vec3::from_column_slice(&entry.translation), Vec3::from_column_slice(&entry.rotation));
+
+            if store.insert(entry.id, delta).is_some() {
+                return Err(GeometryError::DuplicateId(entry.id))
+            }
+        }
+
+        Ok(store)
+    }
+
+    fn to_file(&self) -> AlignmentFile {
+        let alignments = self.iter()
+            .map(|(id, delta)| AlignmentEntry {
+                translation: [delta.translation.x, delta.translation.y, delta.translation.z],
+                rotation: [delta.rotation.x, delta.rotation.y, delta.rotation.z],
+                id
+            })
+            .collect();
+
+        AlignmentFile {alignments}
+    }
+}
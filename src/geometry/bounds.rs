@@ -0,0 +1,505 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::utils::wrap_phi;
+
+/// Bounds of a sensor in its local (x, y) frame. This is what makes the bounds checks of
+/// every planar sensor interchangeable, see `geometry::plane_surface::PlaneSurface`.
+pub trait Bounds {
+
+    /// Checks if a local point is inside the bounds. Points up to `tolerance` outside of
+    /// the closest edge are still accepted.
+    fn inside(&self, local: &P2, tolerance: Real) -> bool {
+        self.distance_to_boundary(local) <= tolerance
+    }
+
+    /// Signed distance from a local point to the closest edge of the bounds. The distance is
+    /// negative for points inside the bounds and positive for points outside of them.
+    fn distance_to_boundary(&self, local: &P2) -> Real;
+
+    /// Corners of the bounds in order, counter clockwise. Curved edges are approximated by
+    /// `arc_segments` straight segments.
+    fn outline(&self, arc_segments: usize) -> Vec<P2>;
+}
+
+
+/// How strictly a local point is compared against the bounds of a sensor. Points that fall
+/// outside of the bounds by less than the tolerance are still accepted.
+#[derive(Debug, Clone)]
+pub enum BoundaryCheck {
+    /// only points inside of the bounds are accepted
+    Strict,
+    /// points up to a fixed distance outside of the bounds are accepted
+    Absolute(Real),
+    /// points up to `n_sigma` standard deviations outside of the bounds are accepted. The
+    /// covariance is the one of the local coordinates of the sensor.
+    Covariance{covariance: Mat2, n_sigma: Real}
+}
+
+impl BoundaryCheck {
+
+    /// Tolerance on the signed distance to the boundary at a local point. `distance` is the
+    /// signed distance function of the sensor, it is used to find the direction of the
+    /// closest edge so that the covariance can be projected onto it.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::bounds::*;
+    ///
+    /// let bounds = RectangleBounds::new(1., 1.);
+    /// let check = BoundaryCheck::Covariance{covariance: Mat2::identity() * 0.01, n_sigma: 3.};
+    ///
+    /// let point = P2::new(1.2, 0.);
+    /// let tolerance = check.tolerance(&point, |p| bounds.distance_to_boundary(p));
+    ///
+    /// assert!(bounds.inside(&point, tolerance));
+    /// ```
+    pub fn tolerance<F: Fn(&P2) -> Real>(&self, local: &P2, distance: F) -> Real {
+        match self {
+            BoundaryCheck::Strict => 0.,
+            BoundaryCheck::Absolute(tolerance) => tolerance.abs(),
+            BoundaryCheck::Covariance{covariance, n_sigma} => {
+                // numerical gradient of the distance, for local frames that are not cartesian
+                // its length scales the uncertainty into a distance
+                let step = 1e-6 * local.coords.norm().max(1.);
+                let dx = Vec2::new(step, 0.);
+                let dy = Vec2::new(0., step);
+
+                let gradient = Vec2::new(
+                    distance(&(local + dx)) - distance(&(local - dx)),
+                    distance(&(local + dy)) - distance(&(local - dy))
+                ) / (2. * step);
+
+                let variance = (gradient.transpose() * covariance * gradient)[(0, 0)];
+
+                n_sigma.abs() * variance.max(0.).sqrt()
+            }
+        }
+    }
+
+    /// The same check with the covariance of a `Covariance` check replaced by `covariance`,
+    /// for example the predicted covariance of a track. Other checks are returned unchanged.
+    pub fn with_covariance(&self, covariance: &Mat2) -> BoundaryCheck {
+        match self {
+            BoundaryCheck::Covariance{n_sigma, ..} => BoundaryCheck::Covariance{covariance: *covariance, n_sigma: *n_sigma},
+            check => check.clone()
+        }
+    }
+}
+
+
+/// Rectangle centered on the local origin
+#[derive(Debug, Clone)]
+pub struct RectangleBounds {
+    half_x: Real,
+    half_y: Real
+}
+
+impl RectangleBounds {
+    pub fn new(half_x: Real, half_y: Real) -> Self {
+        RectangleBounds {half_x: half_x.abs(), half_y: half_y.abs()}
+    }
+
+    pub fn half_x(&self) -> Real {
+        self.half_x
+    }
+
+    pub fn half_y(&self) -> Real {
+        self.half_y
+    }
+}
+
+impl Bounds for RectangleBounds {
+    fn outline(&self, _arc_segments: usize) -> Vec<P2> {
+        let (x, y) = (self.half_x, self.half_y);
+        vec![P2::new(-x, -y), P2::new(x, -y), P2::new(x, y), P2::new(-x, y)]
+    }
+
+    fn distance_to_boundary(&self, local: &P2) -> Real {
+        let dx = local.x.abs() - self.half_x;
+        let dy = local.y.abs() - self.half_y;
+
+        if (dx > 0.) || (dy > 0.) {
+            (dx.max(0.).powi(2) + dy.max(0.).powi(2)).sqrt()
+        }
+        else {
+            dx.max(dy)
+        }
+    }
+}
+
+
+/// Any convex polygon in the local frame. Vertices are stored counter clockwise.
+#[derive(Debug, Clone)]
+pub struct ConvexPolygonBounds {
+    vertices: Vec<P2>
+}
+
+impl ConvexPolygonBounds {
+
+    /// Creates polygon bounds from its corners in order (clockwise or counter clockwise).
+    /// Returns `Err(SensorError::InvalidBounds)` if there are fewer than three corners, the
+    /// polygon is not convex or its edges intersect.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::bounds::*;
+    ///
+    /// let corners = vec![P2::new(-1., -1.), P2::new(1., -1.), P2::new(0., 1.)];
+    /// let triangle = ConvexPolygonBounds::new(corners).unwrap();
+    ///
+    /// assert!(triangle.inside(&P2::new(0., 0.), 0.));
+    /// ```
+    pub fn new(mut vertices: Vec<P2>) -> Result<Self, SensorError> {
+        // repeated corners (for example a diamond without a top edge) are not edges
+        vertices.dedup();
+        if vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+
+        if vertices.len() < 3 {
+            return Err(SensorError::InvalidBounds("a polygon needs at least three vertices"))
+        }
+
+        let len = vertices.len();
+        let edges = (0..len)
+            .map(|i| {
+                let a = vertices[i];
+                let b = vertices[(i + 1) % len];
+                let c = vertices[(i + 2) % len];
+                (b - a, c - b)
+            })
+            .collect::<Vec<_>>();
+        let turns = edges.iter().map(|(u, v)| cross(u, v)).collect::<Vec<_>>();
+
+        // the turns of a star polygon (e.g. a pentagram) all have the same sign, but its
+        // edges wind around more than once
+        let winding = edges.iter().map(|(u, v)| cross(u, v).atan2(u.dot(v))).sum::<Real>();
+        if (winding.abs() - 2. * PI).abs() > 1e-6 {
+            return Err(SensorError::InvalidBounds("polygon edges intersect"))
+        }
+
+        // collinear vertices (zero turns) are allowed as long as the polygon is not degenerate
+        let counter_clockwise = turns.iter().all(|turn| *turn >= 0.) && turns.iter().any(|turn| *turn > 0.);
+        let clockwise = turns.iter().all(|turn| *turn <= 0.) && turns.iter().any(|turn| *turn < 0.);
+
+        if clockwise {
+            vertices.reverse();
+        }
+        else if !counter_clockwise {
+            return Err(SensorError::InvalidBounds("polygon vertices are not convex"))
+        }
+
+        Ok(ConvexPolygonBounds {vertices})
+    }
+
+    /// Corners of the polygon, counter clockwise
+    pub fn vertices(&self) -> &[P2] {
+        &self.vertices
+    }
+}
+
+impl Bounds for ConvexPolygonBounds {
+    fn outline(&self, _arc_segments: usize) -> Vec<P2> {
+        self.vertices.clone()
+    }
+
+    fn distance_to_boundary(&self, local: &P2) -> Real {
+        let len = self.vertices.len();
+
+        let mut inside = true;
+        let mut closest = Real::INFINITY;
+
+        for i in 0..len {
+            let a = &self.vertices[i];
+            let b = &self.vertices[(i + 1) % len];
+
+            // interior of a counter clockwise polygon is on the left of every edge
+            if cross(&(b - a), &(local - a)) < 0. {
+                inside = false;
+            }
+
+            closest = closest.min(segment_distance(local, a, b));
+        }
+
+        if inside {-closest}
+        else {closest}
+    }
+}
+
+
+/// Trapezoid symmetric about the local y axis
+#[derive(Debug, Clone)]
+pub struct TrapezoidBounds {
+    half_x_neg_y: Real,     // half width of the edge at -half_y
+    half_x_pos_y: Real,     // half width of the edge at +half_y
+    half_y: Real,
+    polygon: ConvexPolygonBounds
+}
+
+impl TrapezoidBounds {
+    pub fn new(half_x_neg_y: Real, half_x_pos_y: Real, half_y: Real) -> Result<Self, SensorError> {
+        let (bot, top, h) = (half_x_neg_y.abs(), half_x_pos_y.abs(), half_y.abs());
+
+        let polygon = ConvexPolygonBounds::new(vec![
+            P2::new(-bot, -h), P2::new(bot, -h), P2::new(top, h), P2::new(-top, h)
+        ])?;
+
+        Ok(TrapezoidBounds {half_x_neg_y: bot, half_x_pos_y: top, half_y: h, polygon})
+    }
+
+    pub fn half_x_neg_y(&self) -> Real {
+        self.half_x_neg_y
+    }
+
+    pub fn half_x_pos_y(&self) -> Real {
+        self.half_x_pos_y
+    }
+
+    pub fn half_y(&self) -> Real {
+        self.half_y
+    }
+
+    pub fn vertices(&self) -> &[P2] {
+        self.polygon.vertices()
+    }
+}
+
+impl Bounds for TrapezoidBounds {
+    fn outline(&self, _arc_segments: usize) -> Vec<P2> {
+        self.vertices().to_vec()
+    }
+
+    fn distance_to_boundary(&self, local: &P2) -> Real {
+        self.polygon.distance_to_boundary(local)
+    }
+}
+
+
+/// Hexagon symmetric about the local y axis with its widest point on the local x axis
+#[derive(Debug, Clone)]
+pub struct DiamondBounds {
+    half_x_neg_y: Real,     // half width of the edge at -half_y_neg
+    half_x_zero_y: Real,    // half width at y = 0
+    half_x_pos_y: Real,     // half width of the edge at +half_y_pos
+    half_y_neg: Real,
+    half_y_pos: Real,
+    polygon: ConvexPolygonBounds
+}
+
+impl DiamondBounds {
+    pub fn new(
+        half_x_neg_y: Real,
+        half_x_zero_y: Real,
+        half_x_pos_y: Real,
+        half_y_neg: Real,
+        half_y_pos: Real
+        ) -> Result<Self, SensorError> {
+
+        let (x1, x2, x3) = (half_x_neg_y.abs(), half_x_zero_y.abs(), half_x_pos_y.abs());
+        let (y1, y2) = (half_y_neg.abs(), half_y_pos.abs());
+
+        if (x2 < x1) || (x2 < x3) {
+            return Err(SensorError::InvalidBounds("diamond must be widest at y = 0"))
+        }
+
+        let corners = vec![
+            P2::new(-x1, -y1), P2::new(x1, -y1), P2::new(x2, 0.),
+            P2::new(x3, y2), P2::new(-x3, y2), P2::new(-x2, 0.)
+        ];
+
+        let polygon = ConvexPolygonBounds::new(corners)?;
+
+        Ok(DiamondBounds {
+            half_x_neg_y: x1,
+            half_x_zero_y: x2,
+            half_x_pos_y: x3,
+            half_y_neg: y1,
+            half_y_pos: y2,
+            polygon
+        })
+    }
+
+    pub fn half_x_neg_y(&self) -> Real {
+        self.half_x_neg_y
+    }
+
+    pub fn half_x_zero_y(&self) -> Real {
+        self.half_x_zero_y
+    }
+
+    pub fn half_x_pos_y(&self) -> Real {
+        self.half_x_pos_y
+    }
+
+    pub fn half_y_neg(&self) -> Real {
+        self.half_y_neg
+    }
+
+    pub fn half_y_pos(&self) -> Real {
+        self.half_y_pos
+    }
+
+    pub fn vertices(&self) -> &[P2] {
+        self.polygon.vertices()
+    }
+}
+
+impl Bounds for DiamondBounds {
+    fn outline(&self, _arc_segments: usize) -> Vec<P2> {
+        self.vertices().to_vec()
+    }
+
+    fn distance_to_boundary(&self, local: &P2) -> Real {
+        self.polygon.distance_to_boundary(local)
+    }
+}
+
+
+/// Ring sector around the local origin of a planar sensor, `r_min <= r <= r_max` and
+/// `|phi - average_phi| <= half_phi`
+#[derive(Debug, Clone)]
+pub struct AnnulusBounds {
+    r_min: Real,
+    r_max: Real,
+    average_phi: Real,
+    half_phi: Real
+}
+
+impl AnnulusBounds {
+    pub fn new(r_min: Real, r_max: Real, average_phi: Real, half_phi: Real) -> Result<Self, SensorError> {
+        if (r_min < 0.) || (r_max <= r_min) {
+            return Err(SensorError::InvalidBounds("annulus radii must satisfy 0 <= r_min < r_max"))
+        }
+
+        Ok(AnnulusBounds {r_min, r_max, average_phi, half_phi: half_phi.abs().min(PI)})
+    }
+
+    pub fn r_min(&self) -> Real {
+        self.r_min
+    }
+
+    pub fn r_max(&self) -> Real {
+        self.r_max
+    }
+
+    pub fn average_phi(&self) -> Real {
+        self.average_phi
+    }
+
+    pub fn half_phi(&self) -> Real {
+        self.half_phi
+    }
+}
+
+impl AnnulusBounds {
+    /// Signed distance to the closest edge from a local polar (r, phi) point. This is what
+    /// `geometry::Disc` uses since its local frame is already polar.
+    pub fn distance_to_boundary_polar(&self, polar: &P2) -> Real {
+        let r = polar.x;
+
+        let radial = (r - self.r_max).max(self.r_min - r);
+
+        // a full ring has no phi edges
+        if self.half_phi >= PI {
+            return radial
+        }
+
+        let offset = wrap_phi(polar.y - self.average_phi).abs() - self.half_phi;
+
+        // distance to the straight phi edge, points behind the origin are a full radius away
+        let angular =
+            if offset.abs() < PI/2. {r * offset.sin()}
+            else {r * offset.signum()};
+
+        radial.max(angular)
+    }
+}
+
+impl Bounds for AnnulusBounds {
+    /// Outer arc followed by the inner arc. A full ring only has its outer circle.
+    fn outline(&self, arc_segments: usize) -> Vec<P2> {
+        let segments = arc_segments.max(1);
+        let start = self.average_phi - self.half_phi;
+        let step = 2. * self.half_phi / (segments as Real);
+
+        let arc = |r: Real| (0..=segments).map(move |i| {
+            let phi = start + (step * i as Real);
+            P2::new(r * phi.cos(), r * phi.sin())
+        });
+
+        if (self.half_phi >= PI) || (self.r_min == 0.) {
+            let mut points = arc(self.r_max).collect::<Vec<_>>();
+            if self.half_phi >= PI {
+                points.pop();
+            }
+            else {
+                points.push(P2::origin());
+            }
+            points
+        }
+        else {
+            arc(self.r_max).chain(arc(self.r_min).rev()).collect()
+        }
+    }
+
+    /// Exact along the radial and phi edges. Outside of the corners of the sector this is
+    /// the largest of the edge distances, which never overestimates the real distance.
+    fn distance_to_boundary(&self, local: &P2) -> Real {
+        let polar = P2::new(local.coords.norm(), local.y.atan2(local.x));
+        self.distance_to_boundary_polar(&polar)
+    }
+}
+
+
+/// Any of the bounds above. This is used when the shape of a sensor is only known at runtime,
+/// for example when the detector is loaded from a file.
+#[derive(Debug, Clone)]
+pub enum SurfaceBounds {
+    Rectangle(RectangleBounds),
+    Trapezoid(TrapezoidBounds),
+    Diamond(DiamondBounds),
+    ConvexPolygon(ConvexPolygonBounds),
+    Annulus(AnnulusBounds)
+}
+
+impl Bounds for SurfaceBounds {
+    fn outline(&self, arc_segments: usize) -> Vec<P2> {
+        match self {
+            SurfaceBounds::Rectangle(bounds) => bounds.outline(arc_segments),
+            SurfaceBounds::Trapezoid(bounds) => bounds.outline(arc_segments),
+            SurfaceBounds::Diamond(bounds) => bounds.outline(arc_segments),
+            SurfaceBounds::ConvexPolygon(bounds) => bounds.outline(arc_segments),
+            SurfaceBounds::Annulus(bounds) => bounds.outline(arc_segments)
+        }
+    }
+
+    fn distance_to_boundary(&self, local: &P2) -> Real {
+        match self {
+            SurfaceBounds::Rectangle(bounds) => bounds.distance_to_boundary(local),
+            SurfaceBounds::Trapezoid(bounds) => bounds.distance_to_boundary(local),
+            SurfaceBounds::Diamond(bounds) => bounds.distance_to_boundary(local),
+            SurfaceBounds::ConvexPolygon(bounds) => bounds.distance_to_boundary(local),
+            SurfaceBounds::Annulus(bounds) => bounds.distance_to_boundary(local)
+        }
+    }
+}
+
+
+/// z component of the cross product of two vectors in the plane
+fn cross(a: &Vec2, b: &Vec2) -> Real {
+    (a.x * b.y) - (a.y * b.x)
+}
+
+/// Distance from a point to the segment between `a` and `b`
+fn segment_distance(point: &P2, a: &P2, b: &P2) -> Real {
+    let edge = b - a;
+    let length_squared = edge.norm_squared();
+
+    let t =
+        if length_squared > 0. {((point - a).dot(&edge) / length_squared).max(0.).min(1.)}
+        else {0.};
+
+    (point - (a + edge * t)).norm()
+}
@@ -0,0 +1,196 @@
+use super::bounds::SurfaceBounds;
+use super::plane_surface::PlaneSurface;
+use super::surface::Surface;
+use super::disc::Disc;
+use super::id::GeometryId;
+use super::material::SurfaceMaterial;
+use super::validation::check_transform;
+use super::traits::{Identified, HasMaterial};
+use super::super::config::*;
+use super::super::error::*;
+
+/// Largest error allowed in the transformations of a built sensor
+const DEFAULT_TOLERANCE: Real = 1e-9;
+
+/// Position and orientation of a surface: its center in the global frame and the orthonormal
+/// local -> global rotation. The columns of the rotation are the local x, y and z (normal)
+/// axes in the global frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    center: P3,
+    rotation: Mat3
+}
+
+impl Placement {
+
+    /// Placement from the center of the surface, its normal (local z axis) and the direction
+    /// of the local x axis. `local_x` does not need to be exactly perpendicular to `normal`,
+    /// only its perpendicular part is used. Without `local_x` the x axis is `global z x normal`,
+    /// or the global x axis if the normal is along z.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::builder::Placement;
+    ///
+    /// // a surface facing along global x
+    /// let placement = Placement::from_normal(P3::new(10., 0., 0.), &Vec3::new(2., 0., 0.), None).unwrap();
+    ///
+    /// assert_eq!(placement.normal(), Vec3::new(1., 0., 0.));
+    /// assert_eq!(placement.local_x(), Vec3::new(0., 1., 0.));
+    /// ```
+    pub fn from_normal(center: P3, normal: &Vec3, local_x: Option<&Vec3>) -> Result<Self, GeometryError> {
+        let z = match normal.try_normalize(0.) {
+            Some(z) => z,
+            None => return Err(GeometryError::InvalidPlacement("normal vector has zero length"))
+        };
+
+        let local_x =
+            match local_x {
+                Some(local_x) => *local_x,
+                None => {
+                    let global_z = Vec3::z();
+                    if z.cross(&global_z).norm() > DOT_PRODUCT_EPSILON {global_z.cross(&z)}
+                    else {Vec3::x()}
+                }
+            };
+
+        // drop the part of the local x axis along the normal
+        let x = match (local_x - (z * local_x.dot(&z))).try_normalize(DOT_PRODUCT_EPSILON) {
+            Some(x) => x,
+            None => return Err(GeometryError::InvalidPlacement("local x axis is parallel to the normal"))
+        };
+        let y = z.cross(&x);
+
+        Ok(Placement {center, rotation: Mat3::from_columns(&[x, y, z])})
+    }
+
+    /// Placement from the center of the surface and the angles of the rotations about the
+    /// global x, y and z axes (applied in that order)
+    pub fn from_euler_angles(center: P3, roll: Real, pitch: Real, yaw: Real) -> Self {
+        Placement {center, rotation: *Rot3::from_euler_angles(roll, pitch, yaw).matrix()}
+    }
+
+    /// Placement from the translation and rotation matrices taken by the sensor constructors.
+    /// Returns an error if the rotation is not orthonormal within `tolerance` or if either
+    /// matrix has more than a translation / rotation in it.
+    pub fn from_matrices(translation: &Mat4, rotation: &Mat4, tolerance: Real) -> Result<Self, GeometryError> {
+        let rot = rotation.fixed_slice::<U3, U3>(0, 0).into_owned();
+
+        let orthonormal_error = (rot.transpose() * rot - Mat3::identity()).norm() + (rot.determinant() - 1.).abs();
+        if orthonormal_error.is_nan() || (orthonormal_error > tolerance) {
+            return Err(GeometryError::InvalidPlacement("rotation matrix is not orthonormal"))
+        }
+
+        let shift = translation.fixed_slice::<U3, U1>(0, 3).into_owned();
+        if ((translation - Mat4::new_translation(&shift)).norm() > tolerance) || ((rotation - rot.to_homogeneous()).norm() > tolerance) {
+            return Err(GeometryError::InvalidPlacement("placement matrices are not a translation and a rotation"))
+        }
+
+        Ok(Placement {center: P3::from(shift), rotation: rot})
+    }
+
+    pub fn center(&self) -> &P3 {
+        &self.center
+    }
+
+    /// Local -> global rotation
+    pub fn rotation(&self) -> &Mat3 {
+        &self.rotation
+    }
+
+    pub fn local_x(&self) -> Vec3 {
+        self.rotation.column(0).into_owned()
+    }
+
+    pub fn local_y(&self) -> Vec3 {
+        self.rotation.column(1).into_owned()
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.rotation.column(2).into_owned()
+    }
+
+    /// Translation and rotation matrices (local -> global) in the form expected by the sensor
+    /// constructors
+    pub fn matrices(&self) -> (Mat4, Mat4) {
+        (Mat4::new_translation(&self.center.coords), self.rotation.to_homogeneous())
+    }
+}
+
+
+/// Builds surfaces from a `Placement`. Every built surface is checked with
+/// `validation::check_transform`, so its transformations are inverse of each other, its
+/// rotation is orthonormal and its normal is the local z axis.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::geometry::GeometryId;
+/// use kalman_rs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+/// use kalman_rs::geometry::builder::{Placement, SurfaceBuilder};
+/// use kalman_rs::geometry::traits::*;
+///
+/// let placement = Placement::from_normal(P3::new(0., 0., 5.), &Vec3::new(0., 1., 1.), Some(&Vec3::x())).unwrap();
+///
+/// let surface = SurfaceBuilder::new(placement)
+///     .geometry_id(GeometryId::new(1, 2, 3))
+///     .plane(SurfaceBounds::Rectangle(RectangleBounds::new(1., 2.)))
+///     .unwrap();
+///
+/// assert_eq!(surface.geometry_id(), GeometryId::new(1, 2, 3));
+/// assert!((surface.to_global(P3::new(1., 0., 0.)) - P3::new(1., 0., 5.)).norm() < 1e-12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SurfaceBuilder {
+    placement: Placement,
+    geometry_id: GeometryId,
+    material: Option<SurfaceMaterial>,
+    tolerance: Real
+}
+
+impl SurfaceBuilder {
+
+    pub fn new(placement: Placement) -> Self {
+        SurfaceBuilder {placement, geometry_id: GeometryId::default(), material: None, tolerance: DEFAULT_TOLERANCE}
+    }
+
+    pub fn geometry_id(mut self, geometry_id: GeometryId) -> Self {
+        self.geometry_id = geometry_id;
+        self
+    }
+
+    pub fn material(mut self, material: SurfaceMaterial) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Largest error allowed when checking the built surface
+    pub fn tolerance(mut self, tolerance: Real) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Builds a surface with a cartesian local frame
+    pub fn plane(&self, bounds: SurfaceBounds) -> Result<Surface, GeometryError> {
+        let (translation, rotation) = self.placement.matrices();
+        self.finish(Surface::Plane(PlaneSurface::new(bounds, translation, rotation)?))
+    }
+
+    /// Builds a disc with a polar (r, phi) local frame
+    pub fn disc(&self, r_min: Real, r_max: Real, average_phi: Real, half_phi: Real) -> Result<Surface, GeometryError> {
+        let (translation, rotation) = self.placement.matrices();
+        self.finish(Surface::Disc(Disc::new(r_min, r_max, average_phi, half_phi, translation, rotation)?))
+    }
+
+    fn finish(&self, mut surface: Surface) -> Result<Surface, GeometryError> {
+        if !check_transform(&surface, self.tolerance).is_empty() {
+            return Err(GeometryError::InvalidPlacement("built surface has inconsistent transformations"))
+        }
+
+        surface.set_geometry_id(self.geometry_id);
+        surface.set_material(self.material.clone());
+
+        Ok(surface)
+    }
+}
@@ -0,0 +1,333 @@
+use super::surface::Surface;
+use super::id::GeometryId;
+use super::traits::{Identified, Outline};
+use super::super::config::*;
+
+/// Axis aligned box in the global frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: P3,
+    pub max: P3
+}
+
+impl Aabb {
+
+    /// Smallest box that contains every point. Returns `None` if there are no points.
+    pub fn from_points(points: &[P3]) -> Option<Self> {
+        let first = points.first()?;
+
+        let bounds = points.iter()
+            .fold(Aabb {min: *first, max: *first}, |bounds, point| {
+                bounds.union(&Aabb {min: *point, max: *point})
+            });
+
+        Some(bounds)
+    }
+
+    /// Smallest box that contains both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: P3::from(self.min.coords.zip_map(&other.min.coords, Real::min)),
+            max: P3::from(self.max.coords.zip_map(&other.max.coords, Real::max))
+        }
+    }
+
+    /// Box grown by `margin` in every direction
+    pub fn expanded(&self, margin: Real) -> Aabb {
+        let margin = Vec3::repeat(margin);
+        Aabb {min: self.min - margin, max: self.max + margin}
+    }
+
+    /// Checks if the two boxes share any point
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| (self.min[axis] <= other.max[axis]) && (other.min[axis] <= self.max[axis]))
+    }
+
+    pub fn center(&self) -> P3 {
+        P3::from((self.min.coords + self.max.coords) / 2.)
+    }
+
+    /// Checks if the part of the line `origin + t * direction` with `t_min <= t <= t_max`
+    /// passes through the box (slab test).
+    pub fn intersects(&self, origin: &P3, direction: &Vec3, t_min: Real, t_max: Real) -> bool {
+        let mut near = t_min;
+        let mut far = t_max;
+
+        for axis in 0..3 {
+            if direction[axis] == 0. {
+                if (origin[axis] < self.min[axis]) || (origin[axis] > self.max[axis]) {
+                    return false
+                }
+                continue
+            }
+
+            let inverse = 1. / direction[axis];
+            let t0 = (self.min[axis] - origin[axis]) * inverse;
+            let t1 = (self.max[axis] - origin[axis]) * inverse;
+
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+
+            if near > far {
+                return false
+            }
+        }
+
+        true
+    }
+}
+
+
+/// Helix through `position` with unit tangent `direction` at that point. The track bends
+/// around `axis` (the magnetic field direction) with the given signed radius of curvature.
+/// An infinite radius is a straight line.
+#[derive(Debug, Clone)]
+pub struct Helix {
+    pub position: P3,
+    pub direction: Vec3,
+    pub axis: Vec3,
+    pub radius: Real
+}
+
+impl Helix {
+    pub fn new(position: P3, direction: Vec3, axis: Vec3, radius: Real) -> Self {
+        Helix {position, direction: direction.normalize(), axis: axis.normalize(), radius}
+    }
+
+    /// Point on the helix after a path length `s`
+    pub fn point(&self, s: Real) -> P3 {
+        if !self.radius.is_finite() {
+            return self.position + (self.direction * s)
+        }
+
+        // split the direction into the part along the axis and the part that rotates
+        let parallel = self.axis * self.direction.dot(&self.axis);
+        let transverse = self.direction - parallel;
+        let transverse_norm = transverse.norm();
+
+        if transverse_norm < Real::EPSILON {
+            return self.position + (self.direction * s)
+        }
+
+        let t = transverse / transverse_norm;
+        let n = self.axis.cross(&t);
+
+        // angle turned in the transverse plane
+        let angle = (s * transverse_norm) / self.radius;
+        let r = self.radius;
+
+        self.position + (parallel * s) + (t * (r * angle.sin())) + (n * (r * (1. - angle.cos())))
+    }
+
+    /// Unit tangent of the helix after a path length `s`
+    pub fn direction(&self, s: Real) -> Vec3 {
+        let parallel = self.axis * self.direction.dot(&self.axis);
+        let transverse = self.direction - parallel;
+        let transverse_norm = transverse.norm();
+
+        if !self.radius.is_finite() || (transverse_norm < Real::EPSILON) {
+            return self.direction
+        }
+
+        let t = transverse / transverse_norm;
+        let n = self.axis.cross(&t);
+        let angle = (s * transverse_norm) / self.radius;
+
+        parallel + (t * (transverse_norm * angle.cos())) + (n * (transverse_norm * angle.sin()))
+    }
+}
+
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf {bounds: Aabb, first: usize, count: usize},
+    Branch {bounds: Aabb, left: usize, right: usize}
+}
+
+impl Node {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            Node::Leaf{bounds, ..} => bounds,
+            Node::Branch{bounds, ..} => bounds
+        }
+    }
+}
+
+/// Maximum number of surfaces in a leaf of the hierarchy
+const LEAF_SIZE: usize = 4;
+
+/// Number of straight segments used for the curved edges of the surfaces
+const ARC_SEGMENTS: usize = 16;
+
+/// Bounding volume hierarchy over the global extent of surfaces. Queries return the ids of
+/// the surfaces whose boxes are crossed, those surfaces still need an exact intersection.
+#[derive(Debug, Clone)]
+pub struct SurfaceBvh {
+    nodes: Vec<Node>,
+    ids: Vec<GeometryId>,   // leaves reference ranges of this vector
+    boxes: Vec<Aabb>        // box of each id, same order as `ids`
+}
+
+impl SurfaceBvh {
+
+    /// Builds the hierarchy by splitting the surfaces at the median of the longest axis
+    pub fn new<'a, I: IntoIterator<Item = &'a Surface>>(surfaces: I) -> Self {
+        let mut entries = surfaces.into_iter()
+            .filter_map(|surface| {
+                let bounds = Aabb::from_points(&surface.global_outline(ARC_SEGMENTS))?;
+                Some((surface.geometry_id(), bounds))
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = Vec::new();
+
+        if !entries.is_empty() {
+            let len = entries.len();
+            build(&mut entries, 0, len, &mut nodes);
+        }
+
+        let (ids, boxes) = entries.into_iter().unzip();
+
+        SurfaceBvh {nodes, ids, boxes}
+    }
+
+    /// Number of surfaces in the hierarchy
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Surfaces whose boxes (grown by `margin`) are crossed by the line
+    /// `origin + t * direction` for `t_min <= t <= t_max`.
+    pub fn line_candidates(&self, origin: &P3, direction: &Vec3, t_min: Real, t_max: Real, margin: Real) -> Vec<GeometryId> {
+        let mut candidates = Vec::new();
+        self.query(&mut candidates, &|bounds| bounds.expanded(margin).intersects(origin, direction, t_min, t_max));
+        candidates
+    }
+
+    /// Surfaces whose boxes (grown by `margin`) overlap the given box
+    pub fn box_candidates(&self, bounds: &Aabb, margin: Real) -> Vec<GeometryId> {
+        let mut candidates = Vec::new();
+        self.query(&mut candidates, &|b| b.expanded(margin).overlaps(bounds));
+        candidates
+    }
+
+    /// Surfaces whose boxes are crossed by a straight track in front of `position`
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::{GeometryId, PlaneSurface, Surface};
+    /// use kalman_rs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+    /// use kalman_rs::geometry::traits::Identified;
+    /// use kalman_rs::geometry::bvh::SurfaceBvh;
+    ///
+    /// let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    /// let translation = Mat4::new_translation(&Vec3::new(0., 0., 5.));
+    /// let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, Mat4::identity()).unwrap());
+    /// surface.set_geometry_id(GeometryId::new(1, 1, 1));
+    ///
+    /// let bvh = SurfaceBvh::new(vec![&surface]);
+    ///
+    /// assert_eq!(bvh.ray_candidates(&P3::origin(), &Vec3::new(0., 0., 1.), 0.).len(), 1);
+    /// assert!(bvh.ray_candidates(&P3::origin(), &Vec3::new(0., 0., -1.), 0.).is_empty());
+    /// ```
+    pub fn ray_candidates(&self, position: &P3, direction: &Vec3, margin: Real) -> Vec<GeometryId> {
+        self.line_candidates(position, direction, 0., Real::INFINITY, margin)
+    }
+
+    /// Surfaces whose boxes are crossed by the first `max_path` of a helix. The helix is split
+    /// into straight segments of length `step`, the boxes are grown by `margin` plus the largest
+    /// distance between the helix and its segments.
+    pub fn helix_candidates(&self, helix: &Helix, max_path: Real, step: Real, margin: Real) -> Vec<GeometryId> {
+        let steps = (max_path / step).ceil().max(1.) as usize;
+        let step = max_path / (steps as Real);
+
+        // sagitta of a segment
+        let sagitta =
+            if helix.radius.is_finite() {(step * step) / (8. * helix.radius.abs())}
+            else {0.};
+
+        let mut candidates = Vec::new();
+        let mut start = helix.point(0.);
+
+        for i in 1..=steps {
+            let end = helix.point(step * (i as Real));
+            let segment = end - start;
+
+            self.query(&mut candidates, &|bounds| bounds.expanded(margin + sagitta).intersects(&start, &segment, 0., 1.));
+
+            start = end;
+        }
+
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    fn query<F: Fn(&Aabb) -> bool>(&self, candidates: &mut Vec<GeometryId>, accept: &F) {
+        if self.nodes.is_empty() {
+            return
+        }
+
+        // the root is the last node that was built
+        let mut stack = vec![self.nodes.len() - 1];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            if !accept(node.bounds()) {
+                continue
+            }
+
+            match node {
+                Node::Leaf{first, count, ..} => {
+                    for i in *first..(first + count) {
+                        if accept(&self.boxes[i]) {
+                            candidates.push(self.ids[i]);
+                        }
+                    }
+                },
+                Node::Branch{left, right, ..} => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the node for `entries[first..last]` and returns its index
+fn build(entries: &mut [(GeometryId, Aabb)], first: usize, last: usize, nodes: &mut Vec<Node>) -> usize {
+    let slice = &mut entries[first..last];
+
+    let bounds = slice.iter()
+        .skip(1)
+        .fold(slice[0].1.clone(), |bounds, (_, b)| bounds.union(b));
+
+    if slice.len() <= LEAF_SIZE {
+        nodes.push(Node::Leaf {bounds, first, count: slice.len()});
+        return nodes.len() - 1
+    }
+
+    // split along the axis where the box centers are spread the most
+    let centers = slice.iter().map(|(_, b)| b.center()).collect::<Vec<_>>();
+    let spread = Aabb::from_points(&centers).expect("slice is not empty");
+    let extent = spread.max - spread.min;
+    let axis = extent.imax();
+
+    slice.sort_by(|a, b| {
+        a.1.center()[axis].partial_cmp(&b.1.center()[axis]).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let middle = first + (slice.len() / 2);
+
+    let left = build(entries, first, middle, nodes);
+    let right = build(entries, middle, last, nodes);
+
+    nodes.push(Node::Branch {bounds, left, right});
+    nodes.len() - 1
+}
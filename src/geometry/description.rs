@@ -0,0 +1,366 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::Path;
+
+use super::bounds::*;
+use super::plane_surface::PlaneSurface;
+use super::surface::Surface;
+use super::disc::Disc;
+use super::id::GeometryId;
+use super::material::*;
+use super::builder::Placement;
+use super::traits::{Identified, HasMaterial};
+use super::super::config::*;
+use super::super::error::*;
+
+/*
+
+    Description of a whole detector that can be read from / written to a JSON or TOML file.
+    The description types only hold plain numbers so that the file format does not depend on
+    how nalgebra serializes its matrices.
+
+*/
+
+/// All the surfaces of a detector
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DetectorDescription {
+    pub surfaces: Vec<SurfaceDescription>
+}
+
+/// A single surface of the detector
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SurfaceDescription {
+    /// unique identifier of the surface within the detector
+    pub id: GeometryId,
+    pub placement: PlacementDescription,
+    pub bounds: BoundsDescription,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<MaterialDescription>
+}
+
+/// Position and orientation of a surface in the global frame
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PlacementDescription {
+    /// translation of the local origin and the 3x3 local -> global rotation (row major)
+    Transform {
+        translation: [Real; 3],
+        rotation: [[Real; 3]; 3]
+    },
+    /// center of the surface, its normal (local z axis) and the direction of the local x axis.
+    /// `local_x` does not need to be exactly perpendicular to `normal`.
+    Axes {
+        center: [Real; 3],
+        normal: [Real; 3],
+        local_x: [Real; 3]
+    }
+}
+
+/// Shape of a surface. The type of the surface is taken from the bounds: `disc` is a surface
+/// with a polar (r, phi) local frame, everything else has a cartesian (x, y) local frame.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BoundsDescription {
+    Rectangle {half_x: Real, half_y: Real},
+    Trapezoid {half_x_neg_y: Real, half_x_pos_y: Real, half_y: Real},
+    Diamond {half_x_neg_y: Real, half_x_zero_y: Real, half_x_pos_y: Real, half_y_neg: Real, half_y_pos: Real},
+    ConvexPolygon {vertices: Vec<[Real; 2]>},
+    Annulus {r_min: Real, r_max: Real, average_phi: Real, half_phi: Real},
+    Disc {r_min: Real, r_max: Real, average_phi: Real, half_phi: Real}
+}
+
+/// Material of a surface. Older descriptions only give the thickness, radiation length and
+/// interaction length of a homogeneous slab.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MaterialDescription {
+    Surface(SurfaceMaterial),
+    Thickness {thickness: Real, radiation_length: Real, interaction_length: Real}
+}
+
+
+/// A surface of a detector built from its description. The id and the material of the
+/// element are stored on the surface.
+#[derive(Debug, Clone)]
+pub struct DetectorElement {
+    pub surface: Surface
+}
+
+/// A detector built from a `DetectorDescription`
+#[derive(Debug, Clone)]
+pub struct Detector {
+    pub elements: Vec<DetectorElement>
+}
+
+
+impl PlacementDescription {
+
+    /// Translation and rotation matrices (local -> global) of the placement, in the form
+    /// expected by the sensor constructors.
+    pub fn matrices(&self) -> Result<(Mat4, Mat4), GeometryError> {
+        match self {
+            PlacementDescription::Transform{translation, rotation} => {
+                let rot = Mat3::from_fn(|row, col| rotation[row][col]);
+                let translation = Vec3::from_column_slice(translation);
+
+                Ok((Mat4::new_translation(&translation), rot.to_homogeneous()))
+            },
+            PlacementDescription::Axes{center, normal, local_x} => {
+                let center = P3::new(center[0], center[1], center[2]);
+                let placement = Placement::from_normal(center, &Vec3::from_column_slice(normal), Some(&Vec3::from_column_slice(local_x)))?;
+
+                Ok(placement.matrices())
+            }
+        }
+    }
+
+    /// Placement of a sensor from its local -> global translation and rotation matrices
+    pub fn from_matrices(translation: &Mat4, rotation: &Mat4) -> Self {
+        let rotation = [
+            [rotation[(0, 0)], rotation[(0, 1)], rotation[(0, 2)]],
+            [rotation[(1, 0)], rotation[(1, 1)], rotation[(1, 2)]],
+            [rotation[(2, 0)], rotation[(2, 1)], rotation[(2, 2)]]
+        ];
+
+        PlacementDescription::Transform {
+            translation: [translation[(0, 3)], translation[(1, 3)], translation[(2, 3)]],
+            rotation
+        }
+    }
+}
+
+impl MaterialDescription {
+
+    /// Material of the surface. The atomic properties are not known for older descriptions
+    /// and are set to 0.
+    pub fn surface_material(&self) -> SurfaceMaterial {
+        match self {
+            MaterialDescription::Surface(material) => material.clone(),
+            MaterialDescription::Thickness{thickness, radiation_length, interaction_length} => {
+                let material = Material::new(*radiation_length, *interaction_length, 0., 0., 0.);
+                SurfaceMaterial::Homogeneous(MaterialSlab::new(material, *thickness))
+            }
+        }
+    }
+}
+
+impl BoundsDescription {
+
+    /// Builds the bounds of a surface with a cartesian local frame. Returns `None` for discs.
+    pub fn planar_bounds(&self) -> Result<Option<SurfaceBounds>, SensorError> {
+        let bounds =
+            match self {
+                BoundsDescription::Rectangle{half_x, half_y} =>
+                    SurfaceBounds::Rectangle(RectangleBounds::new(*half_x, *half_y)),
+                BoundsDescription::Trapezoid{half_x_neg_y, half_x_pos_y, half_y} =>
+                    SurfaceBounds::Trapezoid(TrapezoidBounds::new(*half_x_neg_y, *half_x_pos_y, *half_y)?),
+                BoundsDescription::Diamond{half_x_neg_y, half_x_zero_y, half_x_pos_y, half_y_neg, half_y_pos} =>
+                    SurfaceBounds::Diamond(DiamondBounds::new(*half_x_neg_y, *half_x_zero_y, *half_x_pos_y, *half_y_neg, *half_y_pos)?),
+                BoundsDescription::ConvexPolygon{vertices} => {
+                    let vertices = vertices.iter().map(|v| P2::new(v[0], v[1])).collect();
+                    SurfaceBounds::ConvexPolygon(ConvexPolygonBounds::new(vertices)?)
+                },
+                BoundsDescription::Annulus{r_min, r_max, average_phi, half_phi} =>
+                    SurfaceBounds::Annulus(AnnulusBounds::new(*r_min, *r_max, *average_phi, *half_phi)?),
+                BoundsDescription::Disc{..} => return Ok(None)
+            };
+
+        Ok(Some(bounds))
+    }
+
+    /// Description of the bounds of a surface with a cartesian local frame
+    pub fn from_bounds(bounds: &SurfaceBounds) -> Self {
+        match bounds {
+            SurfaceBounds::Rectangle(b) =>
+                BoundsDescription::Rectangle{half_x: b.half_x(), half_y: b.half_y()},
+            SurfaceBounds::Trapezoid(b) =>
+                BoundsDescription::Trapezoid{half_x_neg_y: b.half_x_neg_y(), half_x_pos_y: b.half_x_pos_y(), half_y: b.half_y()},
+            SurfaceBounds::Diamond(b) =>
+                BoundsDescription::Diamond{
+                    half_x_neg_y: b.half_x_neg_y(),
+                    half_x_zero_y: b.half_x_zero_y(),
+                    half_x_pos_y: b.half_x_pos_y(),
+                    half_y_neg: b.half_y_neg(),
+                    half_y_pos: b.half_y_pos()
+                },
+            SurfaceBounds::ConvexPolygon(b) =>
+                BoundsDescription::ConvexPolygon{vertices: b.vertices().iter().map(|v| [v.x, v.y]).collect()},
+            SurfaceBounds::Annulus(b) =>
+                BoundsDescription::Annulus{r_min: b.r_min(), r_max: b.r_max(), average_phi: b.average_phi(), half_phi: b.half_phi()}
+        }
+    }
+}
+
+impl SurfaceDescription {
+
+    /// Builds the sensor described by this entry
+    pub fn build(&self) -> Result<DetectorElement, GeometryError> {
+        let (translation, rotation) = self.placement.matrices()?;
+
+        let mut surface =
+            match self.bounds.planar_bounds()? {
+                Some(bounds) => Surface::Plane(PlaneSurface::new(bounds, translation, rotation)?),
+                None => {
+                    match self.bounds {
+                        BoundsDescription::Disc{r_min, r_max, average_phi, half_phi} =>
+                            Surface::Disc(Disc::new(r_min, r_max, average_phi, half_phi, translation, rotation)?),
+                        _ => unreachable!()
+                    }
+                }
+            };
+
+        surface.set_geometry_id(self.id);
+        surface.set_material(self.material.as_ref().map(|material| material.surface_material()));
+
+        Ok(DetectorElement {surface})
+    }
+
+    /// Description of an already built sensor. Placements are always written as a
+    /// translation and rotation.
+    pub fn from_element(element: &DetectorElement) -> Self {
+        let (center, rotation, bounds) =
+            match &element.surface {
+                Surface::Plane(sensor) =>
+                    (sensor.center_global, sensor.to_global_rot, BoundsDescription::from_bounds(sensor.bounds())),
+                Surface::Disc(sensor) => {
+                    let b = sensor.bounds();
+                    let bounds = BoundsDescription::Disc{r_min: b.r_min(), r_max: b.r_max(), average_phi: b.average_phi(), half_phi: b.half_phi()};
+                    (sensor.center_global, sensor.to_global_rot, bounds)
+                }
+            };
+
+        let translation = Mat4::new_translation(&center.coords);
+
+        SurfaceDescription {
+            id: element.id(),
+            placement: PlacementDescription::from_matrices(&translation, &rotation),
+            bounds,
+            material: element.surface.material().cloned().map(MaterialDescription::Surface)
+        }
+    }
+}
+
+impl DetectorDescription {
+
+    /// Reads a description from a JSON string
+    pub fn from_json(input: &str) -> Result<Self, GeometryError> {
+        Ok(serde_json::from_str(input)?)
+    }
+
+    /// Reads a description from a TOML string
+    pub fn from_toml(input: &str) -> Result<Self, GeometryError> {
+        toml::from_str(input).map_err(|e| GeometryError::Toml(e.to_string()))
+    }
+
+    /// Writes the description to a JSON string
+    pub fn to_json(&self) -> Result<String, GeometryError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes the description to a TOML string
+    pub fn to_toml(&self) -> Result<String, GeometryError> {
+        toml::to_string(self).map_err(|e| GeometryError::Toml(e.to_string()))
+    }
+
+    /// Reads a description from a `.json` or `.toml` file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GeometryError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+
+        match file_format(path)? {
+            Format::Json => Self::from_json(&contents),
+            Format::Toml => Self::from_toml(&contents)
+        }
+    }
+
+    /// Writes the description to a `.json` or `.toml` file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GeometryError> {
+        let path = path.as_ref();
+
+        let contents =
+            match file_format(path)? {
+                Format::Json => self.to_json()?,
+                Format::Toml => self.to_toml()?
+            };
+
+        Ok(fs::write(path, contents)?)
+    }
+
+    /// Builds every sensor of the description. Returns `Err(GeometryError::DuplicateId)`
+    /// if two surfaces share the same id.
+    pub fn build(&self) -> Result<Detector, GeometryError> {
+        let mut ids = self.surfaces.iter().map(|s| s.id).collect::<Vec<_>>();
+        ids.sort();
+
+        if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(GeometryError::DuplicateId(pair[0]))
+        }
+
+        let elements = self.surfaces.iter()
+            .map(|surface| surface.build())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Detector {elements})
+    }
+}
+
+impl Detector {
+
+    /// Loads and builds a detector from a `.json` or `.toml` file
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use kalman_rs::geometry::description::Detector;
+    ///
+    /// let detector = Detector::load("detector.toml").unwrap();
+    /// detector.save("detector.json").unwrap();
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GeometryError> {
+        DetectorDescription::load(path)?.build()
+    }
+
+    /// Writes the detector to a `.json` or `.toml` file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GeometryError> {
+        self.description().save(path)
+    }
+
+    /// Description of all the sensors in the detector
+    pub fn description(&self) -> DetectorDescription {
+        DetectorDescription {
+            surfaces: self.elements.iter().map(SurfaceDescription::from_element).collect()
+        }
+    }
+
+    /// Fetches an element of the detector by its id
+    pub fn element(&self, id: GeometryId) -> Option<&DetectorElement> {
+        self.elements.iter().find(|element| element.id() == id)
+    }
+
+    /// All the elements in the same volume and layer as `layer`
+    pub fn layer_elements(&self, layer: GeometryId) -> Vec<&DetectorElement> {
+        self.elements.iter()
+            .filter(|element| element.id().same_layer(&layer))
+            .collect()
+    }
+}
+
+impl DetectorElement {
+    /// Id of the surface
+    pub fn id(&self) -> GeometryId {
+        self.surface.geometry_id()
+    }
+}
+
+
+pub(super) enum Format {
+    Json,
+    Toml
+}
+
+pub(super) fn file_format(path: &Path) -> Result<Format, GeometryError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => Ok(Format::Json),
+        Some("toml") => Ok(Format::Toml),
+        _ => Err(GeometryError::UnknownFormat(path.display().to_string()))
+    }
+}
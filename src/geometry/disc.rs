@@ -0,0 +1,281 @@
+use nalgebra as na;
+use super::traits::{Transform, Plane, Identified, Outline, HasMaterial};
+use super::id::GeometryId;
+use super::material::SurfaceMaterial;
+use super::super::config::*;
+use super::super::error::*;
+use super::bounds::{AnnulusBounds, Bounds};
+use super::utils::wrap_phi;
+
+/// A struct for endcap sensors whose local frame is polar. Local points are stored
+/// as (r, phi) instead of (x, y) so that measurements near the inner radius are not
+/// distorted by a cartesian approximation.
+#[derive(Debug, Clone)]
+pub struct Disc {
+    pub center_global: P3,  // center of the disc (origin of the polar frame)
+    pub normal : Vec3,      // normal vector of plane
+    pub plane_constant: Real, // D in Ax +By + Cz +D =0
+
+    bounds: AnnulusBounds,
+    geometry_id: GeometryId,
+    material: Option<SurfaceMaterial>,
+
+    pub to_global: Aff3,    // L => G for point (cartesian local frame)
+    pub to_local: Aff3,     // G => L for point (cartesian local frame)
+
+    pub to_global_rot: Mat4,
+    pub to_local_rot: Mat4
+}
+
+impl Disc {
+
+    /// This is the constructor for annular sensors with a polar local frame. The bounds are given by
+    /// `r_min < r < r_max` and `|phi - average_phi| < half_phi`. A full disc uses `half_phi = PI`.
+    /// Like `Rectangle::new` it expects an invertible 4x4 translation and rotation matrix and returns
+    /// `Err(GeometryError::Matrix)` if the transformation cannot be inverted, or
+    /// `Err(GeometryError::Sensor)` if the radii do not satisfy `0 <= r_min < r_max`.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::Disc;
+    ///
+    /// let transform_mat = Mat4::identity();
+    ///
+    /// let sensor = Disc::new(5., 10., PI/2., PI/8., transform_mat, transform_mat);
+    /// ```
+    pub fn new(
+        r_min: Real,
+        r_max: Real,
+        average_phi: Real,
+        half_phi: Real,
+        to_global_translation: Mat4,
+        to_global_rotation: Mat4,
+        ) -> Result<Disc, GeometryError> {
+
+        let to_local_rotation = match to_global_rotation.try_inverse() {
+            Some(rot) => rot,
+            None => return Err(MatrixError::NonInvertible.into())
+        };
+
+        let to_global_transform = Aff3::from_matrix_unchecked(to_global_translation * to_global_rotation);
+
+        let to_local_transform = match to_global_transform.try_inverse() {
+            Some(tfm) => tfm,
+            None => return Err(MatrixError::NonInvertible.into())
+        };
+
+        let bounds = AnnulusBounds::new(r_min, r_max, average_phi, half_phi)?;
+
+        let center_global = to_global_transform * P3::origin();
+
+        // the local z axis expressed in the global frame
+        let normal = (to_global_rotation * na::Vector4::new(0., 0., 1., 0.)).xyz();
+        let plane_constant = normal.dot(&center_global.coords);
+
+        Ok(Disc {
+            center_global,
+            normal,
+            plane_constant,
+            bounds,
+            geometry_id: GeometryId::default(),
+            material: None,
+            to_global: to_global_transform,
+            to_local: to_local_transform,
+            to_global_rot: to_global_rotation,
+            to_local_rot: to_local_rotation
+        })
+    }
+
+    /// Inner radius of the sensor
+    pub fn r_min(&self) -> Real {
+        self.bounds.r_min()
+    }
+
+    /// Outer radius of the sensor
+    pub fn r_max(&self) -> Real {
+        self.bounds.r_max()
+    }
+
+    /// Fetches the annular bounds of the sensor
+    pub fn bounds(&self) -> &AnnulusBounds {
+        &self.bounds
+    }
+
+    /// Converts a local cartesian (x, y) point to local polar (r, phi)
+    pub fn cartesian_to_polar(point: &P2) -> P2 {
+        P2::new(point.coords.norm(), point.y.atan2(point.x))
+    }
+
+    /// Converts a local polar (r, phi) point to local cartesian (x, y)
+    pub fn polar_to_cartesian(point: &P2) -> P2 {
+        P2::new(point.x * point.y.cos(), point.x * point.y.sin())
+    }
+
+    /// Projects a measurement made in the local cartesian frame (and its covariance) into the
+    /// polar frame used by the state vector of this sensor.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::Disc;
+    ///
+    /// let sensor = Disc::new(5., 10., 0., PI, Mat4::identity(), Mat4::identity()).unwrap();
+    /// let (polar_hit, polar_cov) = sensor.polar_measurement(&P2::new(7., 0.), &Mat2::identity());
+    /// ```
+    pub fn polar_measurement(&self, cartesian: &P2, covariance: &Mat2) -> (Vec2, Mat2) {
+        let polar = Self::cartesian_to_polar(cartesian);
+        let jac = polar_jacobian(&polar);
+
+        (polar.coords, jac * covariance * jac.transpose())
+    }
+}
+
+
+/// d(r, phi) / d(x, y) evaluated at a polar point
+fn polar_jacobian(polar: &P2) -> Mat2 {
+    let (sin_phi, cos_phi) = polar.y.sin_cos();
+    let r = polar.x;
+
+    Mat2::new(cos_phi, sin_phi,
+             -sin_phi / r, cos_phi / r)
+}
+
+impl Transform for Disc {
+
+    /// Converts a local polar point (r, phi, _) to the global reference frame.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::traits::*;
+    /// use kalman_rs::geometry::Disc;
+    ///
+    /// let sensor = Disc::new(5., 10., 0., PI, Mat4::identity(), Mat4::identity()).unwrap();
+    /// let global_point = sensor.to_global(P3::new(7., PI/4., 0.));
+    /// ```
+    fn to_global(&self, input_point: P3) -> P3 {
+        let cartesian = Self::polar_to_cartesian(&P2::new(input_point.x, input_point.y));
+        self.to_global * P3::new(cartesian.x, cartesian.y, 0.)
+    }
+
+    /// Converts a global point to the local polar (r, phi) frame of the sensor.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::traits::*;
+    /// use kalman_rs::geometry::Disc;
+    ///
+    /// let sensor = Disc::new(5., 10., 0., PI, Mat4::identity(), Mat4::identity()).unwrap();
+    /// let local_point = sensor.to_local(P3::new(0., 7., 0.));
+    /// ```
+    fn to_local(&self, input_point: P3) -> P2 {
+        let local = self.to_local * input_point;
+        Self::cartesian_to_polar(&P2::new(local.x, local.y))
+    }
+
+    /// Checks if a local (r, phi) point is contained within the annulus of the sensor.
+    fn inside(&self, input: &P2) -> bool {
+        self.distance_to_boundary(input) <= 0.
+    }
+
+    /// Signed distance from a local (r, phi) point to the closest edge of the annulus.
+    fn distance_to_boundary(&self, input: &P2) -> Real {
+        self.bounds.distance_to_boundary_polar(input)
+    }
+
+    fn rotation_to_global(&self) -> &Mat4 {
+        &self.to_global_rot
+    }
+
+    fn rotation_to_local(&self) -> &Mat4 {
+        &self.to_local_rot
+    }
+
+    /// d(global) / d(r, phi) = d(global) / d(x, y) * d(x, y) / d(r, phi)
+    fn local_to_global_derivative(&self, local: &P2) -> Mat3x2 {
+        let (sin_phi, cos_phi) = local.y.sin_cos();
+        let r = local.x;
+
+        let cartesian_derivative = Mat2::new(cos_phi, -r * sin_phi,
+                                             sin_phi, r * cos_phi);
+
+        self.to_global_rot.fixed_slice::<U3, U2>(0, 0) * cartesian_derivative
+    }
+
+    /// d(r, phi) / d(global) = d(r, phi) / d(x, y) * d(x, y) / d(global)
+    fn global_to_local_derivative(&self, local: &P2) -> Mat2x3 {
+        polar_jacobian(local) * self.to_global_rot.transpose().fixed_slice::<U2, U3>(0, 0)
+    }
+
+    /// Moves the phi of the measurement by whole turns to within pi of the phi of `reference`,
+    /// a module spanning phi = +-pi would otherwise see residuals of ~2 pi.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::traits::*;
+    /// use kalman_rs::geometry::Disc;
+    ///
+    /// let sensor = Disc::new(5., 10., PI, 0.5, Mat4::identity(), Mat4::identity()).unwrap();
+    /// let aligned = sensor.align_measurement(&Vec2::new(7., -PI + 0.01), &Vec2::new(7., PI - 0.01));
+    ///
+    /// assert!((aligned.y - (PI + 0.01)).abs() < 1e-12);
+    /// ```
+    fn align_measurement(&self, measurement: &Vec2, reference: &Vec2) -> Vec2 {
+        Vec2::new(measurement.x, reference.y + wrap_phi(measurement.y - reference.y))
+    }
+}
+
+
+impl Plane for Disc {
+
+    /// Check if a given global point is located on the same plane as the sensor
+    fn on_plane(&self, input_point: &P3) -> bool {
+        let pv = input_point - self.center_global;
+
+        self.normal.dot(&pv).abs() <= DOT_PRODUCT_EPSILON
+    }
+
+    fn plane_normal_vec(&self) -> &Vec3 {
+        &self.normal
+    }
+
+    fn global_center(&self) -> &P3 {
+        &self.center_global
+    }
+
+    fn plane_constant(&self) -> Real {
+        self.plane_constant
+    }
+}
+
+impl Identified for Disc {
+    fn geometry_id(&self) -> GeometryId {
+        self.geometry_id
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        self.geometry_id = id;
+    }
+}
+
+impl HasMaterial for Disc {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        self.material.as_ref()
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        self.material = material;
+    }
+}
+
+
+impl Outline for Disc {
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3> {
+        self.bounds.outline(arc_segments).iter()
+            .map(|point| self.to_global * P3::new(point.x, point.y, 0.))
+            .collect()
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+/// Identifier of a surface in the detector. The volume, layer and sensitive indices are packed
+/// into a single `u64` (16 / 16 / 32 bits) so that it is cheap to copy, hash and sort. Sorting
+/// by id groups surfaces by volume and then by layer.
+///
+/// An index of 0 means "not set", so `GeometryId::default()` does not identify any surface.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::geometry::GeometryId;
+///
+/// let id = GeometryId::new(2, 4, 17);
+///
+/// assert_eq!(id.volume(), 2);
+/// assert_eq!(id.layer(), 4);
+/// assert_eq!(id.sensitive(), 17);
+/// assert_eq!(GeometryId::from_value(id.value()), id);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "GeometryIdParts", into = "GeometryIdParts")]
+pub struct GeometryId(u64);
+
+const VOLUME_SHIFT: u64 = 48;
+const LAYER_SHIFT: u64 = 32;
+
+const VOLUME_MASK: u64 = 0xffff << VOLUME_SHIFT;
+const LAYER_MASK: u64 = 0xffff << LAYER_SHIFT;
+const SENSITIVE_MASK: u64 = 0xffff_ffff;
+
+impl GeometryId {
+    pub fn new(volume: u16, layer: u16, sensitive: u32) -> Self {
+        GeometryId(
+            ((volume as u64) << VOLUME_SHIFT) |
+            ((layer as u64) << LAYER_SHIFT) |
+            (sensitive as u64)
+        )
+    }
+
+    /// Rebuilds an id from its packed value
+    pub fn from_value(value: u64) -> Self {
+        GeometryId(value)
+    }
+
+    /// Packed value of the id
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    pub fn volume(&self) -> u16 {
+        ((self.0 & VOLUME_MASK) >> VOLUME_SHIFT) as u16
+    }
+
+    pub fn layer(&self) -> u16 {
+        ((self.0 & LAYER_MASK) >> LAYER_SHIFT) as u16
+    }
+
+    pub fn sensitive(&self) -> u32 {
+        (self.0 & SENSITIVE_MASK) as u32
+    }
+
+    pub fn with_volume(&self, volume: u16) -> Self {
+        Self::new(volume, self.layer(), self.sensitive())
+    }
+
+    pub fn with_layer(&self, layer: u16) -> Self {
+        Self::new(self.volume(), layer, self.sensitive())
+    }
+
+    pub fn with_sensitive(&self, sensitive: u32) -> Self {
+        Self::new(self.volume(), self.layer(), sensitive)
+    }
+
+    /// Id of the layer this surface belongs to (sensitive index set to 0)
+    pub fn layer_id(&self) -> Self {
+        self.with_sensitive(0)
+    }
+
+    /// Id of the volume this surface belongs to (layer and sensitive indices set to 0)
+    pub fn volume_id(&self) -> Self {
+        Self::new(self.volume(), 0, 0)
+    }
+
+    /// Checks if both ids are in the same volume and layer
+    pub fn same_layer(&self, other: &GeometryId) -> bool {
+        self.layer_id() == other.layer_id()
+    }
+}
+
+impl fmt::Display for GeometryId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "vol={}|lay={}|sen={}", self.volume(), self.layer(), self.sensitive())
+    }
+}
+
+
+// written to files as separate indices so that geometry files stay readable
+#[derive(Serialize, Deserialize)]
+struct GeometryIdParts {
+    volume: u16,
+    layer: u16,
+    sensitive: u32
+}
+
+impl From<GeometryIdParts> for GeometryId {
+    fn from(parts: GeometryIdParts) -> Self {
+        GeometryId::new(parts.volume, parts.layer, parts.sensitive)
+    }
+}
+
+impl From<GeometryId> for GeometryIdParts {
+    fn from(id: GeometryId) -> Self {
+        GeometryIdParts {volume: id.volume(), layer: id.layer(), sensitive: id.sensitive()}
+    }
+}
@@ -0,0 +1,168 @@
+use super::bounds::BoundaryCheck;
+use super::bvh::Helix;
+use super::traits::{Transform, Plane};
+use super::super::config::*;
+
+/// Direction of propagation relative to the momentum of the particle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavigationDirection {
+    /// along the momentum
+    Forward,
+    /// against the momentum, e.g. for cosmic ray tracks or backward smoothing
+    Backward
+}
+
+impl Default for NavigationDirection {
+    fn default() -> Self {
+        NavigationDirection::Forward
+    }
+}
+
+impl NavigationDirection {
+    /// +1 for `Forward`, -1 for `Backward`
+    pub fn sign(&self) -> Real {
+        match self {
+            NavigationDirection::Forward => 1.,
+            NavigationDirection::Backward => -1.
+        }
+    }
+
+    pub fn reversed(&self) -> Self {
+        match self {
+            NavigationDirection::Forward => NavigationDirection::Backward,
+            NavigationDirection::Backward => NavigationDirection::Forward
+        }
+    }
+}
+
+/// Where an intersection lies on the surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntersectionStatus {
+    /// within the bounds of the surface (up to the tolerance of the boundary check)
+    Inside,
+    /// on the plane of the surface but outside of its bounds
+    OutsideBounds
+}
+
+/// A point where a track crosses the plane of a surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intersection {
+    pub position: P3,
+    pub local: P2,
+    /// distance along the track in the navigation direction. Negative values are behind the
+    /// starting position.
+    pub path_length: Real,
+    pub status: IntersectionStatus
+}
+
+impl Intersection {
+    /// Checks if the intersection is inside of the surface and can be reached by moving
+    /// in the navigation direction
+    pub fn is_valid(&self) -> bool {
+        (self.status == IntersectionStatus::Inside) && (self.path_length >= 0.)
+    }
+}
+
+/// Closest intersection that is inside of its surface and in front of the starting position
+pub fn nearest_valid(intersections: &[Intersection]) -> Option<&Intersection> {
+    intersections.iter()
+        .filter(|intersection| intersection.is_valid())
+        .min_by(|a, b| a.path_length.partial_cmp(&b.path_length).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// Intersections of straight and helical tracks with a surface
+pub trait Intersect {
+    /// All the points where the straight line through `position` along `direction` crosses
+    /// the surface, ordered by path length. The path lengths are signed relative to the
+    /// navigation direction: a `Backward` navigation moves against `direction`.
+    fn intersect_with_check(&self, position: &P3, direction: &Vec3, navigation: NavigationDirection, check: &BoundaryCheck) -> Vec<Intersection>;
+
+    /// Same as `intersect_with_check` with a strict boundary check
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::Rectangle;
+    /// use kalman_rs::geometry::intersection::*;
+    ///
+    /// let translation = Mat4::new_translation(&Vec3::new(0., 0., -5.));
+    /// let sensor = Rectangle::new(2., 2., translation, Mat4::identity()).unwrap();
+    ///
+    /// // the sensor is behind the particle
+    /// let intersections = sensor.intersect(&P3::origin(), &Vec3::z(), NavigationDirection::Forward);
+    /// assert_eq!(intersections[0].path_length, -5.);
+    /// assert!(nearest_valid(&intersections).is_none());
+    ///
+    /// let intersections = sensor.intersect(&P3::origin(), &Vec3::z(), NavigationDirection::Backward);
+    /// assert_eq!(nearest_valid(&intersections).unwrap().path_length, 5.);
+    /// ```
+    fn intersect(&self, position: &P3, direction: &Vec3, navigation: NavigationDirection) -> Vec<Intersection> {
+        self.intersect_with_check(position, direction, navigation, &BoundaryCheck::Strict)
+    }
+
+    /// All the points where the helix crosses the surface within `max_path` of its starting
+    /// position (in both directions), ordered by path length. The helix is followed in steps
+    /// of `step`, so crossings closer together than a step can be missed.
+    fn intersect_helix(&self, helix: &Helix, max_path: Real, step: Real, navigation: NavigationDirection, check: &BoundaryCheck) -> Vec<Intersection>;
+}
+
+impl <T: Transform + Plane> Intersect for T {
+
+    fn intersect_with_check(&self, position: &P3, direction: &Vec3, navigation: NavigationDirection, check: &BoundaryCheck) -> Vec<Intersection> {
+        let direction = direction.normalize() * navigation.sign();
+        let normal = self.plane_normal_vec();
+        let denominator = normal.dot(&direction);
+
+        if denominator.abs() < Real::EPSILON {
+            return Vec::new()
+        }
+
+        let path_length = (self.global_center() - position).dot(normal) / denominator;
+        let global = position + (direction * path_length);
+
+        vec![intersection_at(self, global, path_length, check)]
+    }
+
+    fn intersect_helix(&self, helix: &Helix, max_path: Real, step: Real, navigation: NavigationDirection, check: &BoundaryCheck) -> Vec<Intersection> {
+        let sign = navigation.sign();
+        let steps = (max_path / step).ceil().max(1.) as usize;
+        let step = max_path / (steps as Real);
+
+        // signed distance to the plane after a path length `s` in the navigation direction
+        let distance = |s: Real| self.plane_normal_vec().dot(&(helix.point(s * sign) - self.global_center()));
+
+        let mut intersections = Vec::new();
+
+        for i in 0..(2 * steps) {
+            let mut low = (step * (i as Real)) - max_path;
+            let mut high = low + step;
+            let (low_distance, high_distance) = (distance(low), distance(high));
+
+            // a crossing exactly on a step belongs to the step that starts there
+            if (low_distance * high_distance > 0.) || ((high_distance == 0.) && (i + 1 < 2 * steps)) {
+                continue
+            }
+
+            for _ in 0..50 {
+                let middle = (low + high) / 2.;
+                if (distance(middle) * low_distance) > 0. {low = middle} else {high = middle}
+            }
+
+            let path_length = (low + high) / 2.;
+            intersections.push(intersection_at(self, helix.point(path_length * sign), path_length, check));
+        }
+
+        intersections
+    }
+}
+
+/// Intersection at a global point on the plane of a sensor
+fn intersection_at<T: Transform + Plane>(sensor: &T, global: P3, path_length: Real, check: &BoundaryCheck) -> Intersection {
+    let local = sensor.to_local(global);
+
+    let status =
+        if sensor.inside_with_tolerance(&local, check) {IntersectionStatus::Inside}
+        else {IntersectionStatus::OutsideBounds};
+
+    Intersection {position: global, local, path_length, status}
+}
@@ -0,0 +1,175 @@
+use std::convert::TryFrom;
+
+use serde::{Serialize, Deserialize};
+
+use super::super::config::*;
+
+/// Bulk properties of a material
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    /// radiation length
+    pub x0: Real,
+    /// nuclear interaction length
+    pub l0: Real,
+    /// relative atomic mass
+    pub a: Real,
+    /// atomic number
+    pub z: Real,
+    /// mass density
+    pub density: Real
+}
+
+impl Material {
+    pub fn new(x0: Real, l0: Real, a: Real, z: Real, density: Real) -> Self {
+        Material {x0, l0, a, z, density}
+    }
+}
+
+/// A layer of material with a given thickness
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MaterialSlab {
+    pub thickness: Real,    // before `material` since TOML writes values before tables
+    pub material: Material
+}
+
+impl MaterialSlab {
+    pub fn new(material: Material, thickness: Real) -> Self {
+        MaterialSlab {thickness, material}
+    }
+
+    /// Thickness in units of the radiation length
+    pub fn thickness_in_x0(&self) -> Real {
+        self.thickness / self.material.x0
+    }
+
+    /// Thickness in units of the nuclear interaction length
+    pub fn thickness_in_l0(&self) -> Real {
+        self.thickness / self.material.l0
+    }
+
+    /// Same material with the thickness multiplied by `factor`. A track crossing the slab at
+    /// an angle sees `thickness / cos(angle)`.
+    pub fn scaled(&self, factor: Real) -> Self {
+        MaterialSlab {thickness: self.thickness * factor.abs(), material: self.material}
+    }
+}
+
+/// Equally sized bins between `min` and `max` along one of the local coordinates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct BinAxis {
+    pub min: Real,
+    pub max: Real,
+    pub bins: usize
+}
+
+impl BinAxis {
+    /// Bin that contains `value`. Values outside of the axis are put in the first or last bin.
+    pub fn bin(&self, value: Real) -> usize {
+        let fraction = (value - self.min) / (self.max - self.min);
+        let bin = (fraction * self.bins as Real).floor();
+
+        if bin.is_nan() || (bin < 0.) {0}
+        else {(bin as usize).min(self.bins - 1)}
+    }
+}
+
+/// Material that changes over a surface, binned in the two local coordinates. For a `Disc`
+/// the local coordinates are (r, phi).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "BinnedMaterialFields")]
+pub struct BinnedMaterial {
+    axis_0: BinAxis,
+    axis_1: BinAxis,
+    slabs: Vec<MaterialSlab>
+}
+
+// unchecked fields of `BinnedMaterial` as they are read from a file
+#[derive(Deserialize)]
+struct BinnedMaterialFields {
+    axis_0: BinAxis,
+    axis_1: BinAxis,
+    slabs: Vec<MaterialSlab>
+}
+
+impl TryFrom<BinnedMaterialFields> for BinnedMaterial {
+    type Error = &'static str;
+
+    fn try_from(fields: BinnedMaterialFields) -> Result<Self, Self::Error> {
+        BinnedMaterial::new(fields.axis_0, fields.axis_1, fields.slabs)
+    }
+}
+
+impl BinnedMaterial {
+
+    /// Creates a material map from the slab of every bin. The bins of `axis_0` vary the
+    /// fastest: the slab of bin (i, j) is `slabs[i + j * axis_0.bins]`. Returns an error if
+    /// an axis has no bins, is empty, or the number of slabs does not match the bins.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::material::*;
+    ///
+    /// let silicon = Material::new(93.7, 465.2, 28.0855, 14., 2.329);
+    /// let axis = BinAxis {min: -1., max: 1., bins: 2};
+    ///
+    /// // thicker on the +x half of the surface
+    /// let slabs = vec![MaterialSlab::new(silicon, 0.1), MaterialSlab::new(silicon, 0.3)];
+    /// let map = BinnedMaterial::new(axis, BinAxis {min: -1., max: 1., bins: 1}, slabs).unwrap();
+    ///
+    /// assert_eq!(map.slab(&P2::new(0.5, 0.)).thickness, 0.3);
+    /// ```
+    pub fn new(axis_0: BinAxis, axis_1: BinAxis, slabs: Vec<MaterialSlab>) -> Result<Self, &'static str> {
+        for axis in &[axis_0, axis_1] {
+            if axis.bins == 0 {
+                return Err("material map axis has no bins")
+            }
+            if axis.min.is_nan() || axis.max.is_nan() || (axis.max <= axis.min) {
+                return Err("material map axis must satisfy min < max")
+            }
+        }
+
+        if slabs.len() != axis_0.bins * axis_1.bins {
+            return Err("number of material slabs does not match the number of bins")
+        }
+
+        Ok(BinnedMaterial {axis_0, axis_1, slabs})
+    }
+
+    pub fn axis_0(&self) -> &BinAxis {
+        &self.axis_0
+    }
+
+    pub fn axis_1(&self) -> &BinAxis {
+        &self.axis_1
+    }
+
+    pub fn slabs(&self) -> &[MaterialSlab] {
+        &self.slabs
+    }
+
+    /// Slab of the bin that contains a local point
+    pub fn slab(&self, local: &P2) -> &MaterialSlab {
+        let (i, j) = (self.axis_0.bin(local.x), self.axis_1.bin(local.y));
+        &self.slabs[i + (j * self.axis_0.bins)]
+    }
+}
+
+/// Material carried by a surface
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SurfaceMaterial {
+    /// the same slab everywhere on the surface
+    Homogeneous(MaterialSlab),
+    Binned(BinnedMaterial)
+}
+
+impl SurfaceMaterial {
+    /// Slab at a local point of the surface
+    pub fn slab(&self, local: &P2) -> &MaterialSlab {
+        match self {
+            SurfaceMaterial::Homogeneous(slab) => slab,
+            SurfaceMaterial::Binned(map) => map.slab(local)
+        }
+    }
+}
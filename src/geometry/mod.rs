@@ -1,7 +1,27 @@
 pub mod trapezoid;
 pub mod rectangle;
+pub mod disc;
+pub mod straw;
+pub mod bounds;
+pub mod material;
+pub mod alignment;
+pub mod plane_surface;
+pub mod surface;
+pub mod builder;
+pub mod description;
+pub mod tracking_geometry;
+pub mod navigator;
+pub mod bvh;
+pub mod intersection;
+pub mod validation;
 pub mod traits;
+pub mod id;
 pub mod utils;
 
 pub use trapezoid::Trapezoid;
-pub use rectangle::Rectangle;
\ No newline at end of file
+pub use rectangle::Rectangle;
+pub use disc::Disc;
+pub use straw::Straw;
+pub use plane_surface::PlaneSurface;
+pub use surface::Surface;
+pub use id::GeometryId;
\ No newline at end of file
@@ -0,0 +1,159 @@
+use super::tracking_geometry::TrackingGeometry;
+use super::surface::Surface;
+use super::id::GeometryId;
+use super::bounds::BoundaryCheck;
+use super::bvh::Helix;
+use super::intersection::{Intersect, Intersection, NavigationDirection, nearest_valid};
+use super::material::MaterialSlab;
+use super::traits::{Plane, Identified, HasMaterial};
+use super::super::config::*;
+
+/// A surface crossed by a straight track
+#[derive(Debug, Clone)]
+pub struct SurfaceCrossing {
+    pub geometry_id: GeometryId,
+    pub path_length: Real,  // distance along the track from its starting position
+    pub global: P3,
+    pub local: P2,
+    /// material of the surface at the crossing, with the thickness seen by the track
+    pub material: Option<MaterialSlab>
+}
+
+/// Finds the surfaces of a `TrackingGeometry` that a straight track crosses
+#[derive(Debug, Clone)]
+pub struct Navigator<'a> {
+    geometry: &'a TrackingGeometry,
+    /// tolerance used when checking that the track crosses a surface inside of its bounds
+    pub boundary_check: BoundaryCheck
+}
+
+impl <'a> Navigator<'a> {
+
+    pub fn new(geometry: &'a TrackingGeometry) -> Self {
+        Navigator {geometry, boundary_check: BoundaryCheck::Strict}
+    }
+
+    pub fn with_boundary_check(geometry: &'a TrackingGeometry, boundary_check: BoundaryCheck) -> Self {
+        Navigator {geometry, boundary_check}
+    }
+
+    pub fn geometry(&self) -> &TrackingGeometry {
+        self.geometry
+    }
+
+    /// All the surfaces crossed by a straight track starting at `position`, ordered by the
+    /// distance along `direction`. Surfaces behind the starting position are not included.
+    /// Only the surfaces found in the bounding volume hierarchy of the geometry are checked,
+    /// unless the boundary check depends on a covariance.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::{GeometryId, PlaneSurface, Surface};
+    /// use kalman_rs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+    /// use kalman_rs::geometry::traits::Identified;
+    /// use kalman_rs::geometry::tracking_geometry::TrackingGeometry;
+    /// use kalman_rs::geometry::navigator::Navigator;
+    ///
+    /// let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    /// let translation = Mat4::new_translation(&Vec3::new(0., 0., 5.));
+    /// let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, Mat4::identity()).unwrap());
+    /// surface.set_geometry_id(GeometryId::new(1, 1, 1));
+    ///
+    /// let geometry = TrackingGeometry::new(vec![surface]).unwrap();
+    /// let crossings = Navigator::new(&geometry).crossings(&P3::origin(), &Vec3::new(0., 0., 1.));
+    ///
+    /// assert_eq!(crossings.len(), 1);
+    /// ```
+    pub fn crossings(&self, position: &P3, direction: &Vec3) -> Vec<SurfaceCrossing> {
+        let direction = &direction.normalize();
+
+        let candidates =
+            match self.boundary_check {
+                BoundaryCheck::Strict => self.geometry.bvh().ray_candidates(position, direction, 0.),
+                BoundaryCheck::Absolute(tolerance) => self.geometry.bvh().ray_candidates(position, direction, tolerance.abs()),
+                // the tolerance is not known until the crossing is found
+                BoundaryCheck::Covariance{..} => self.geometry.surfaces().map(|s| s.geometry_id()).collect()
+            };
+
+        let mut crossings = candidates.iter()
+            .filter_map(|id| self.geometry.surface(*id))
+            .filter_map(|surface| {
+                let intersections = surface.intersect_with_check(position, direction, NavigationDirection::Forward, &self.boundary_check);
+                let Intersection {position: global, local, path_length, ..} = nearest_valid(&intersections)?.clone();
+
+                let material = effective_material(surface, &local, direction);
+                Some(SurfaceCrossing {geometry_id: surface.geometry_id(), path_length, global, local, material})
+            })
+            .collect::<Vec<_>>();
+
+        sort_by_path_length(&mut crossings);
+
+        crossings
+    }
+
+    /// All the surfaces crossed by the first `max_path` of a helix, ordered by path length.
+    /// The helix is followed in straight steps of length `step` to find the candidate surfaces,
+    /// the crossing itself is found by bisection on the helix.
+    pub fn helix_crossings(&self, helix: &Helix, max_path: Real, step: Real) -> Vec<SurfaceCrossing> {
+        let margin =
+            match self.boundary_check {
+                BoundaryCheck::Absolute(tolerance) => tolerance.abs(),
+                _ => 0.
+            };
+
+        let mut crossings = Vec::new();
+
+        for id in self.geometry.bvh().helix_candidates(helix, max_path, step, margin) {
+            let surface = self.geometry.surface(id).expect("bvh surfaces are in the geometry");
+
+            let intersections = surface.intersect_helix(helix, max_path, step, NavigationDirection::Forward, &self.boundary_check);
+
+            for Intersection {position: global, local, path_length, ..} in intersections.into_iter().filter(Intersection::is_valid) {
+                let material = effective_material(surface, &local, &helix.direction(path_length));
+                crossings.push(SurfaceCrossing {geometry_id: id, path_length, global, local, material});
+            }
+        }
+
+        sort_by_path_length(&mut crossings);
+
+        crossings
+    }
+
+    /// Orders the given surfaces by where a straight track crosses their planes. The bounds
+    /// of the surfaces are not checked so that measurements close to an edge are never dropped.
+    /// Surfaces that are not in the geometry or are parallel to the track are left out.
+    pub fn order_surfaces(&self, position: &P3, direction: &Vec3, ids: &[GeometryId]) -> Vec<SurfaceCrossing> {
+        let direction = &direction.normalize();
+
+        let mut crossings = ids.iter()
+            .filter_map(|id| {
+                let surface = self.geometry.surface(*id)?;
+                let intersections = surface.intersect(position, direction, NavigationDirection::Forward);
+                let Intersection {position: global, local, path_length, ..} = intersections.into_iter().next()?;
+
+                let material = effective_material(surface, &local, direction);
+
+                Some(SurfaceCrossing {geometry_id: *id, path_length, global, local, material})
+            })
+            .collect::<Vec<_>>();
+
+        sort_by_path_length(&mut crossings);
+
+        crossings
+    }
+}
+
+
+/// Material slab of a surface at a local point, thickened by the angle between the track
+/// and the normal of the surface
+fn effective_material(surface: &Surface, local: &P2, direction: &Vec3) -> Option<MaterialSlab> {
+    let slab = surface.material_at(local)?;
+    let cos_incidence = surface.plane_normal_vec().normalize().dot(&direction.normalize()).abs();
+
+    Some(slab.scaled(1. / cos_incidence))
+}
+
+fn sort_by_path_length(crossings: &mut [SurfaceCrossing]) {
+    crossings.sort_by(|a, b| a.path_length.partial_cmp(&b.path_length).unwrap_or(std::cmp::Ordering::Equal));
+}
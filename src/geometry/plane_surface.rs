@@ -0,0 +1,164 @@
+use nalgebra as na;
+use super::traits::{Transform, Plane, Identified, Outline, HasMaterial};
+use super::id::GeometryId;
+use super::material::SurfaceMaterial;
+use super::bounds::Bounds;
+use super::super::config::*;
+use super::super::error::*;
+
+/// A planar sensor of any shape. The placement is given by a transformation and the
+/// shape by any type implementing `Bounds`.
+#[derive(Debug, Clone)]
+pub struct PlaneSurface<B: Bounds> {
+    pub center_global: P3,
+    pub normal : Vec3,
+    pub plane_constant: Real,
+
+    bounds: B,
+    geometry_id: GeometryId,
+    material: Option<SurfaceMaterial>,
+
+    pub to_global: Aff3,
+    pub to_local: Aff3,
+
+    pub to_global_rot: Mat4,
+    pub to_local_rot: Mat4
+}
+
+impl <B: Bounds> PlaneSurface<B> {
+
+    /// Creates a planar sensor from its bounds and an invertible 4x4 translation and rotation
+    /// matrix. If either matrix is not invertible it will return `Err(MatrixError)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::PlaneSurface;
+    /// use kalman_rs::geometry::bounds::DiamondBounds;
+    ///
+    /// let bounds = DiamondBounds::new(1., 3., 2., 2., 4.).unwrap();
+    /// let transform_mat = Mat4::identity();
+    ///
+    /// let sensor = PlaneSurface::new(bounds, transform_mat, transform_mat).unwrap();
+    /// ```
+    pub fn new(
+        bounds: B,
+        to_global_translation: Mat4,
+        to_global_rotation: Mat4
+        ) -> Result<Self, MatrixError> {
+
+        let to_local_rotation = match to_global_rotation.try_inverse() {
+            Some(rot) => rot,
+            None => return Err(MatrixError::NonInvertible)
+        };
+
+        let to_global = Aff3::from_matrix_unchecked(to_global_translation * to_global_rotation);
+
+        let to_local = match to_global.try_inverse() {
+            Some(tfm) => tfm,
+            None => return Err(MatrixError::NonInvertible)
+        };
+
+        let center_global = to_global * P3::origin();
+
+        // the local z axis expressed in the global frame
+        let normal = (to_global_rotation * na::Vector4::new(0., 0., 1., 0.)).xyz();
+        let plane_constant = normal.dot(&center_global.coords);
+
+        Ok(PlaneSurface {
+            center_global,
+            normal,
+            plane_constant,
+            bounds,
+            geometry_id: GeometryId::default(),
+            material: None,
+            to_global,
+            to_local,
+            to_global_rot: to_global_rotation,
+            to_local_rot: to_local_rotation
+        })
+    }
+
+    /// Fetches the bounds of the sensor
+    pub fn bounds(&self) -> &B {
+        &self.bounds
+    }
+}
+
+impl <B: Bounds> Transform for PlaneSurface<B> {
+
+    fn to_global(&self, input_point: P3) -> P3 {
+        self.to_global * input_point
+    }
+
+    fn to_local(&self, input_point: P3) -> P2 {
+        let local = self.to_local * input_point;
+        P2::new(local.x, local.y)
+    }
+
+    fn inside(&self, input: &P2) -> bool {
+        self.bounds.inside(input, 0.)
+    }
+
+    fn distance_to_boundary(&self, input: &P2) -> Real {
+        self.bounds.distance_to_boundary(input)
+    }
+
+    fn rotation_to_global(&self) -> &Mat4 {
+        &self.to_global_rot
+    }
+
+    fn rotation_to_local(&self) -> &Mat4 {
+        &self.to_local_rot
+    }
+}
+
+impl <B: Bounds> Plane for PlaneSurface<B> {
+
+    fn on_plane(&self, input_point: &P3) -> bool {
+        let pv = input_point - self.center_global;
+
+        self.normal.dot(&pv).abs() <= DOT_PRODUCT_EPSILON
+    }
+
+    fn plane_normal_vec(&self) -> &Vec3 {
+        &self.normal
+    }
+
+    fn global_center(&self) -> &P3 {
+        &self.center_global
+    }
+
+    fn plane_constant(&self) -> Real {
+        self.plane_constant
+    }
+}
+
+impl <B: Bounds> Identified for PlaneSurface<B> {
+    fn geometry_id(&self) -> GeometryId {
+        self.geometry_id
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        self.geometry_id = id;
+    }
+}
+
+impl <B: Bounds> HasMaterial for PlaneSurface<B> {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        self.material.as_ref()
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        self.material = material;
+    }
+}
+
+
+impl <B: Bounds> Outline for PlaneSurface<B> {
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3> {
+        self.bounds.outline(arc_segments).iter()
+            .map(|point| self.to_global * P3::new(point.x, point.y, 0.))
+            .collect()
+    }
+}
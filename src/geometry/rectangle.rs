@@ -1,18 +1,22 @@
 use nalgebra as na;
-use super::traits::{Transform, Plane};
+use super::traits::{Transform, Plane, Identified, Outline, HasMaterial};
+use super::id::GeometryId;
+use super::material::SurfaceMaterial;
+use super::bounds::{Bounds, RectangleBounds};
 use super::super::config::*;
 use super::super::error::*;
 use super::utils;
 
 /// A struct for sensors of rectangular geometry
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Rectangle {
     pub center_global: P3,  //center of the sensor (not used in bound checks)
     pub normal : Vec3,      // normal vector of plane
     pub plane_constant: Real, // D in Ax +By + Cz +D =0 
 
-    half_base: Real,
-    half_height: Real,
+    bounds: RectangleBounds,
+    geometry_id: GeometryId,
+    material: Option<SurfaceMaterial>,
 
     pub to_global: Aff3,    // L => G for point
     pub to_local: Aff3,     // G => L for point
@@ -70,8 +74,9 @@ impl Rectangle {
                 let center_global = to_global_transform * orig;
                 let plane_const = (center_global.x * normal_vector.x + center_global.y * normal_vector.y + center_global.z * normal_vector.z);
 
-                let rect = Rectangle{half_base: half_base, 
-                             half_height: half_height,
+                let rect = Rectangle{bounds: RectangleBounds::new(half_base, half_height),
+                             geometry_id: GeometryId::default(),
+                             material: None,
                              normal: normal_vector,
                              plane_constant: plane_const,
                              center_global: center_global,
@@ -98,6 +103,11 @@ impl Rectangle {
         Self::new(base, height, to_global, rot).expect("could not generate rect. sensor")
     }
 
+    /// Fetches the rectangular bounds of the sensor
+    pub fn bounds(&self) -> &RectangleBounds {
+        &self.bounds
+    }
+
     pub fn new_test_sensor(
         base: Real,
         height: Real,
@@ -126,8 +136,9 @@ impl Rectangle {
 
         // dbg!{plane_constant};
         Rectangle{
-            half_base: base/2.,
-            half_height: height / 2.,
+            bounds: RectangleBounds::new(base / 2., height / 2.),
+            geometry_id: GeometryId::default(),
+            material: None,
             normal: normal ,
             plane_constant: plane_constant,
             center_global: global_center,
@@ -194,13 +205,11 @@ impl Transform for Rectangle{
     /// let is_inside_bounds: bool = sensor.inside(&local_point);
     /// ```
     fn inside(&self, input: &P2) -> bool {
-        
-        if (input.x.abs() < self.half_base.abs()) && (input.y.abs() < self.half_height.abs()) {
-            true
-        }
-        else {
-            false
-        }
+        self.bounds.inside(input, 0.)
+    }
+
+    fn distance_to_boundary(&self, input: &P2) -> Real {
+        self.bounds.distance_to_boundary(input)
     }
     fn rotation_to_global(&self) -> &Mat4{
         &self.to_global_rot
@@ -251,3 +260,32 @@ impl Plane for Rectangle{
     }
 
 }
+
+impl Identified for Rectangle {
+    fn geometry_id(&self) -> GeometryId {
+        self.geometry_id
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        self.geometry_id = id;
+    }
+}
+
+impl HasMaterial for Rectangle {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        self.material.as_ref()
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        self.material = material;
+    }
+}
+
+
+impl Outline for Rectangle {
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3> {
+        self.bounds.outline(arc_segments).iter()
+            .map(|point| self.to_global * P3::new(point.x, point.y, 0.))
+            .collect()
+    }
+}
@@ -0,0 +1,185 @@
+use super::super::config::*;
+use super::super::error::*;
+use super::bounds::{Bounds, BoundaryCheck, RectangleBounds};
+use super::traits::{Identified, HasMaterial};
+use super::id::GeometryId;
+use super::material::SurfaceMaterial;
+
+/// A struct for line (straw / drift tube) sensors. The surface is defined by a wire position
+/// and direction. Local coordinates are (signed distance of closest approach, position along the wire).
+/// The sign of the distance is positive when the track passes the wire on the side of
+/// `wire_direction x track_direction`, which is why most conversions need the track direction.
+#[derive(Debug, Clone)]
+pub struct Straw {
+    pub wire_position: P3,      // point on the wire, origin of the position along the wire
+    pub wire_direction: Vec3,   // unit vector along the wire
+
+    radius: Real,       // radius of the tube
+    half_length: Real,  // half length of the wire
+    geometry_id: GeometryId,
+    material: Option<SurfaceMaterial>
+}
+
+impl Straw {
+
+    /// Constructor for straw sensors. The wire direction is normalized, and a
+    /// zero length direction returns `Err(SensorError::InvalidDirection)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::Straw;
+    ///
+    /// let wire_position = P3::new(10., 0., 0.);
+    /// let wire_direction = Vec3::new(0., 0., 1.);
+    ///
+    /// let straw = Straw::new(wire_position, wire_direction, 1.5, 100.).unwrap();
+    /// ```
+    pub fn new(
+        wire_position: P3,
+        wire_direction: Vec3,
+        radius: Real,
+        half_length: Real
+        ) -> Result<Straw, SensorError> {
+
+        let norm = wire_direction.norm();
+
+        if norm <= DOT_PRODUCT_EPSILON {
+            return Err(SensorError::InvalidDirection(wire_direction))
+        }
+
+        Ok(Straw {
+            wire_position,
+            wire_direction: wire_direction / norm,
+            radius: radius.abs(),
+            half_length: half_length.abs(),
+            geometry_id: GeometryId::default(),
+            material: None
+        })
+    }
+
+    /// Radius of the tube
+    pub fn radius(&self) -> Real {
+        self.radius
+    }
+
+    /// Half of the length of the wire
+    pub fn half_length(&self) -> Real {
+        self.half_length
+    }
+
+    /// Unit vector of the signed distance axis for a given track direction.
+    /// Returns `None` if the track is parallel to the wire.
+    pub fn measurement_axis(&self, direction: &Vec3) -> Option<Vec3> {
+        let axis = self.wire_direction.cross(direction);
+        let norm = axis.norm();
+
+        if norm <= DOT_PRODUCT_EPSILON {None}
+        else {Some(axis / norm)}
+    }
+
+    /// Finds the point of closest approach of a straight track to the wire. Returns the global point
+    /// on the track and the signed path length from `position` to that point, or `None` if the track
+    /// is parallel to the wire.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::Straw;
+    ///
+    /// let straw = Straw::new(P3::new(10., 0., 0.), Vec3::new(0., 0., 1.), 1.5, 100.).unwrap();
+    /// let (pca, path_length) =
+    ///     straw.point_of_closest_approach(&P3::new(0., 1., 0.), &Vec3::new(1., 0., 0.)).unwrap();
+    /// ```
+    pub fn point_of_closest_approach(&self, position: &P3, direction: &Vec3) -> Option<(P3, Real)> {
+        let direction = direction.normalize();
+
+        let w0 = position - self.wire_position;
+        let b = direction.dot(&self.wire_direction);
+        let d = direction.dot(&w0);
+        let e = self.wire_direction.dot(&w0);
+
+        let denominator = 1. - (b * b);
+        if denominator <= DOT_PRODUCT_EPSILON {
+            return None
+        }
+
+        let path_length = ((b * e) - d) / denominator;
+
+        Some((position + (direction * path_length), path_length))
+    }
+
+    /// Converts a global point (at the point of closest approach) to local (signed distance, z) coordinates.
+    pub fn to_local(&self, input_point: &P3, direction: &Vec3) -> P2 {
+        let offset = input_point - self.wire_position;
+        let z = offset.dot(&self.wire_direction);
+        let radial = offset - (self.wire_direction * z);
+
+        let signed_distance =
+            match self.measurement_axis(direction) {
+                Some(axis) => radial.dot(&axis),
+                None => radial.norm()
+            };
+
+        P2::new(signed_distance, z)
+    }
+
+    /// Converts local (signed distance, z) coordinates to a global point.
+    /// Returns `None` if the track is parallel to the wire.
+    pub fn to_global(&self, input_point: &P2, direction: &Vec3) -> Option<P3> {
+        let axis = self.measurement_axis(direction)?;
+
+        Some(self.wire_position + (self.wire_direction * input_point.y) + (axis * input_point.x))
+    }
+
+    /// Checks if a local (signed distance, z) point is inside the tube.
+    pub fn inside(&self, input: &P2) -> bool {
+        (input.x.abs() <= self.radius) && (input.y.abs() <= self.half_length)
+    }
+
+    /// Signed distance from a local (signed distance, z) point to the wall or end of the tube.
+    pub fn distance_to_boundary(&self, input: &P2) -> Real {
+        RectangleBounds::new(self.radius, self.half_length).distance_to_boundary(input)
+    }
+
+    /// Checks if a local point is inside the tube, accepting points that are outside by less
+    /// than the tolerance of `check`.
+    pub fn inside_with_tolerance(&self, input: &P2, check: &BoundaryCheck) -> bool {
+        let tolerance = check.tolerance(input, |local| self.distance_to_boundary(local));
+        self.distance_to_boundary(input) <= tolerance
+    }
+
+    /// Derivative of the global position with respect to the local coordinates.
+    pub fn local_to_global_derivative(&self, direction: &Vec3) -> Option<Mat3x2> {
+        let axis = self.measurement_axis(direction)?;
+
+        Some(Mat3x2::from_columns(&[axis, self.wire_direction]))
+    }
+
+    /// Derivative of the local coordinates with respect to the global position.
+    pub fn global_to_local_derivative(&self, direction: &Vec3) -> Option<Mat2x3> {
+        let axis = self.measurement_axis(direction)?;
+
+        Some(Mat2x3::from_rows(&[axis.transpose(), self.wire_direction.transpose()]))
+    }
+}
+
+impl Identified for Straw {
+    fn geometry_id(&self) -> GeometryId {
+        self.geometry_id
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        self.geometry_id = id;
+    }
+}
+
+impl HasMaterial for Straw {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        self.material.as_ref()
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        self.material = material;
+    }
+}
@@ -0,0 +1,131 @@
+use super::traits::{Transform, Plane, Identified, Outline, HasMaterial};
+use super::material::SurfaceMaterial;
+use super::id::GeometryId;
+use super::bounds::{Bounds, SurfaceBounds};
+use super::plane_surface::PlaneSurface;
+use super::disc::Disc;
+use super::super::config::*;
+
+/// A sensor whose type is only known at runtime. All the sensors of a detector loaded from
+/// a file are stored as `Surface` so that they can be used together in a single `Vec`.
+#[derive(Debug, Clone)]
+pub enum Surface {
+    /// planar sensor with a cartesian local frame
+    Plane(PlaneSurface<SurfaceBounds>),
+    /// planar sensor with a polar local frame
+    Disc(Disc)
+}
+
+impl Surface {
+    /// Corners of the surface in its local cartesian frame
+    pub fn local_outline(&self, arc_segments: usize) -> Vec<P2> {
+        match self {
+            Surface::Plane(sensor) => sensor.bounds().outline(arc_segments),
+            Surface::Disc(sensor) => sensor.bounds().outline(arc_segments)
+        }
+    }
+
+    /// Converts a point in the local cartesian frame to the global frame. For a `Disc`
+    /// this differs from `to_global` which takes a polar point.
+    pub fn cartesian_to_global(&self, point: &P2) -> P3 {
+        let to_global =
+            match self {
+                Surface::Plane(sensor) => &sensor.to_global,
+                Surface::Disc(sensor) => &sensor.to_global
+            };
+
+        to_global * P3::new(point.x, point.y, 0.)
+    }
+}
+
+// calls the same method on whichever sensor is stored
+macro_rules! dispatch {
+    ($self:ident, $sensor:ident => $call:expr) => {
+        match $self {
+            Surface::Plane($sensor) => $call,
+            Surface::Disc($sensor) => $call
+        }
+    };
+}
+
+impl Transform for Surface {
+    fn to_global(&self, input_point: P3) -> P3 {
+        dispatch!(self, sensor => sensor.to_global(input_point))
+    }
+
+    fn to_local(&self, input_point: P3) -> P2 {
+        dispatch!(self, sensor => sensor.to_local(input_point))
+    }
+
+    fn inside(&self, input: &P2) -> bool {
+        dispatch!(self, sensor => sensor.inside(input))
+    }
+
+    fn distance_to_boundary(&self, input: &P2) -> Real {
+        dispatch!(self, sensor => sensor.distance_to_boundary(input))
+    }
+
+    fn rotation_to_global(&self) -> &Mat4 {
+        dispatch!(self, sensor => sensor.rotation_to_global())
+    }
+
+    fn rotation_to_local(&self) -> &Mat4 {
+        dispatch!(self, sensor => sensor.rotation_to_local())
+    }
+
+    fn local_to_global_derivative(&self, local: &P2) -> Mat3x2 {
+        dispatch!(self, sensor => sensor.local_to_global_derivative(local))
+    }
+
+    fn global_to_local_derivative(&self, local: &P2) -> Mat2x3 {
+        dispatch!(self, sensor => sensor.global_to_local_derivative(local))
+    }
+
+    fn align_measurement(&self, measurement: &Vec2, reference: &Vec2) -> Vec2 {
+        dispatch!(self, sensor => sensor.align_measurement(measurement, reference))
+    }
+}
+
+impl Plane for Surface {
+    fn on_plane(&self, input_point: &P3) -> bool {
+        dispatch!(self, sensor => sensor.on_plane(input_point))
+    }
+
+    fn plane_normal_vec(&self) -> &Vec3 {
+        dispatch!(self, sensor => sensor.plane_normal_vec())
+    }
+
+    fn plane_constant(&self) -> Real {
+        dispatch!(self, sensor => sensor.plane_constant())
+    }
+
+    fn global_center(&self) -> &P3 {
+        dispatch!(self, sensor => sensor.global_center())
+    }
+}
+
+impl Outline for Surface {
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3> {
+        dispatch!(self, sensor => sensor.global_outline(arc_segments))
+    }
+}
+
+impl Identified for Surface {
+    fn geometry_id(&self) -> GeometryId {
+        dispatch!(self, sensor => sensor.geometry_id())
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        dispatch!(self, sensor => sensor.set_geometry_id(id))
+    }
+}
+
+impl HasMaterial for Surface {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        dispatch!(self, sensor => sensor.material())
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        dispatch!(self, sensor => sensor.set_material(material))
+    }
+}
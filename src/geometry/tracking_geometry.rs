@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use super::surface::Surface;
+use super::id::GeometryId;
+use super::traits::Identified;
+use super::description::Detector;
+use super::bvh::SurfaceBvh;
+use super::super::error::*;
+
+/// Sensitive surfaces that share the same volume and layer index
+#[derive(Debug, Clone)]
+pub struct Layer {
+    id: GeometryId,     // sensitive index is always 0
+    surfaces: Vec<Surface>
+}
+
+/// Layers that share the same volume index
+#[derive(Debug, Clone)]
+pub struct Volume {
+    id: GeometryId,     // layer and sensitive indices are always 0
+    layers: Vec<Layer>
+}
+
+/// All the surfaces of a detector organised into volumes and layers by their `GeometryId`.
+/// Volumes, layers and surfaces are sorted by id.
+#[derive(Debug, Clone)]
+pub struct TrackingGeometry {
+    volumes: Vec<Volume>,
+    // id -> (volume index, layer index, surface index)
+    lookup: HashMap<GeometryId, (usize, usize, usize)>,
+    bvh: SurfaceBvh
+}
+
+impl Layer {
+    pub fn id(&self) -> GeometryId {
+        self.id
+    }
+
+    pub fn surfaces(&self) -> &[Surface] {
+        &self.surfaces
+    }
+}
+
+impl Volume {
+    pub fn id(&self) -> GeometryId {
+        self.id
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+}
+
+impl TrackingGeometry {
+
+    /// Organises surfaces by the volume and layer indices of their ids. Returns
+    /// `Err(GeometryError::DuplicateId)` if two surfaces have the same id.
+    ///
+    /// # Examples
+    /// ```
+    /// use kalman_rs::config::*;
+    /// use kalman_rs::geometry::{GeometryId, PlaneSurface, Surface};
+    /// use kalman_rs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+    /// use kalman_rs::geometry::traits::Identified;
+    /// use kalman_rs::geometry::tracking_geometry::TrackingGeometry;
+    ///
+    /// let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    /// let mut surface = Surface::Plane(PlaneSurface::new(bounds, Mat4::identity(), Mat4::identity()).unwrap());
+    /// surface.set_geometry_id(GeometryId::new(1, 1, 1));
+    ///
+    /// let geometry = TrackingGeometry::new(vec![surface]).unwrap();
+    /// assert!(geometry.surface(GeometryId::new(1, 1, 1)).is_some());
+    /// ```
+    pub fn new(mut surfaces: Vec<Surface>) -> Result<Self, GeometryError> {
+        surfaces.sort_by_key(|surface| surface.geometry_id());
+
+        let mut volumes: Vec<Volume> = Vec::new();
+
+        for surface in surfaces {
+            let id = surface.geometry_id();
+
+            let new_volume = volumes.last().map(|volume| volume.id) != Some(id.volume_id());
+            if new_volume {
+                volumes.push(Volume {id: id.volume_id(), layers: Vec::new()});
+            }
+            let volume = volumes.last_mut().unwrap();
+
+            let new_layer = volume.layers.last().map(|layer| layer.id) != Some(id.layer_id());
+            if new_layer {
+                volume.layers.push(Layer {id: id.layer_id(), surfaces: Vec::new()});
+            }
+            let layer = volume.layers.last_mut().unwrap();
+
+            if layer.surfaces.last().map(|last| last.geometry_id()) == Some(id) {
+                return Err(GeometryError::DuplicateId(id))
+            }
+
+            layer.surfaces.push(surface);
+        }
+
+        let mut lookup = HashMap::new();
+        for (i, volume) in volumes.iter().enumerate() {
+            for (j, layer) in volume.layers.iter().enumerate() {
+                for (k, surface) in layer.surfaces.iter().enumerate() {
+                    lookup.insert(surface.geometry_id(), (i, j, k));
+                }
+            }
+        }
+
+        let mut geometry = TrackingGeometry {volumes, lookup, bvh: SurfaceBvh::new(Vec::new())};
+        geometry.bvh = SurfaceBvh::new(geometry.surfaces());
+
+        Ok(geometry)
+    }
+
+    /// Organises all the surfaces of a detector loaded from a file
+    pub fn from_detector(detector: &Detector) -> Result<Self, GeometryError> {
+        Self::new(detector.elements.iter().map(|element| element.surface.clone()).collect())
+    }
+
+    pub fn volumes(&self) -> &[Volume] {
+        &self.volumes
+    }
+
+    /// Fetches a volume by the volume index of `id`
+    pub fn volume(&self, id: GeometryId) -> Option<&Volume> {
+        self.volumes.iter().find(|volume| volume.id == id.volume_id())
+    }
+
+    /// Fetches a layer by the volume and layer indices of `id`
+    pub fn layer(&self, id: GeometryId) -> Option<&Layer> {
+        self.volume(id)?.layers.iter().find(|layer| layer.id == id.layer_id())
+    }
+
+    /// Fetches a surface by its id
+    pub fn surface(&self, id: GeometryId) -> Option<&Surface> {
+        let (i, j, k) = self.lookup.get(&id)?;
+        Some(&self.volumes[*i].layers[*j].surfaces[*k])
+    }
+
+    /// All the surfaces of the geometry, sorted by id
+    pub fn surfaces(&self) -> impl Iterator<Item = &Surface> {
+        self.volumes.iter()
+            .flat_map(|volume| volume.layers.iter())
+            .flat_map(|layer| layer.surfaces.iter())
+    }
+
+    /// Bounding volume hierarchy over all the surfaces, used to find the surfaces
+    /// close to a track without checking all of them
+    pub fn bvh(&self) -> &SurfaceBvh {
+        &self.bvh
+    }
+
+    /// Number of surfaces in the geometry
+    pub fn len(&self) -> usize {
+        self.lookup.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lookup.is_empty()
+    }
+}
@@ -1,4 +1,7 @@
 use super::super::config::*;
+use super::bounds::BoundaryCheck;
+use super::id::GeometryId;
+use super::material::{SurfaceMaterial, MaterialSlab};
 
 /// Finding the attributes of a generic sensor's plane
 pub trait Plane {
@@ -36,6 +39,17 @@ pub trait Transform{
     /// Checks if a local point is contained within the bounds of a sensor.
     fn inside(&self, input: &P2) -> bool;
 
+    /// Signed distance from a local point to the closest edge of the sensor. The distance is
+    /// negative inside of the bounds and positive outside of them.
+    fn distance_to_boundary(&self, input: &P2) -> Real;
+
+    /// Checks if a local point is contained within the bounds of a sensor, accepting points
+    /// that are outside of the bounds by less than the tolerance of `check`.
+    fn inside_with_tolerance(&self, input: &P2, check: &BoundaryCheck) -> bool {
+        let tolerance = check.tolerance(input, |local| self.distance_to_boundary(local));
+        self.distance_to_boundary(input) <= tolerance
+    }
+
     /// Fetches the rotation matrix from local -> global of the sensor.Default
     /// This is done so that KF calculations can be generic over sensor types
     fn rotation_to_global(&self) -> &Mat4;
@@ -45,4 +59,54 @@ pub trait Transform{
     /// This is done so that KF calculations can be generic over sensor types
     fn rotation_to_local(&self) -> &Mat4;
 
+    /// Derivative of the global position with respect to the local coordinates at `local`.
+    /// Sensors with a cartesian local frame use the first two columns of the rotation to global.
+    fn local_to_global_derivative(&self, _local: &P2) -> Mat3x2 {
+        self.rotation_to_global().fixed_slice::<U3, U2>(0, 0).into_owned()
+    }
+
+    /// Derivative of the local coordinates with respect to the global position at `local`.
+    /// Sensors with a cartesian local frame use the first two rows of the rotation to local.
+    fn global_to_local_derivative(&self, _local: &P2) -> Mat2x3 {
+        self.rotation_to_global().transpose().fixed_slice::<U2, U3>(0, 0).into_owned()
+    }
+
+    /// Expresses a measurement next to the local point `reference`, so that the residual
+    /// between them can be taken as a plain difference. Cartesian local frames return the
+    /// measurement unchanged.
+    fn align_measurement(&self, measurement: &Vec2, _reference: &Vec2) -> Vec2 {
+        *measurement
+    }
+
+}
+
+/// Shape of a planar sensor in the global frame, used to draw it or to find its extent
+pub trait Outline {
+    /// Corners of the sensor in the global frame. Curved edges are approximated by
+    /// `arc_segments` straight segments.
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3>;
+}
+
+/// Identification of a sensor within the detector. Sensors are created with an unset
+/// (`GeometryId::default()`) id.
+pub trait Identified {
+    /// Fetches the id of the sensor
+    fn geometry_id(&self) -> GeometryId;
+
+    /// Changes the id of the sensor
+    fn set_geometry_id(&mut self, id: GeometryId);
+}
+
+/// Material of a sensor. Sensors are created without material.
+pub trait HasMaterial {
+    /// Fetches the material of the sensor, if it has any
+    fn material(&self) -> Option<&SurfaceMaterial>;
+
+    /// Changes the material of the sensor
+    fn set_material(&mut self, material: Option<SurfaceMaterial>);
+
+    /// Material slab at a local point of the sensor
+    fn material_at(&self, local: &P2) -> Option<&MaterialSlab> {
+        self.material().map(|material| material.slab(local))
+    }
 }
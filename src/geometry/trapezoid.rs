@@ -1,64 +1,26 @@
 use nalgebra as na;
 
-use super::traits::{Transform, Plane};
+use super::traits::{Transform, Plane, Identified, Outline, HasMaterial};
+use super::id::GeometryId;
+use super::material::SurfaceMaterial;
+use super::bounds::{Bounds, TrapezoidBounds};
 use super::utils;
 
 use super::super::config::*;
 use super::super::error::*;
 
-// Struct to calculate the y value at any given x
-// This is used instead of a closure since closures require 
-// heap allocation w/ trait objects (dynamic dispatch)
-#[derive(Debug)]
-pub struct Line {
-    pub yint: Real,
-    pub slope: Real
-}
-impl Line {//
-    pub fn new_from_points(p1: &P2, p2: &P2) -> Self{
-        let slope = (p2.y - p1.y)/ (p2.x - p1.x);
-
-        // y = m*x + [-m*x_0 + y_0] <- yint
-        let prod = -1 as Real *(p1.x*slope);
-        let yint = prod + p1.y;
-
-        Line{yint: yint, slope:slope}
-    }
-
-    // a known y intercept / slope
-    pub fn new_from_values(yint: Real, slope: Real) -> Self {
-        Line{yint: yint, slope: slope}
-    }
-
-    pub fn new_from_y_axis_reflection(line: &Line) -> Self{
-        let new_slope = (-1 as Real) * line.slope;
-        Line{yint: line.yint , slope: new_slope }
-    }
-
-    pub fn call(&self, point: &P2) -> P2 {
-        // "the maximum height we can have with that x value"
-        let y = (self.slope * point.x) + self.yint;
-        // "the maximum x value we can have when we have that y value"
-        let x = (point.y - self.yint)/self.slope;
-        P2::new(x, y)
-    }
-}
-
 /// A struct for sensors of trapezoidal geometry
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Trapezoid{
-    half_height: Real,
     normal: Vec3,
     center_global: P3,
 
     to_global: Aff3,
     to_local : Aff3,
 
-    left_line: Line,    // equation of line used for bounds checking 
-    right_line: Line,  //   ''
-
-    max_half_width: Real,
-    min_half_width: Real,
+    bounds: TrapezoidBounds,
+    geometry_id: GeometryId,
+    material: Option<SurfaceMaterial>,
 
     pub to_global_rot: Mat4,
     pub to_local_rot: Mat4
@@ -67,7 +29,8 @@ pub struct Trapezoid{
 impl Trapezoid{
     
     /// This is the constructor for the rectangular geometry. It expects a 4x4 `nalgebra::Matrix4<f64>` that is invertible 
-    /// and a 4 element array of `nalgebra::Point3<f64>`. If the matrix is not invertible it will return `Err(&str)`.
+    /// and a 4 element array of `nalgebra::Point3<f64>`. If the matrix is not invertible it will return
+    /// `Err(GeometryError::Matrix)`, degenerate bases or heights return `Err(GeometryError::Sensor)`.
     /// The provided matrix should be an affine transformation for converting from R2->R3
     /// 
     /// # Examples
@@ -88,14 +51,20 @@ impl Trapezoid{
             base_bot: Real, 
             to_global_translation: Mat4,
             to_global_rotation: Mat4,
-            height: Real) -> Result<Trapezoid, MatrixError> {
+            height: Real) -> Result<Trapezoid, GeometryError> {
         
         let compose = to_global_translation * to_global_rotation;
 
         let to_global_transform = Aff3::from_matrix_unchecked(compose);
-        let to_local_transform = to_global_transform.try_inverse().expect("local -> global trap matrix non invertible");
+        let to_local_transform = match to_global_transform.try_inverse() {
+            Some(tfm) => tfm,
+            None => return Err(MatrixError::NonInvertible.into())
+        };
 
-        let to_local_rotation = to_global_rotation.try_inverse().expect("local -> global trap matrix non invertible");
+        let to_local_rotation = match to_global_rotation.try_inverse() {
+            Some(rot) => rot,
+            None => return Err(MatrixError::NonInvertible.into())
+        };
 
 
         // calculate half lengths
@@ -106,11 +75,8 @@ impl Trapezoid{
         // normal vector calculation
         let normal_vector = utils::plane_normal_vector(half_b1, half_height);
         
-        // equations of lines along slope of trapezoid for bounding checks
-        let top_right_corner = P2::new(half_b1, half_height);
-        let bottom_right_corner = P2::new(half_b2, -half_height);
-        let right_line_eq = Line::new_from_points(&top_right_corner, &bottom_right_corner);
-        let left_line_eq = Line::new_from_y_axis_reflection(&right_line_eq);
+        // the top base lies along +y and the bottom base along -y
+        let bounds = TrapezoidBounds::new(half_b2, half_b1, half_height)?;
 
         let local_center = P3::new(0., 0., 0.);
         let global_center = to_global_transform * local_center;
@@ -118,15 +84,13 @@ impl Trapezoid{
 
         let trap = 
             Trapezoid{
-                half_height: half_height,
                 normal: normal_vector,
                 center_global: global_center,
                 to_global: to_global_transform,
                 to_local: to_local_transform,
-                left_line: left_line_eq,
-                right_line: right_line_eq,
-                max_half_width: half_b1.max(half_b2),
-                min_half_width: half_b1.min(half_b2),
+                bounds,
+                geometry_id: GeometryId::default(),
+                material: None,
                 to_global_rot: to_global_rotation,
                 to_local_rot: to_local_rotation
             };
@@ -147,8 +111,12 @@ impl Trapezoid{
         Self::new(base_top, base_bot, to_global,rot, height).expect("could not generate trap. sensor")
         
     }
-}
 
+    /// Fetches the trapezoidal bounds of the sensor
+    pub fn bounds(&self) -> &TrapezoidBounds {
+        &self.bounds
+    }
+}
 
 impl Transform for Trapezoid{
     
@@ -206,36 +174,11 @@ impl Transform for Trapezoid{
     /// let is_inside: bool = sensor.inside(&local_point);
     /// ```
     fn inside(&self, input: &P2) -> bool {
+        self.bounds.inside(input, 0.)
+    }
 
-        let line = 
-            if (0 as Real) <= input.x {&self.right_line}
-            else {&self.left_line};
-
-        let new_point = line.call(&input);
-
-        let max_height = new_point.y.abs().min(self.half_height);
-        let x_abs = new_point.x.abs();
-
-        let max_width =  
-            if x_abs <= self.min_half_width {self.min_half_width}
-            else if x_abs >= self.max_half_width{self.max_half_width}
-            else if (x_abs >= self.min_half_width) && (x_abs <= self.max_half_width) {x_abs}
-            else{panic!("problem with bounds checking trapezoid")}; //TODO: fix this panic 
-
-        // let dbg_h = format!{"max height:  {}   actual   {}", &max_height, &$point.y};
-        // let dbg_w = format!{"max width:   {}   min width   {} x_abs value:   {}   input value:   {} \
-        //             choosen maxium   {}", $max_w, $min_w, x_abs, &$point.x, max_width};
-        // dbg!{dbg_h};
-        // dbg!{dbg_w};
-
-        if input.y.abs() <= max_height {
-            if input.x.abs() <= max_width {return true}
-            else{return false}
-        }
-        else{
-            return false
-        }
-
+    fn distance_to_boundary(&self, input: &P2) -> Real {
+        self.bounds.distance_to_boundary(input)
     }
     fn rotation_to_global(&self) -> &Mat4{
         &self.to_global_rot
@@ -285,3 +228,32 @@ impl Plane for Trapezoid{
         unimplemented!()
     }
 }
+
+impl Identified for Trapezoid {
+    fn geometry_id(&self) -> GeometryId {
+        self.geometry_id
+    }
+
+    fn set_geometry_id(&mut self, id: GeometryId) {
+        self.geometry_id = id;
+    }
+}
+
+impl HasMaterial for Trapezoid {
+    fn material(&self) -> Option<&SurfaceMaterial> {
+        self.material.as_ref()
+    }
+
+    fn set_material(&mut self, material: Option<SurfaceMaterial>) {
+        self.material = material;
+    }
+}
+
+
+impl Outline for Trapezoid {
+    fn global_outline(&self, arc_segments: usize) -> Vec<P3> {
+        self.bounds.outline(arc_segments).iter()
+            .map(|point| self.to_global * P3::new(point.x, point.y, 0.))
+            .collect()
+    }
+}
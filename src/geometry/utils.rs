@@ -9,6 +9,7 @@ use super::super::config::*;
 /// Checks if an input point is contained within the within the XY bounds of the sensor. A point with 
 /// any nonzero Z value needs to also use `traits::Plane::on_plane` to ensure that the point falls
 /// on the same plane as the sensor. 
+#[deprecated(note = "use `geometry::bounds::ConvexPolygonBounds` instead")]
 pub fn quadralateral_contains(points: &[P3;4], check_point: &P3)->bool{
     // subtract points so we can make position vectors
     let am_vec = points[0] - check_point;
@@ -37,6 +38,20 @@ pub fn quadralateral_contains(points: &[P3;4], check_point: &P3)->bool{
 }
 
 
+/// Brings an azimuthal angle into (-pi, pi]. The smallest signed difference between two
+/// angles is `wrap_phi(phi - reference)`. Angles that are already in range are returned
+/// unchanged.
+pub fn wrap_phi(phi: Real) -> Real {
+    if (phi > -PI) && (phi <= PI) {
+        return phi
+    }
+
+    let wrapped = (phi + PI).rem_euclid(2. * PI) - PI;
+
+    if wrapped <= -PI {PI} else {wrapped}
+}
+
+
 /// Calculates the vector normal to three input points
 pub fn plane_normal_vector(side_1: Real, side_2: Real) -> Vec3 {
     let point_1 = P3::new(side_1, 0.0, 0.0);
@@ -57,6 +72,7 @@ pub fn plane_normal_vector(side_1: Real, side_2: Real) -> Vec3 {
 /// will correctly function. There is a known edge in which a trapezoid with an extremely low height will choose
 /// the wrong order of points. This is relatively easy to fix but quadruples the total number of comparissons needed.
 #[allow(dead_code)]
+#[deprecated(note = "use `geometry::bounds::ConvexPolygonBounds` which orders its vertices")]
 pub fn organize_points<'a>(input_points: &'a mut [P3;4]) -> &'a [P3;4]{
 
     // we collect into a vec here since FromIterator is not implemented for 
@@ -0,0 +1,458 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::tracking_geometry::TrackingGeometry;
+use super::surface::Surface;
+use super::id::GeometryId;
+use super::bvh::Aabb;
+use super::traits::{Transform, Plane, Identified, Outline};
+use super::super::config::*;
+
+/// Number of straight segments used for the curved edges of the surfaces
+const ARC_SEGMENTS: usize = 16;
+
+/// Number of steps taken away from an edge when looking for the neighbouring surface
+const GAP_STEPS: usize = 50;
+
+/// A problem found with a single surface of the geometry
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finding {
+    /// part of the surface is also inside of `other`. `depth` is the largest distance from a
+    /// shared point to the closest edge of either surface.
+    Overlap {other: GeometryId, depth: Real},
+    /// nothing in the layer covers a strip of `width` between an edge of the surface and `other`
+    Gap {other: GeometryId, width: Real},
+    /// largest distance between a point and the same point after a round trip through the
+    /// local frame
+    TransformNotInverse {error: Real},
+    /// distance of the rotation to global from an orthonormal, right handed rotation
+    RotationNotOrthonormal {error: Real},
+    /// angle between the normal of the plane and the local z axis of the rotation to global
+    NormalMismatch {angle: Real}
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::Overlap{other, depth} => write!(f, "overlaps {} by {}", other, depth),
+            Finding::Gap{other, width} => write!(f, "gap of {} to {}", width, other),
+            Finding::TransformNotInverse{error} => write!(f, "to_local is not the inverse of to_global (error {})", error),
+            Finding::RotationNotOrthonormal{error} => write!(f, "rotation to global is not orthonormal (error {})", error),
+            Finding::NormalMismatch{angle} => write!(f, "normal is {} rad away from the local z axis", angle)
+        }
+    }
+}
+
+/// Tolerances of the checks done by `validate`
+#[derive(Debug, Clone)]
+pub struct ValidationOptions {
+    /// numerical error allowed in the transformations, rotations and normals
+    pub tolerance: Real,
+    /// surfaces sharing less than this are not reported as overlapping
+    pub overlap_tolerance: Real,
+    /// gaps narrower than this are not reported
+    pub min_gap: Real,
+    /// edges further than this from every other surface of the layer are the edges of the layer
+    pub max_gap: Real,
+    /// points sampled along each local axis when looking for coplanar overlaps. Overlaps
+    /// narrower than the spacing of the samples can be missed.
+    pub samples: usize
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        ValidationOptions {
+            tolerance: 1e-9,
+            overlap_tolerance: 1e-6,
+            min_gap: 1e-6,
+            max_gap: 1.,
+            samples: 20
+        }
+    }
+}
+
+/// Findings of `validate` grouped by the id of the surface they were found on
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    findings: BTreeMap<GeometryId, Vec<Finding>>
+}
+
+impl ValidationReport {
+
+    /// Checks that nothing was found
+    pub fn is_valid(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Number of surfaces with at least one finding
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Findings for a single surface
+    pub fn findings(&self, id: GeometryId) -> &[Finding] {
+        self.findings.get(&id).map_or(&[], |findings| findings.as_slice())
+    }
+
+    /// Surfaces with findings and what was found, sorted by id
+    pub fn iter(&self) -> impl Iterator<Item = (GeometryId, &[Finding])> {
+        self.findings.iter().map(|(id, findings)| (*id, findings.as_slice()))
+    }
+
+    /// Records a finding. Overlaps and gaps with the same surface are only kept once, with
+    /// the largest depth or width.
+    pub fn add(&mut self, id: GeometryId, finding: Finding) {
+        let findings = self.findings.entry(id).or_default();
+
+        for existing in findings.iter_mut() {
+            match (existing, &finding) {
+                (Finding::Overlap{other, depth}, Finding::Overlap{other: new_other, depth: new_depth})
+                    if other == new_other => {
+                        *depth = depth.max(*new_depth);
+                        return
+                    },
+                (Finding::Gap{other, width}, Finding::Gap{other: new_other, width: new_width})
+                    if other == new_other => {
+                        *width = width.max(*new_width);
+                        return
+                    },
+                _ => ()
+            }
+        }
+
+        findings.push(finding);
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (id, findings) in self.iter() {
+            for finding in findings {
+                writeln!(f, "{}: {}", id, finding)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Checks every surface of the geometry for broken transformations, overlaps with other
+/// surfaces and gaps between the surfaces of a layer.
+///
+/// # Examples
+/// ```
+/// use kalman_rs::config::*;
+/// use kalman_rs::geometry::{GeometryId, PlaneSurface, Surface};
+/// use kalman_rs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+/// use kalman_rs::geometry::traits::Identified;
+/// use kalman_rs::geometry::tracking_geometry::TrackingGeometry;
+/// use kalman_rs::geometry::validation::{validate, ValidationOptions};
+///
+/// let square = |x: Real, sensitive: u32| {
+///     let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+///     let translation = Mat4::new_translation(&Vec3::new(x, 0., 0.));
+///     let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, Mat4::identity()).unwrap());
+///     surface.set_geometry_id(GeometryId::new(1, 1, sensitive));
+///     surface
+/// };
+///
+/// // the squares touch along x = 0
+/// let geometry = TrackingGeometry::new(vec![square(-1., 1), square(1., 2)]).unwrap();
+/// assert!(validate(&geometry, &ValidationOptions::default()).is_valid());
+/// ```
+pub fn validate(geometry: &TrackingGeometry, options: &ValidationOptions) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    for surface in geometry.surfaces() {
+        for finding in check_transform(surface, options.tolerance) {
+            report.add(surface.geometry_id(), finding);
+        }
+    }
+
+    check_overlaps(geometry, options, &mut report);
+    check_gaps(geometry, options, &mut report);
+
+    report
+}
+
+/// Checks that `to_local` and `to_global` are inverse of each other, that the rotation to global
+/// is orthonormal and that the normal of the plane is the local z axis. This works for any sensor.
+pub fn check_transform<T: Transform + Plane>(sensor: &T, tolerance: Real) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let rotation = sensor.rotation_to_global().fixed_slice::<U3, U3>(0, 0).into_owned();
+
+    // round trips of the center and of points along the local axes
+    let center = *sensor.global_center();
+    let points = [center, center + rotation.column(0), center + rotation.column(1)];
+
+    let round_trip = points.iter()
+        .map(|global| {
+            let local = sensor.to_local(*global);
+            let back_global = sensor.to_global(P3::new(local.x, local.y, 0.));
+            let back_local = sensor.to_local(back_global);
+
+            (back_global - global).norm().max((back_local - local).norm())
+        })
+        .fold(0., Real::max);
+
+    let inverse_rotation = (sensor.rotation_to_local() * sensor.rotation_to_global() - Mat4::identity()).norm();
+    let error = round_trip.max(inverse_rotation);
+
+    if exceeds(error, tolerance) {
+        findings.push(Finding::TransformNotInverse {error});
+    }
+
+    let error = (rotation.transpose() * rotation - Mat3::identity()).norm() + (rotation.determinant() - 1.).abs();
+    if exceeds(error, tolerance) {
+        findings.push(Finding::RotationNotOrthonormal {error});
+    }
+
+    // atan2 keeps small angles accurate
+    let normal = sensor.plane_normal_vec();
+    let axis = rotation.column(2);
+    let angle = normal.cross(&axis).norm().atan2(normal.dot(&axis));
+
+    if exceeds(angle, tolerance) {
+        findings.push(Finding::NormalMismatch {angle});
+    }
+
+    findings
+}
+
+// NaN from a degenerate transformation is reported as well
+fn exceeds(error: Real, tolerance: Real) -> bool {
+    error.is_nan() || (error > tolerance)
+}
+
+
+// every pair of surfaces with overlapping boxes, each pair checked once
+fn check_overlaps(geometry: &TrackingGeometry, options: &ValidationOptions, report: &mut ValidationReport) {
+    for surface in geometry.surfaces() {
+        let id = surface.geometry_id();
+
+        let bounds =
+            match Aabb::from_points(&surface.global_outline(ARC_SEGMENTS)) {
+                Some(bounds) => bounds,
+                None => continue
+            };
+
+        for other_id in geometry.bvh().box_candidates(&bounds, options.overlap_tolerance) {
+            if other_id <= id {
+                continue
+            }
+
+            let other = geometry.surface(other_id).expect("bvh surfaces are in the geometry");
+
+            if let Some(depth) = overlap_depth(surface, other, options) {
+                report.add(id, Finding::Overlap {other: other_id, depth});
+                report.add(other_id, Finding::Overlap {other: id, depth});
+            }
+        }
+    }
+}
+
+fn overlap_depth(a: &Surface, b: &Surface, options: &ValidationOptions) -> Option<Real> {
+    let normal_a = a.plane_normal_vec().normalize();
+    let normal_b = b.plane_normal_vec().normalize();
+
+    let distance = normal_b.dot(&(a.global_center() - b.global_center()));
+    let coplanar = (normal_a.cross(&normal_b).norm() <= options.tolerance) && (distance.abs() <= options.overlap_tolerance);
+
+    let depth =
+        if coplanar {
+            shared_area_depth(a, b, options.samples).max(shared_area_depth(b, a, options.samples))
+        }
+        else {
+            edge_crossing_depth(a, b).max(edge_crossing_depth(b, a))
+        };
+
+    if depth > options.overlap_tolerance {Some(depth)} else {None}
+}
+
+// deepest point of a grid over `a` that is inside of both surfaces
+fn shared_area_depth(a: &Surface, b: &Surface, samples: usize) -> Real {
+    let outline = a.local_outline(ARC_SEGMENTS);
+
+    let (min, max) = outline.iter()
+        .fold((Vec2::repeat(Real::INFINITY), Vec2::repeat(Real::NEG_INFINITY)), |(min, max), point| {
+            (min.zip_map(&point.coords, Real::min), max.zip_map(&point.coords, Real::max))
+        });
+
+    let extent = max - min;
+    let samples = samples.max(1);
+    let mut depth: Real = 0.;
+
+    for i in 0..samples {
+        for j in 0..samples {
+            let fraction = Vec2::new(i as Real + 0.5, j as Real + 0.5) / (samples as Real);
+            let point = P2::from(min + extent.component_mul(&fraction));
+
+            let global = a.cartesian_to_global(&point);
+            let inside_a = -a.distance_to_boundary(&a.to_local(global));
+            let inside_b = -b.distance_to_boundary(&b.to_local(global));
+
+            depth = depth.max(inside_a.min(inside_b));
+        }
+    }
+
+    depth
+}
+
+// deepest point inside of `b` where an edge of `a` goes through the plane of `b`
+fn edge_crossing_depth(a: &Surface, b: &Surface) -> Real {
+    let outline = a.global_outline(ARC_SEGMENTS);
+    let normal = b.plane_normal_vec();
+    let center = b.global_center();
+
+    let mut depth: Real = 0.;
+
+    for i in 0..outline.len() {
+        let (start, end) = (outline[i], outline[(i + 1) % outline.len()]);
+        let (start_distance, end_distance) = (normal.dot(&(start - center)), normal.dot(&(end - center)));
+
+        if start_distance * end_distance >= 0. {
+            continue
+        }
+
+        let crossing = start + (end - start) * (start_distance / (start_distance - end_distance));
+        depth = depth.max(-b.distance_to_boundary(&b.to_local(crossing)));
+    }
+
+    depth
+}
+
+
+// walks away from the edges of every surface until another surface of the layer covers the point
+fn check_gaps(geometry: &TrackingGeometry, options: &ValidationOptions, report: &mut ValidationReport) {
+    for surface in geometry.surfaces() {
+        let id = surface.geometry_id();
+
+        let bounds =
+            match Aabb::from_points(&surface.global_outline(ARC_SEGMENTS)) {
+                Some(bounds) => bounds,
+                None => continue
+            };
+
+        let neighbours = geometry.bvh().box_candidates(&bounds, options.max_gap).into_iter()
+            .filter(|other| (*other != id) && other.same_layer(&id))
+            .filter_map(|other| geometry.surface(other))
+            .collect::<Vec<_>>();
+
+        if neighbours.is_empty() {
+            continue
+        }
+
+        for (probe, outward) in edge_probes(surface) {
+            if let Some((other, width)) = gap_width(surface, &neighbours, &probe, &outward, options) {
+                report.add(id, Finding::Gap {other, width});
+            }
+        }
+    }
+}
+
+// points along the edges of the surface with the global direction pointing away from the surface
+fn edge_probes(surface: &Surface) -> Vec<(P3, Vec3)> {
+    let outline = surface.local_outline(ARC_SEGMENTS);
+    let n = outline.len();
+
+    if n < 3 {
+        return Vec::new()
+    }
+
+    // the outward side of an edge depends on the orientation of the outline
+    let orientation = (0..n)
+        .map(|i| {
+            let (p, q) = (outline[i], outline[(i + 1) % n]);
+            (p.x * q.y) - (q.x * p.y)
+        })
+        .sum::<Real>()
+        .signum();
+
+    let mut probes = Vec::new();
+
+    for i in 0..n {
+        let (start, end) = (outline[i], outline[(i + 1) % n]);
+        let edge = end - start;
+
+        if edge.norm() < DOT_PRODUCT_EPSILON {
+            continue
+        }
+
+        let outward = Vec2::new(edge.y, -edge.x) * (orientation / edge.norm());
+
+        for fraction in &[0.25, 0.5, 0.75] {
+            let point = start + (edge * *fraction);
+            let global = surface.cartesian_to_global(&point);
+            let direction = surface.cartesian_to_global(&(point + outward)) - global;
+
+            probes.push((global, direction));
+        }
+    }
+
+    probes
+}
+
+// width of the uncovered strip in front of an edge, or `None` if the edge touches a neighbour
+// or is at the edge of the layer
+fn gap_width(
+    surface: &Surface,
+    neighbours: &[&Surface],
+    probe: &P3,
+    outward: &Vec3,
+    options: &ValidationOptions
+    ) -> Option<(GeometryId, Real)> {
+
+    let normal = surface.plane_normal_vec().normalize();
+
+    let covering = |width: Real| {
+        let point = probe + (outward * width);
+        neighbours.iter()
+            .find(|other| covers(other, &point, &normal, options.max_gap))
+            .map(|other| other.geometry_id())
+    };
+
+    if covering(options.min_gap).is_some() {
+        return None
+    }
+
+    let step = (options.max_gap - options.min_gap) / (GAP_STEPS as Real);
+
+    for i in 1..=GAP_STEPS {
+        let width = options.min_gap + (step * i as Real);
+
+        if let Some(other) = covering(width) {
+            // the edge of the neighbour is between the last two steps
+            let (mut low, mut high) = (width - step, width);
+            for _ in 0..30 {
+                let middle = (low + high) / 2.;
+                if covering(middle).is_some() {high = middle} else {low = middle}
+            }
+
+            return Some((other, high))
+        }
+    }
+
+    None
+}
+
+// checks if the line through `point` along `normal` crosses `surface` within `max_distance`
+fn covers(surface: &Surface, point: &P3, normal: &Vec3, max_distance: Real) -> bool {
+    let surface_normal = surface.plane_normal_vec();
+    let denominator = surface_normal.dot(normal);
+
+    if denominator.abs() < DOT_PRODUCT_EPSILON {
+        return false
+    }
+
+    let distance = surface_normal.dot(&(surface.global_center() - point)) / denominator;
+    if distance.abs() > max_distance {
+        return false
+    }
+
+    surface.inside(&surface.to_local(point + (normal * distance)))
+}
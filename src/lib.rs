@@ -12,7 +12,10 @@ pub mod geometry;
 pub mod filter;
 pub mod error;
 pub mod generate_data;
+pub mod export;
 
 pub use geometry::rectangle::Rectangle;
 pub use geometry::trapezoid::Trapezoid;
+pub use geometry::disc::Disc;
+pub use geometry::straw::Straw;
 pub use geometry::traits as sensor_traits;
@@ -0,0 +1,153 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::GeometryError;
+use krs::filter::linear;
+use krs::filter::measurement::Measurement;
+use krs::geometry::alignment::*;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::navigator::Navigator;
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::*;
+use krs::geometry::validation::check_transform;
+use krs::geometry::{Disc, GeometryId, PlaneSurface, Rectangle, Surface};
+
+/*
+
+    Tests for kalman_rs::geometry::alignment
+
+*/
+
+fn initialize_square(z: Real, id: GeometryId) -> Surface {
+    let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(5., 5.));
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., z));
+    let rotation = Mat4::from_axis_angle(&Vec3::z_axis(), 0.2);
+
+    let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, rotation).unwrap());
+    surface.set_geometry_id(id);
+    surface
+}
+
+fn delta() -> AlignmentDelta {
+    AlignmentDelta::new(Vec3::new(0.1, -0.05, 0.02), Vec3::new(0.01, -0.02, 0.03))
+}
+
+fn assert_points_close(left: &P3, right: &P3) {
+    assert!((left - right).norm() < 1e-12, "{} != {}", left, right)
+}
+
+#[test]
+fn aligned_transforms() {
+    let nominal = initialize_square(1., GeometryId::new(1, 1, 1));
+    let aligned = Aligned::new(nominal.clone(), delta());
+
+    // the shift is along the local axes of the nominal surface
+    let rotation = nominal.rotation_to_global().fixed_slice::<U3, U3>(0, 0).into_owned();
+    assert_points_close(aligned.global_center(), &(nominal.global_center() + (rotation * delta().translation)));
+
+    let local = P2::new(0.7, -1.3);
+    let global = aligned.to_global(P3::new(local.x, local.y, 0.));
+    assert!((aligned.to_local(global) - local).norm() < 1e-12);
+    assert!(aligned.on_plane(&global));
+    assert!(!nominal.on_plane(&global));
+
+    // still a valid placement, and the same as rebuilding the surface
+    assert!(check_transform(&aligned, 1e-12).is_empty());
+
+    let rebuilt = align_surface(&nominal, &delta()).unwrap();
+    assert_eq!(rebuilt.geometry_id(), nominal.geometry_id());
+    assert_points_close(&rebuilt.to_global(P3::new(local.x, local.y, 0.)), &global);
+    assert!((rebuilt.rotation_to_global() - aligned.rotation_to_global()).norm() < 1e-12);
+    assert!((rebuilt.plane_normal_vec() - aligned.plane_normal_vec()).norm() < 1e-12);
+
+    let derivative = aligned.local_to_global_derivative(&local);
+    assert!((derivative - rebuilt.local_to_global_derivative(&local)).norm() < 1e-12);
+
+    // a zero correction changes nothing
+    let unchanged = Aligned::new(nominal.clone(), AlignmentDelta::default());
+    assert_points_close(&unchanged.to_global(P3::new(1., 2., 0.)), &nominal.to_global(P3::new(1., 2., 0.)));
+    assert_eq!(unchanged.into_nominal().geometry_id(), GeometryId::new(1, 1, 1));
+}
+
+#[test]
+fn aligned_sensors() {
+    // polar local frame
+    let disc = Disc::new(1., 3., 0., PI, Mat4::identity(), Mat4::identity()).unwrap();
+    let aligned = Aligned::new(disc, AlignmentDelta::new(Vec3::zeros(), Vec3::new(0., 0., 0.1)));
+
+    let local = aligned.to_local(P3::new(2., 0., 0.));
+    assert!((local - P2::new(2., -0.1)).norm() < 1e-12);
+
+    let rebuilt = align_surface(&Surface::Disc(aligned.nominal().clone()), aligned.delta()).unwrap();
+    assert!((rebuilt.to_local(P3::new(2., 0., 0.)) - local).norm() < 1e-12);
+
+    let rect = Rectangle::new(2., 2., Mat4::identity(), Mat4::identity()).unwrap();
+    let aligned = Aligned::new(rect, AlignmentDelta::new(Vec3::new(0., 0., 0.5), Vec3::zeros()));
+    let outline = aligned.global_outline(1);
+    outline.iter().for_each(|point| assert!((point.z - 0.5).abs() < 1e-12));
+}
+
+#[test]
+fn store_files() {
+    let mut store = AlignmentStore::new();
+    store.insert(GeometryId::new(1, 1, 1), delta());
+    store.insert(GeometryId::new(1, 2, 1), AlignmentDelta::new(Vec3::new(0., 0., 1.), Vec3::zeros()));
+
+    assert_eq!(AlignmentStore::from_json(&store.to_json().unwrap()).unwrap(), store);
+    assert_eq!(AlignmentStore::from_toml(&store.to_toml().unwrap()).unwrap(), store);
+
+    let path = std::env::temp_dir().join("kalman_rs_alignment.toml");
+    store.save(&path).unwrap();
+    assert_eq!(AlignmentStore::load(&path).unwrap(), store);
+
+    let duplicate = r#"{"alignments": [
+        {"id": {"volume": 1, "layer": 1, "sensitive": 1}, "translation": [0, 0, 0], "rotation": [0, 0, 0]},
+        {"id": {"volume": 1, "layer": 1, "sensitive": 1}, "translation": [1, 0, 0], "rotation": [0, 0, 0]}
+    ]}"#;
+    match AlignmentStore::from_json(duplicate) {
+        Err(GeometryError::DuplicateId(id)) => assert_eq!(id, GeometryId::new(1, 1, 1)),
+        _ => panic!("duplicate entries should not be accepted")
+    }
+
+    assert!(AlignmentStore::load("alignment.yaml").is_err());
+}
+
+// hits simulated on one geometry only line up on a straight track when they are read back
+// with the same geometry
+#[test]
+fn misaligned_simulation() {
+    let surfaces = (1..=5).map(|layer| initialize_square(layer as Real, GeometryId::new(1, layer, 1))).collect::<Vec<_>>();
+    let nominal = TrackingGeometry::new(surfaces).unwrap();
+
+    let mut store = AlignmentStore::new();
+    store.insert(GeometryId::new(1, 3, 1), AlignmentDelta::new(Vec3::new(0.1, 0., 0.), Vec3::zeros()));
+    let misaligned = store.align_geometry(&nominal).unwrap();
+
+    let (start, direction) = (P3::new(0.2, 0.1, 0.), Vec3::new(0.05, 0.02, 1.));
+
+    // largest distance of the hits to the line through the first and last hit
+    let largest_deviation = |simulated: &TrackingGeometry, reconstructed: &TrackingGeometry| {
+        let hits = Navigator::new(simulated).crossings(&start, &direction).iter()
+            .map(|c| reconstructed.surface(c.geometry_id).unwrap().to_global(P3::new(c.local.x, c.local.y, 0.)))
+            .collect::<Vec<_>>();
+        assert_eq!(hits.len(), 5);
+
+        let axis = (hits[4] - hits[0]).normalize();
+        hits.iter().map(|hit| (hit - hits[0]).cross(&axis).norm()).fold(0., Real::max)
+    };
+
+    assert!(largest_deviation(&misaligned, &misaligned) < 1e-12);
+    assert!(largest_deviation(&nominal, &nominal) < 1e-12);
+    assert!(largest_deviation(&misaligned, &nominal) > 0.05);
+    assert!(largest_deviation(&nominal, &misaligned) > 0.05);
+
+    // the fitter runs on both geometries with the same measurements
+    let measurements = Navigator::new(&misaligned).crossings(&start, &direction).iter()
+        .map(|c| Measurement::new(c.geometry_id, c.local.coords, Mat2::identity() * 1e-6))
+        .collect::<Vec<_>>();
+
+    let options = linear::FitterOptions::default();
+    for geometry in &[&nominal, &misaligned] {
+        let result = linear::run_navigated(&start, &direction, &measurements, &Navigator::new(geometry), None, &options).unwrap();
+        assert_eq!(result.smth.state_vec.len(), 5);
+    }
+}
@@ -0,0 +1,104 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::filter::angles::{normalize_state, state_difference, wrap_phi};
+use krs::filter::{filter_gain, linear, smoothing};
+use krs::generate_data::statistics;
+use krs::generate_data::structs::KFData;
+use krs::geometry::Rectangle;
+
+/*
+
+    Tests for the periodicity of phi and the range of theta in filter updates
+
+*/
+
+const THETA: Real = 0.2;
+
+// the hits move by tan(THETA) ~ 0.2 in x between sensors, so the +-0.02 zig-zag in y turns
+// the fitted phi by up to ~0.2 around pi
+const PHI_SPREAD: Real = 0.25;
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    Rectangle::new(10., 10., Mat4::new_translation(&Vec3::new(0., 0., z)), Mat4::identity()).unwrap()
+}
+
+// hits of a track along phi = pi, smeared up and down in y so the fitted phi crosses pi
+fn hits(z: &[Real]) -> (Vec<Vec2>, Vec<Vec2>) {
+    let truth = z.iter().map(|z| Vec2::new(-THETA.tan() * z, 0.)).collect::<Vec<_>>();
+    let smeared = truth.iter().enumerate().map(|(i, hit)| hit + Vec2::new(0., if i % 2 == 0 {0.02} else {-0.02})).collect();
+
+    (truth, smeared)
+}
+
+#[test]
+fn wrapped_differences() {
+    let a = Vec5::new(1., 2., PI - 0.01, 0.5, 1.);
+    let b = Vec5::new(0.5, 1., -PI + 0.01, 0.4, 1.);
+
+    let difference = state_difference(&a, &b);
+    assert!((difference - Vec5::new(0.5, 1., -0.02, 0.1, 0.)).norm() < 1e-12);
+    assert!((state_difference(&b, &a)[ePHI] - 0.02).abs() < 1e-12);
+
+    // theta beyond a pole points to the other side of it
+    let normalized = normalize_state(&Vec5::new(0., 0., 0.5, -0.1, 1.));
+    assert!((normalized - Vec5::new(0., 0., 0.5 - PI, 0.1, 1.)).norm() < 1e-12);
+    let normalized = normalize_state(&Vec5::new(0., 0., -PI + 0.2, PI + 0.1, 1.));
+    assert!((normalized - Vec5::new(0., 0., 0.2, PI - 0.1, 1.)).norm() < 1e-12);
+    assert_eq!(normalize_state(&Vec5::new(0., 0., 4. * PI, 1., 1.))[ePHI], 0.);
+}
+
+#[test]
+fn updates_across_pi() {
+    let map = Mat2x5::new(1., 0., 0., 0., 0., 0., 1., 0., 0., 0.);
+
+    // the gain pushes phi over pi
+    let pred = Vec5::new(0., 0., PI - 0.01, 0.1, 1.);
+    let mut gain = Mat5x2::zeros();
+    gain[(ePHI, 1)] = 1.;
+    let filt = filter_gain::state_vector(&pred, &gain, &Vec2::new(0.3, 0.03), &map);
+    assert!((filt[ePHI] - (-PI + 0.02)).abs() < 1e-12, "{}", filt);
+
+    // and theta over the pole, which turns phi around
+    gain[(eTHETA, 0)] = -1.;
+    let filt = filter_gain::state_vector(&pred, &gain, &Vec2::new(0.3, 0.03), &map);
+    assert!((filt[ePHI] - 0.02).abs() < 1e-12, "{}", filt);
+    assert!((filt[eTHETA] - 0.2).abs() < 1e-12, "{}", filt);
+
+    // filtered and smoothed states on both sides of pi are close to each other
+    let curr_filt = Vec5::new(0., 0., PI - 0.01, 1., 1.);
+    let smth = smoothing::state_vector(&curr_filt, &Mat5::identity(), &Vec5::new(0., 0., -PI + 0.01, 1., 1.), &Vec5::new(0., 0., PI - 0.02, 1., 1.));
+    assert!((smth[ePHI] - (-PI + 0.02)).abs() < 1e-12, "{}", smth);
+}
+
+#[test]
+fn track_crossing_pi() {
+    let z = (1..=8).map(|z| z as Real).collect::<Vec<_>>();
+    let sensors = z.iter().map(|z| initialize_rect(*z)).collect::<Vec<_>>();
+    let covariance = z.iter().map(|_| Mat2::identity() * 0.0004).collect::<Vec<_>>();
+    let (truth, smeared) = hits(&z);
+
+    let seed = Vec5::new(smeared[0].x, smeared[0].y, -PI + 0.001, THETA, 1.);
+    let result = linear::run(&P3::origin(), &covariance, &smeared, &sensors, Some(&seed));
+
+    for state in result.pred.state_vec.iter().chain(&result.filt.state_vec).chain(&result.smth.state_vec) {
+        assert!((state[ePHI] > -PI) && (state[ePHI] <= PI), "{}", state);
+        assert!((state[eTHETA] >= 0.) && (state[eTHETA] <= PI), "{}", state);
+    }
+
+    let states = result.pred.state_vec.iter().chain(&result.filt.state_vec).collect::<Vec<_>>();
+    for state in &states {
+        assert!(wrap_phi(state[ePHI] - PI).abs() < PHI_SPREAD, "phi jumped: {}", state);
+        assert!((state[eTHETA] - THETA).abs() < 0.01, "{}", state);
+    }
+
+    // the fitted phi is on both sides of the boundary
+    assert!(states.iter().any(|state| state[ePHI] > 0.) && states.iter().any(|state| state[ePHI] < 0.));
+
+    // the angle residuals do not see the boundary
+    let data = KFData::new(sensors, covariance, smeared, truth, (PI, THETA), seed, Vec5::new(0., 0., PI, THETA, 1.));
+    let residuals = statistics::truth_kf_angle_residuals(&[(data, result)]);
+    for residual in residuals[0].filt.iter().chain(&residuals[0].pred) {
+        assert!(residual[0].abs() < PHI_SPREAD && residual[1].abs() < 0.01, "{}", residual);
+    }
+}
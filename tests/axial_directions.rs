@@ -0,0 +1,159 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{FilterError, SensorError};
+use krs::filter::angles::Angles;
+use krs::filter::parameters::FreeParameters;
+use krs::filter::{covariance, jacobian, linear, utils};
+use krs::geometry::traits::*;
+use krs::geometry::{GeometryId, Rectangle, Straw};
+
+/*
+
+    Tests for directions along (or within 1e-8 of) the global z axis, where phi is not defined
+
+*/
+
+// directions on and next to the z axis, in both directions along it
+fn axial_directions() -> Vec<Vec3> {
+    vec![
+        Vec3::z(),
+        -Vec3::z(),
+        Vec3::new(1e-8, 0., 1.).normalize(),
+        Vec3::new(0., -1e-9, -1.).normalize(),
+        Vec3::new(-1e-8, 1e-8, 1.).normalize(),
+    ]
+}
+
+fn initialize_rect(z: Real) -> Rectangle {
+    let mut sensor = Rectangle::new(10., 10., Mat4::new_translation(&Vec3::new(0., 0., z)), Mat4::identity()).unwrap();
+    sensor.set_geometry_id(GeometryId::new(1, 1, 1 + z as u32));
+    sensor
+}
+
+fn is_finite<R: nalgebra::Dim, C: nalgebra::Dim, S: nalgebra::storage::Storage<Real, R, C>>(matrix: &nalgebra::Matrix<Real, R, C, S>) -> bool {
+    matrix.iter().all(|x| x.is_finite())
+}
+
+#[test]
+fn angles_of_axial_directions() {
+    for direction in axial_directions() {
+        let angles = Angles::new_from_unit_direction(direction.x, direction.y, direction.z);
+
+        assert!((angles.cos_phi.powi(2) + angles.sin_phi.powi(2) - 1.).abs() < 1e-12, "{:?}", angles);
+        assert!(angles.sin_theta.is_finite());
+
+        let phi = angles.sin_phi.atan2(angles.cos_phi);
+        let theta = angles.sin_theta.atan2(angles.cos_theta);
+        assert!((Angles::new_from_angles(phi, theta).direction - direction).norm() < 1e-15);
+    }
+
+    // along the axis phi is 0
+    let angles = Angles::new_from_unit_direction(0., 0., -1.);
+    assert!(angles.is_axial());
+    assert_eq!((angles.cos_phi, angles.sin_phi), (1., 0.));
+    assert!(!Angles::new_from_unit_direction(1e-8, 0., 1.).is_axial());
+}
+
+#[test]
+fn guarded_jacobians() {
+    let start = initialize_rect(0.);
+    let end = initialize_rect(10.);
+
+    let on_axis = jacobian::linear(&Vec5::new(0.5, -0.5, 0.3, 0., 1.), 10., &start, &end);
+    assert!(is_finite(&on_axis), "{}", on_axis);
+
+    // phi is carried through the transport
+    assert!((on_axis[(ePHI, ePHI)] - 1.).abs() < 1e-12);
+    assert!(on_axis.column(ePHI).rows(0, 2).norm() < 1e-9);
+
+    for theta in &[1e-9, 1e-8] {
+        let near_axis = jacobian::linear(&Vec5::new(0.5, -0.5, 0.3, *theta, 1.), 10., &start, &end);
+        assert!((near_axis - on_axis).abs().max() < 1e-6, "{} != {}", near_axis, on_axis);
+    }
+
+    let backward = jacobian::linear(&Vec5::new(0.5, -0.5, -2., PI, 1.), 10., &end, &start);
+    assert!(is_finite(&backward) && (backward[(ePHI, ePHI)] - 1.).abs() < 1e-12, "{}", backward);
+}
+
+#[test]
+fn curvilinear_frame_near_axis() {
+    for (phi, theta) in &[(0.3, 0.), (2., 1e-8), (-1., PI - 1e-9), (0.7, PI)] {
+        let direction = Angles::new_from_angles(*phi, *theta).direction;
+        let (u, v) = covariance::curvilinear_axes(*phi, *theta);
+
+        assert!((u.norm() - 1.).abs() < 1e-12 && (v.norm() - 1.).abs() < 1e-12);
+        assert!(u.dot(&v).abs() < 1e-12 && u.dot(&direction).abs() < 1e-12);
+        assert!((u.cross(&v) - direction).norm() < 1e-12);
+
+        // the frame does not turn with phi
+        let (other_u, _) = covariance::curvilinear_axes(*phi + 1., *theta);
+        assert!((u - other_u).norm() < 1e-7, "{} != {}", u, other_u);
+
+        // rounding errors of the direction are divided by sin(theta)
+        let round_trip = covariance::free_to_curvilinear_jacobian(*phi, *theta) * covariance::curvilinear_to_free_jacobian(*phi, *theta);
+        assert!((round_trip - Mat5::identity()).abs().max() < 1e-7, "{}", round_trip);
+    }
+}
+
+#[test]
+fn free_conversions_along_axis() {
+    let sensor = initialize_rect(0.);
+    let free_covariance = Mat8::identity() * 0.01;
+
+    // phi of a free covariance along the axis is not defined
+    let along = FreeParameters::new(P3::new(1., 1., 0.), 0., &Vec3::z(), 1., Some(free_covariance));
+    match along.to_bound(&sensor) {
+        Err(SensorError::InvalidDirection(direction)) => assert_eq!(direction, Vec3::z()),
+        _ => panic!("the direction is along the z axis")
+    }
+
+    // without a covariance it is 0
+    let (bound, _) = FreeParameters::new(P3::new(1., 1., 0.), 0., &Vec3::z(), 1., None).to_bound(&sensor).unwrap();
+    assert_eq!((bound[ePHI], bound[eTHETA]), (0., 0.));
+
+    let near = FreeParameters::new(P3::new(1., 1., 0.), 0., &Vec3::new(1e-8, 0., 1.), 1., Some(free_covariance));
+    let (bound, bound_covariance) = near.to_bound(&sensor).unwrap();
+    assert!(is_finite(&bound_covariance.unwrap()));
+    assert!((bound[eTHETA] - 1e-8).abs() < 1e-15);
+}
+
+#[test]
+fn fit_along_axis() {
+    // the start location is right above the centers of the sensors
+    let sensors = (0..5).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+    let hits = (0..5).map(|_| Vec2::zeros()).collect::<Vec<_>>();
+    let covariance = (0..5).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+
+    let seed = utils::seed_state_vec_from_sensor(&P3::new(0., 0., -1.), &sensors[0], &hits[0]);
+    assert_eq!(seed, Vec5::new(0., 0., 0., 0., 1.));
+
+    // the jacobians of the bound parameters are singular along the axis
+    match linear::run_with_options(&P3::new(0., 0., -1.), &covariance, &hits, &sensors, None, &linear::FitterOptions::default()) {
+        Err(FilterError::Sensor(SensorError::InvalidDirection(direction))) => assert_eq!(direction, Vec3::z()),
+        _ => panic!("a track along the z axis can not be fitted with phi and theta")
+    }
+
+    // a track that is only close to the axis is fitted
+    let hits = (0..5).map(|i| Vec2::new(1e-3 * i as Real, 0.)).collect::<Vec<_>>();
+    let result = linear::run_with_options(&P3::new(-1e-3, 0., -1.), &covariance, &hits, &sensors, None, &linear::FitterOptions::default()).unwrap();
+    for state in result.pred.state_vec.iter().chain(&result.filt.state_vec) {
+        assert!(is_finite(state), "{}", state);
+    }
+    assert!(result.filt.cov_mat.iter().all(|cov_mat| cov_mat.abs().max() < 1e3));
+    assert!((result.filt.state_vec[4][eTHETA] - 1e-3).abs() < 1e-6, "{}", result.filt.state_vec[4]);
+}
+
+#[test]
+fn straw_jacobian_along_axis() {
+    // wires along x, stacked along z
+    let start = Straw::new(P3::origin(), Vec3::new(2., 0., 0.), 0.5, 100.).unwrap();
+    let end = Straw::new(P3::new(0., 0., 10.), Vec3::new(2., 0., 0.), 0.5, 100.).unwrap();
+
+    match jacobian::linear_straw(&Vec5::new(0.1, 0.2, 0.3, 0., 1.), 10., &start, &end) {
+        Err(SensorError::InvalidDirection(direction)) => assert_eq!(direction, Vec3::z()),
+        _ => panic!("a track along the z axis can not be fitted with phi and theta")
+    }
+
+    let near_axis = jacobian::linear_straw(&Vec5::new(0.1, 0.2, 0.3, 1e-3, 1.), 10., &start, &end).unwrap();
+    assert!(is_finite(&near_axis));
+}
@@ -0,0 +1,173 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{FilterError, SensorError};
+use krs::filter::angles::wrap_phi;
+use krs::filter::{filter_gain, linear, prediction, smoothing};
+use krs::filter::parameters::{BoundTrackParameters, FreeParameters};
+use krs::geometry::bounds::BoundaryCheck;
+use krs::geometry::intersection::NavigationDirection;
+use krs::geometry::traits::*;
+use krs::geometry::{GeometryId, Rectangle};
+
+/*
+
+    Tests for kalman_rs::filter::parameters::BoundTrackParameters and the filter steps
+    that predict, filter and smooth them
+
+*/
+
+const PHI: Real = 0.3;
+const THETA: Real = 0.2;
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., z));
+    let mut sensor = Rectangle::new(10., 10., translation, Mat4::identity()).unwrap();
+    sensor.set_geometry_id(GeometryId::new(2, 1, 1 + z as u32));
+    sensor
+}
+
+// local position of a straight track from (0.1, 0.2, 0) with angles PHI / THETA on the plane at z
+fn truth(z: Real) -> Vec2 {
+    let slope = THETA.tan();
+    Vec2::new(0.1 + slope * PHI.cos() * z, 0.2 + slope * PHI.sin() * z)
+}
+
+#[test]
+fn phi_wrapping() {
+    assert_eq!(wrap_phi(0.5), 0.5);
+    assert_eq!(wrap_phi(PI), PI);
+    assert_eq!(wrap_phi(-PI), PI);
+    assert!((wrap_phi(3. * PI / 2.) + PI / 2.).abs() < 1e-12);
+    assert!((wrap_phi(-5. * PI / 2.) + PI / 2.).abs() < 1e-12);
+    assert!((wrap_phi(7. * PI + 0.1) - (0.1 - PI)).abs() < 1e-12);
+}
+
+#[test]
+fn accessors() {
+    let id = GeometryId::new(1, 2, 3);
+    let mut parameters = BoundTrackParameters::from_vector(id, Vec5::new(1., 2., 2. * PI + 0.5, PI / 4., -0.25), None);
+
+    assert_eq!(parameters.surface(), id);
+    assert_eq!(parameters.local(), P2::new(1., 2.));
+    assert!((parameters.phi() - 0.5).abs() < 1e-12);
+    assert_eq!(parameters.theta(), PI / 4.);
+    assert_eq!(parameters.charge(), -1.);
+    assert!((parameters.momentum() - 4.).abs() < 1e-12);
+    assert!((parameters.transverse_momentum() - 4. * (PI / 4.).sin()).abs() < 1e-12);
+    assert!((parameters.eta() - 0.881373587019543).abs() < 1e-12);
+    assert!((parameters.momentum_vector() - parameters.direction() * 4.).norm() < 1e-12);
+    assert!((parameters.direction().norm() - 1.).abs() < 1e-12);
+
+    // the state vector is still the storage
+    let state_vec: &Vec5 = parameters.as_ref();
+    assert_eq!(state_vec[eQOP], parameters.qop());
+
+    parameters.set_local(&P2::new(-1., 0.));
+    parameters.set_phi(-PI);
+    parameters.set_theta(0.1);
+    parameters.set_qop(1.);
+    parameters.set_covariance(Some(Mat5::identity()));
+    assert_eq!(parameters.vector(), &Vec5::new(-1., 0., PI, 0.1, 1.));
+    assert_eq!(parameters.covariance(), Some(&Mat5::identity()));
+
+    let sensor = initialize_rect(4.);
+    assert_eq!(parameters.position(&sensor), P3::new(-1., 0., 4.));
+}
+
+#[test]
+fn free_conversions() {
+    let sensor = initialize_rect(4.);
+    let parameters = BoundTrackParameters::new(sensor.geometry_id(), P2::new(0.5, -1.5), -2.5, 0.4, -0.5, Some(Mat5::identity() * 0.01));
+
+    let free = parameters.to_free(&sensor);
+    assert_eq!(free, FreeParameters::from_bound(&sensor, parameters.vector(), parameters.covariance()));
+    assert_eq!(free.position(), parameters.position(&sensor));
+
+    let bound = BoundTrackParameters::from_free(&free, &sensor).unwrap();
+    assert_eq!(bound.surface(), sensor.geometry_id());
+    assert!((bound.vector() - parameters.vector()).norm() < 1e-12);
+    assert!((bound.covariance().unwrap() - parameters.covariance().unwrap()).abs().max() < 1e-12);
+
+    match BoundTrackParameters::from_free(&free, &initialize_rect(5.)) {
+        Err(SensorError::NotOnSurface(position)) => assert_eq!(position, free.position()),
+        _ => panic!("the parameters are not on the sensor")
+    }
+}
+
+#[test]
+fn seeded_fit() {
+    let sensors = (0..5).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+    let hits = (0..5).map(|i| truth(i as Real)).collect::<Vec<_>>();
+    let covariance = (0..5).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+    let options = linear::FitterOptions::default();
+
+    // without a covariance the seed behaves like a seed state vector
+    let first = hits[0];
+    let seed = BoundTrackParameters::new(sensors[0].geometry_id(), P2::new(first.x, first.y), PHI, THETA, 1., None);
+    let typed = linear::run_with_seed(&seed, &covariance, &hits, &sensors, &options).unwrap();
+    let raw = linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, Some(seed.vector()), &options).unwrap();
+    assert_eq!(typed.filt.state_vec, raw.filt.state_vec);
+    assert_eq!(typed.filt.cov_mat, raw.filt.cov_mat);
+
+    // the fitted states come back typed, on their sensors
+    let parameters = typed.filt.parameters(2).unwrap();
+    assert_eq!(parameters.surface(), sensors[2].geometry_id());
+    assert!((parameters.local().coords - hits[2]).norm() < 1e-9);
+    assert_eq!(parameters.covariance(), Some(&typed.filt.cov_mat[2]));
+    assert!(typed.filt.parameters(5).is_none());
+
+    // the covariance of the seed is used
+    let mut known = seed.clone();
+    known.set_covariance(Some(Mat5::identity() * 1e-6));
+    let result = linear::run_with_seed(&known, &covariance, &hits, &sensors, &options).unwrap();
+    assert_eq!(result.filt.cov_mat[0], Mat5::identity() * 1e-6);
+    assert!(result.filt.cov_mat[4][(eLOC_0, eLOC_0)] < raw.filt.cov_mat[4][(eLOC_0, eLOC_0)]);
+
+    // seeds have to be on the first fitted sensor
+    match linear::run_with_seed(&seed, &covariance, &hits, &sensors, &linear::FitterOptions {navigation: NavigationDirection::Backward, ..Default::default()}) {
+        Err(FilterError::Sensor(SensorError::WrongSeedSurface(id))) => assert_eq!(id, sensors[0].geometry_id()),
+        _ => panic!("the seed is not on the last sensor")
+    }
+}
+
+#[test]
+fn filter_steps() {
+    let sensors = (0..2).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+    let hits = (0..2).map(|i| truth(i as Real)).collect::<Vec<_>>();
+    let covariance = (0..2).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+
+    let first = hits[0];
+    let seed = BoundTrackParameters::new(sensors[0].geometry_id(), P2::new(first.x, first.y), PHI, THETA, 1., Some(Mat5::identity() * 0.01));
+    let fit = linear::run_with_seed(&seed, &covariance, &hits, &sensors, &linear::FitterOptions::default()).unwrap();
+
+    // the prediction is bound to the next sensor and carries the transported covariance
+    let predicted = prediction::linear_parameters(&sensors[0], &sensors[1], &seed, &BoundaryCheck::Strict, NavigationDirection::Forward).unwrap();
+    assert_eq!(predicted.parameters.surface(), sensors[1].geometry_id());
+    assert!((predicted.parameters.local().coords - hits[1]).norm() < 1e-9);
+    assert!((predicted.path_length - 1. / THETA.cos()).abs() < 1e-9);
+    assert_eq!(predicted.parameters.covariance(), Some(&(predicted.jacobian * seed.covariance().unwrap() * predicted.jacobian.transpose())));
+    assert_eq!(fit.pred.parameters(1), Some(predicted.parameters.clone()));
+
+    // the fitter is made of the same steps
+    let filtered = filter_gain::update(&predicted.parameters, &hits[1], &covariance[1]).unwrap();
+    assert_eq!(fit.filt.parameters(1), Some(filtered.parameters.clone()));
+    assert_eq!(fit.filt.res_vec[0], filtered.residual_vec);
+
+    let smoothed = smoothing::smooth(&seed, &predicted.jacobian, &predicted.parameters, &filtered.parameters).unwrap();
+    assert_eq!(smoothed.surface(), sensors[0].geometry_id());
+    assert_eq!(fit.smth.parameters(0), Some(smoothed));
+
+    // the filter needs the covariance of the prediction
+    let mut unknown = predicted.parameters;
+    unknown.set_covariance(None);
+    match filter_gain::update(&unknown, &hits[1], &covariance[1]) {
+        Err(FilterError::MissingCovariance) => {},
+        _ => panic!("a prediction without covariance can not be filtered")
+    }
+
+    let mut unknown = seed;
+    unknown.set_covariance(None);
+    let predicted = prediction::linear_parameters(&sensors[0], &sensors[1], &unknown, &BoundaryCheck::Strict, NavigationDirection::Forward).unwrap();
+    assert!(predicted.parameters.covariance().is_none());
+}
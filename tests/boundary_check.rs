@@ -0,0 +1,153 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{FilterError, SensorError};
+use krs::filter::{linear, prediction};
+use krs::geometry::bounds::BoundaryCheck;
+use krs::geometry::traits::Transform;
+use krs::geometry::{Disc, Rectangle, Straw};
+
+/*
+
+    Tests for inside checks with a tolerance (kalman_rs::geometry::bounds::BoundaryCheck)
+    and their use in the linear KF
+
+*/
+
+fn assert_close(left: Real, right: Real) {
+    dbg!{left}; dbg!{right};
+    assert!((left - right).abs() < DOT_PRODUCT_EPSILON)
+}
+
+// 2 x 2 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., z));
+    Rectangle::new(2., 2., translation, Mat4::identity()).unwrap()
+}
+
+#[test]
+fn rectangle_distance_to_boundary() {
+    let sensor = initialize_rect(0.);
+
+    assert_close(sensor.distance_to_boundary(&P2::new(0.5, 0.)), -0.5);
+    assert_close(sensor.distance_to_boundary(&P2::new(1.1, 0.)), 0.1);
+}
+
+#[test]
+fn absolute_tolerance() {
+    let sensor = initialize_rect(0.);
+    let point = P2::new(1.1, 0.);
+
+    assert!(!sensor.inside(&point));
+    assert!(!sensor.inside_with_tolerance(&point, &BoundaryCheck::Strict));
+    assert!(!sensor.inside_with_tolerance(&point, &BoundaryCheck::Absolute(0.05)));
+    assert!(sensor.inside_with_tolerance(&point, &BoundaryCheck::Absolute(0.15)));
+}
+
+#[test]
+fn covariance_tolerance() {
+    let sensor = initialize_rect(0.);
+    let point = P2::new(1.1, 0.);
+
+    // wide in y, but the closest edge is in x where the uncertainty is small
+    let narrow_x = Mat2::new(0.0001, 0.,
+                             0.,     1.);
+    let wide_x = Mat2::new(0.01, 0.,
+                           0.,   0.0001);
+
+    let check = |cov: Mat2| BoundaryCheck::Covariance{covariance: cov, n_sigma: 3.};
+
+    assert!(!sensor.inside_with_tolerance(&point, &check(narrow_x)));
+    assert!(sensor.inside_with_tolerance(&point, &check(wide_x)));
+
+    let tolerance = check(wide_x).tolerance(&point, |p| sensor.distance_to_boundary(p));
+    assert!((tolerance - 0.3).abs() < 1e-6);
+}
+
+// the disc local frame is polar, so the tolerance in phi is scaled by the radius
+#[test]
+fn disc_covariance_tolerance() {
+    let sensor = Disc::new(5., 10., 0., PI/8., Mat4::identity(), Mat4::identity()).unwrap();
+
+    let point = P2::new(7., (PI/8.) + 0.01);
+    assert_close(sensor.distance_to_boundary(&point), 7. * (0.01 as Real).sin());
+
+    let phi_cov = Mat2::new(0.0001, 0.,
+                            0.,     0.0001);
+    let check = BoundaryCheck::Covariance{covariance: phi_cov, n_sigma: 3.};
+
+    // 3 sigma in phi is 0.03 rad -> ~0.21 in distance at r = 7
+    assert!(!sensor.inside(&point));
+    assert!(sensor.inside_with_tolerance(&point, &check));
+}
+
+#[test]
+fn straw_tolerance() {
+    let straw = Straw::new(P3::origin(), Vec3::new(0., 0., 1.), 0.5, 10.).unwrap();
+    let point = P2::new(0.52, 0.);
+
+    assert_close(straw.distance_to_boundary(&point), 0.02);
+    assert!(!straw.inside(&point));
+    assert!(straw.inside_with_tolerance(&point, &BoundaryCheck::Absolute(0.05)));
+}
+
+#[test]
+fn prediction_with_tolerance() {
+    let start = initialize_rect(0.);
+    let end = initialize_rect(1.);
+
+    // moves ~0.1 in x per unit of z, so the prediction lands just outside of the end sensor
+    let state = Vec5::new(0.95, 0., 0., 0.1, 1.);
+
+    match prediction::linear_state_vector(&start, &end, &state) {
+        Err(SensorError::OutsideSensorBounds(local)) => assert!(local.x > 1.),
+        _ => panic!("prediction should be outside of the sensor")
+    }
+
+    let (pred, _) = prediction::linear_state_vector_with_check(&start, &end, &state, &BoundaryCheck::Absolute(0.1)).unwrap();
+    assert_close(pred[eLOC_0], 0.95 + (0.1 as Real).tan());
+}
+
+#[test]
+fn fitter_with_tolerance() {
+    let sensors = (0..4).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+
+    // a straight track leaving the sensors through the +x edge
+    let slope = (0.1 as Real).tan();
+    let hits = (0..4).map(|i| Vec2::new(0.95 + slope * (i as Real), 0.)).collect::<Vec<_>>();
+    let covariance = (0..4).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+    let seed = Vec5::new(0.95, 0., 0., 0.1, 1.);
+
+    let strict = linear::FitterOptions::default();
+    let tolerant = linear::FitterOptions{boundary_check: BoundaryCheck::Absolute(0.5), ..Default::default()};
+
+    assert!(linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, Some(&seed), &strict).is_err());
+
+    let result = linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, Some(&seed), &tolerant).unwrap();
+
+    result.filt.state_vec.iter()
+        .zip(hits.iter())
+        .skip(1)
+        .for_each(|(filt, hit)| assert!((filt[eLOC_0] - hit.x).abs() < 0.01));
+}
+
+#[test]
+fn fitter_with_predicted_covariance() {
+    let sensors = (0..2).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+
+    let slope = (0.1 as Real).tan();
+    let hits = (0..2).map(|i| Vec2::new(0.95 + slope * (i as Real), 0.)).collect::<Vec<_>>();
+    let covariance = (0..2).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+    let seed = Vec5::new(0.95, 0., 0., 0.1, 1.);
+
+    // the covariance of the check is replaced by the one of the prediction, which is wide
+    // enough to accept it 0.05 outside of the sensor
+    let check = |n_sigma| linear::FitterOptions{boundary_check: BoundaryCheck::Covariance{covariance: Mat2::zeros(), n_sigma}, ..Default::default()};
+
+    match linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, Some(&seed), &check(0.)) {
+        Err(FilterError::Sensor(SensorError::OutsideSensorBounds(local))) => assert!(local.x > 1.),
+        _ => panic!("the prediction is outside of the sensor")
+    }
+
+    let result = linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, Some(&seed), &check(3.));
+    assert!(result.is_ok(), "{:?}", result.err());
+}
@@ -0,0 +1,153 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{GeometryError, SensorError};
+use krs::geometry::bounds::*;
+use krs::geometry::traits::Transform;
+use krs::geometry::{PlaneSurface, Trapezoid};
+
+/*
+
+    Tests for the shared bounds checks in kalman_rs::geometry::bounds and sensors
+    built on top of them
+
+*/
+
+fn assert_close(left: Real, right: Real) {
+    dbg!{left}; dbg!{right};
+    assert!((left - right).abs() < DOT_PRODUCT_EPSILON)
+}
+
+#[test]
+fn rectangle_bounds_distance() {
+    let bounds = RectangleBounds::new(2., 1.);
+
+    assert_close(bounds.distance_to_boundary(&P2::new(0., 0.)), -1.);
+    assert_close(bounds.distance_to_boundary(&P2::new(3., 0.)), 1.);
+    assert_close(bounds.distance_to_boundary(&P2::new(5., 5.)), 5.);
+
+    assert!(bounds.inside(&P2::new(1.9, -0.9), 0.));
+    assert!(!bounds.inside(&P2::new(2.1, 0.), 0.));
+    assert!(bounds.inside(&P2::new(2.1, 0.), 0.2));
+}
+
+#[test]
+fn degenerate_trapezoid_sensor() {
+    // both bases of zero length collapse the trapezoid onto a line
+    match Trapezoid::new(0., 0., Mat4::identity(), Mat4::identity(), 2.) {
+        Err(GeometryError::Sensor(SensorError::InvalidBounds(_))) => {},
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn trapezoid_bounds_matches_sensor() {
+    // bottom base of 5 at -y, top base of 2 at +y
+    let bounds = TrapezoidBounds::new(2.5, 1., 1.).unwrap();
+    let sensor = Trapezoid::new(2., 5., Mat4::identity(), Mat4::identity(), 2.).unwrap();
+
+    let points = vec![
+        P2::new(0., 0.), P2::new(2.4, -0.99), P2::new(2.4, 0.99),
+        P2::new(-1.1, 0.9), P2::new(0., 1.1), P2::new(-1.7, 0.)
+    ];
+
+    for point in points {
+        assert_eq!(bounds.inside(&point, 0.), sensor.inside(&point));
+    }
+
+    assert!(sensor.inside(&P2::new(2.4, -0.99)));
+    assert!(!sensor.inside(&P2::new(2.4, 0.99)));
+}
+
+#[test]
+fn polygon_bounds_orders_vertices() {
+    // clockwise square with a repeated closing corner
+    let corners = vec![
+        P2::new(-1., -1.), P2::new(-1., 1.), P2::new(1., 1.), P2::new(1., -1.), P2::new(-1., -1.)
+    ];
+    let square = ConvexPolygonBounds::new(corners).unwrap();
+
+    assert_eq!(square.vertices().len(), 4);
+    assert_close(square.distance_to_boundary(&P2::new(0., 0.)), -1.);
+    assert_close(square.distance_to_boundary(&P2::new(0., 3.)), 2.);
+}
+
+#[test]
+fn polygon_bounds_invalid() {
+    let line = vec![P2::new(0., 0.), P2::new(1., 0.)];
+    assert!(ConvexPolygonBounds::new(line).is_err());
+
+    let concave = vec![
+        P2::new(0., 0.), P2::new(2., 0.), P2::new(1., 0.5), P2::new(2., 2.), P2::new(0., 2.)
+    ];
+    assert!(ConvexPolygonBounds::new(concave).is_err());
+
+    let collinear = vec![P2::new(0., 0.), P2::new(1., 0.), P2::new(2., 0.)];
+    assert!(ConvexPolygonBounds::new(collinear).is_err());
+}
+
+#[test]
+fn polygon_bounds_pentagram() {
+    // every second corner of a regular pentagon, all turns have the same sign
+    let pentagram = (0..5)
+        .map(|i| {
+            let angle = (2 * i) as Real * 2. * PI / 5.;
+            P2::new(angle.cos(), angle.sin())
+        })
+        .collect::<Vec<_>>();
+    assert!(ConvexPolygonBounds::new(pentagram).is_err());
+
+    let pentagon = (0..5)
+        .map(|i| {
+            let angle = i as Real * 2. * PI / 5.;
+            P2::new(angle.cos(), angle.sin())
+        })
+        .collect::<Vec<_>>();
+    assert!(ConvexPolygonBounds::new(pentagon).is_ok());
+}
+
+#[test]
+fn diamond_bounds() {
+    let diamond = DiamondBounds::new(1., 3., 2., 2., 4.).unwrap();
+
+    assert_eq!(diamond.vertices().len(), 6);
+    assert!(diamond.inside(&P2::new(2.9, 0.), 0.));
+    assert!(!diamond.inside(&P2::new(2.9, 1.), 0.));
+    assert!(diamond.inside(&P2::new(1.9, 3.9), 0.));
+    assert!(!diamond.inside(&P2::new(0., -2.1), 0.));
+
+    assert!(DiamondBounds::new(4., 3., 2., 2., 4.).is_err());
+}
+
+#[test]
+fn annulus_bounds() {
+    let annulus = AnnulusBounds::new(2., 4., PI/2., PI/4.).unwrap();
+
+    assert!(annulus.inside(&P2::new(0., 3.), 0.));
+    assert!(!annulus.inside(&P2::new(0., 1.), 0.));
+    assert!(!annulus.inside(&P2::new(0., -3.), 0.));
+    assert_close(annulus.distance_to_boundary(&P2::new(0., 3.)), -1.);
+    assert_close(annulus.distance_to_boundary(&P2::new(0., 5.)), 1.);
+
+    // just past the phi edge, accepted with a tolerance
+    let phi = (3. * PI / 4.) + 0.01;
+    let point = P2::new(3. * phi.cos(), 3. * phi.sin());
+    assert!(!annulus.inside(&point, 0.));
+    assert!(annulus.inside(&point, 0.05));
+
+    assert!(AnnulusBounds::new(4., 2., 0., PI).is_err());
+}
+
+#[test]
+fn plane_surface_bounds_check() {
+    let bounds = DiamondBounds::new(1., 3., 2., 2., 4.).unwrap();
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., 10.));
+
+    let sensor = PlaneSurface::new(bounds, translation, Mat4::identity()).unwrap();
+
+    let global = sensor.to_global(P3::new(2.5, 0., 0.));
+    assert_close(global.z, 10.);
+
+    let local = sensor.to_local(global);
+    assert!(sensor.inside(&local));
+    assert!(!sensor.inside(&P2::new(0., 4.1)));
+}
@@ -0,0 +1,108 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::GeometryError;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::description::PlacementDescription;
+use krs::geometry::material::{Material, MaterialSlab, SurfaceMaterial};
+use krs::geometry::traits::*;
+use krs::geometry::validation::check_transform;
+use krs::geometry::GeometryId;
+
+/*
+
+    Tests for kalman_rs::geometry::builder
+
+*/
+
+fn assert_vec_close(left: &Vec3, right: &Vec3) {
+    assert!((left - right).norm() < 1e-12, "{} != {}", left, right)
+}
+
+#[test]
+fn placements() {
+    let center = P3::new(1., 2., 3.);
+
+    // the local x axis is made perpendicular to the normal
+    let placement = Placement::from_normal(center, &Vec3::new(0., 0., 3.), Some(&Vec3::new(1., 0., 1.))).unwrap();
+    assert_vec_close(&placement.local_x(), &Vec3::x());
+    assert_vec_close(&placement.local_y(), &Vec3::y());
+    assert_vec_close(&placement.normal(), &Vec3::z());
+
+    // default local x axis
+    let placement = Placement::from_normal(center, &Vec3::new(0., 0., -1.), None).unwrap();
+    assert_vec_close(&placement.local_x(), &Vec3::x());
+    assert_vec_close(&placement.local_y(), &-Vec3::y());
+
+    let normal = Vec3::new(1., 1., 0.).normalize();
+    let placement = Placement::from_normal(center, &normal, None).unwrap();
+    assert_vec_close(&placement.normal(), &normal);
+    assert!(placement.local_x().dot(&normal).abs() < 1e-12);
+    assert!((placement.rotation().determinant() - 1.).abs() < 1e-12);
+
+    // a quarter turn about z
+    let placement = Placement::from_euler_angles(center, 0., 0., PI / 2.);
+    assert_vec_close(&placement.local_x(), &Vec3::y());
+    assert_vec_close(&placement.normal(), &Vec3::z());
+    assert_eq!(placement.center(), &center);
+
+    match Placement::from_normal(center, &Vec3::zeros(), None) {
+        Err(GeometryError::InvalidPlacement(_)) => {},
+        _ => panic!("a zero normal is not a placement")
+    }
+    match Placement::from_normal(center, &Vec3::z(), Some(&Vec3::new(0., 0., 2.))) {
+        Err(GeometryError::InvalidPlacement(_)) => {},
+        _ => panic!("the local x axis can not be along the normal")
+    }
+}
+
+#[test]
+fn placement_from_matrices() {
+    let placement = Placement::from_euler_angles(P3::new(0., 1., 0.), 0.1, 0.2, 0.3);
+    let (translation, rotation) = placement.matrices();
+    assert_eq!(Placement::from_matrices(&translation, &rotation, 1e-12).unwrap(), placement);
+
+    // scaled, sheared and combined matrices are rejected
+    let scaled = rotation * 2.;
+    let mut sheared = rotation;
+    sheared[(0, 1)] += 0.1;
+
+    for (translation, rotation) in &[(translation, scaled), (translation, sheared), (translation * rotation, rotation)] {
+        match Placement::from_matrices(translation, rotation, 1e-12) {
+            Err(GeometryError::InvalidPlacement(_)) => {},
+            _ => panic!("{} is not a placement", rotation)
+        }
+    }
+}
+
+#[test]
+fn built_surfaces() {
+    let slab = MaterialSlab::new(Material::new(93.7, 465.2, 28.0855, 14., 2.329), 0.3);
+    let normal = Vec3::new(0.3, -0.2, 1.);
+    let placement = Placement::from_normal(P3::new(0., 0., 10.), &normal, Some(&Vec3::x())).unwrap();
+
+    let plane = SurfaceBuilder::new(placement.clone())
+        .geometry_id(GeometryId::new(2, 4, 6))
+        .material(SurfaceMaterial::Homogeneous(slab))
+        .plane(SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.)))
+        .unwrap();
+
+    assert_eq!(plane.geometry_id(), GeometryId::new(2, 4, 6));
+    assert_eq!(plane.material_at(&P2::origin()), Some(&slab));
+    assert_vec_close(&plane.plane_normal_vec().normalize(), &normal.normalize());
+    assert!(check_transform(&plane, 1e-12).is_empty());
+
+    let global = plane.to_global(P3::new(0.5, -0.5, 0.));
+    assert!(plane.on_plane(&global));
+    assert!((plane.to_local(global) - P2::new(0.5, -0.5)).norm() < 1e-12);
+
+    let disc = SurfaceBuilder::new(placement).tolerance(1e-12).disc(1., 2., 0., PI).unwrap();
+    assert!(check_transform(&disc, 1e-12).is_empty());
+    assert!(disc.inside(&disc.to_local(disc.to_global(P3::new(1.5, 0.3, 0.)))));
+
+    // the axes placement of a description gives the same surface
+    let description = PlacementDescription::Axes {center: [0., 0., 10.], normal: [0.3, -0.2, 1.], local_x: [1., 0., 0.]};
+    let (translation, rotation) = description.matrices().unwrap();
+    assert!((plane.rotation_to_global() - rotation).norm() < 1e-12);
+    assert_eq!(plane.global_center(), &P3::from(translation.fixed_slice::<U3, U1>(0, 3).into_owned()));
+}
@@ -0,0 +1,164 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::generate_data::setup;
+use krs::geometry::bounds::{AnnulusBounds, Bounds, RectangleBounds, SurfaceBounds};
+use krs::geometry::bvh::{Aabb, Helix, SurfaceBvh};
+use krs::geometry::navigator::Navigator;
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::Identified;
+use krs::geometry::{GeometryId, PlaneSurface, Surface};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+/*
+
+    Tests for kalman_rs::geometry::bvh
+
+*/
+
+// 2 x 2 square parallel to the x-y plane centered at (x, y, z)
+fn initialize_square(center: Vec3, id: GeometryId) -> Surface {
+    let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    let translation = Mat4::new_translation(&center);
+
+    let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, Mat4::identity()).unwrap());
+    surface.set_geometry_id(id);
+    surface
+}
+
+// 10 layers along z with a 9 x 9 grid of modules on each
+fn initialize_geometry() -> TrackingGeometry {
+    let mut surfaces = Vec::new();
+
+    for layer in 1..=10 {
+        let mut sensitive = 1;
+        for i in -4..=4 {
+            for j in -4..=4 {
+                let center = Vec3::new(2. * (i as Real), 2. * (j as Real), layer as Real);
+                surfaces.push(initialize_square(center, GeometryId::new(1, layer, sensitive)));
+                sensitive += 1;
+            }
+        }
+    }
+
+    TrackingGeometry::new(surfaces).unwrap()
+}
+
+#[test]
+fn aabb_intersection() {
+    let bounds = Aabb::from_points(&[P3::new(-1., -1., -1.), P3::new(1., 1., 1.)]).unwrap();
+
+    let origin = P3::new(-5., 0., 0.);
+    assert!(bounds.intersects(&origin, &Vec3::new(1., 0., 0.), 0., Real::INFINITY));
+    assert!(!bounds.intersects(&origin, &Vec3::new(-1., 0., 0.), 0., Real::INFINITY));
+    // stops before reaching the box
+    assert!(!bounds.intersects(&origin, &Vec3::new(1., 0., 0.), 0., 3.));
+    // parallel to a face, outside of the box
+    assert!(!bounds.intersects(&P3::new(-5., 2., 0.), &Vec3::new(1., 0., 0.), 0., Real::INFINITY));
+    assert!(bounds.expanded(1.5).intersects(&P3::new(-5., 2., 0.), &Vec3::new(1., 0., 0.), 0., Real::INFINITY));
+
+    assert!(Aabb::from_points(&[]).is_none());
+}
+
+// the hierarchy never misses a surface that a full scan would find
+#[test]
+fn ray_candidates_match_scan() {
+    let geometry = initialize_geometry();
+    let bvh = geometry.bvh();
+    assert_eq!(bvh.len(), geometry.len());
+
+    let mut rng = SmallRng::seed_from_u64(33);
+
+    for _ in 0..100 {
+        let start = P3::new(rng.gen_range(-9., 9.), rng.gen_range(-9., 9.), 0.);
+        let direction = Vec3::new(rng.gen_range(-1., 1.), rng.gen_range(-1., 1.), 1.);
+
+        let candidates = bvh.ray_candidates(&start, &direction, 0.);
+        assert!(candidates.len() < geometry.len());
+
+        let brute_force = Navigator::new(&geometry).crossings(&start, &direction);
+        brute_force.iter().for_each(|crossing| assert!(candidates.contains(&crossing.geometry_id)));
+    }
+}
+
+#[test]
+fn helix_points() {
+    let axis = Vec3::new(0., 0., 1.);
+    let straight = Helix::new(P3::origin(), Vec3::new(1., 0., 1.), axis, Real::INFINITY);
+    let step = (2. as Real).sqrt();
+    assert!((straight.point(step) - P3::new(1., 0., 1.)).norm() < DOT_PRODUCT_EPSILON);
+
+    // circle in the x-y plane closes after one turn
+    let radius = 3.;
+    let circle = Helix::new(P3::origin(), Vec3::new(1., 0., 0.), axis, radius);
+    let turn = 2. * PI * radius;
+    assert!(circle.point(turn).coords.norm() < 1e-9);
+    assert!((circle.point(turn / 2.) - P3::new(0., 2. * radius, 0.)).norm() < 1e-9);
+
+    // the pitch moves the helix along the axis
+    let helix = Helix::new(P3::origin(), Vec3::new(1., 0., 1.), axis, radius);
+    let point = helix.point(2. * PI * radius * step);
+    assert!((point - P3::new(0., 0., 2. * PI * radius)).norm() < 1e-9);
+}
+
+// a track bending in the x-y plane while moving along z
+#[test]
+fn helix_crossings() {
+    let geometry = initialize_geometry();
+    let navigator = Navigator::new(&geometry);
+
+    let helix = Helix::new(P3::new(0., 0., 0.5), Vec3::new(1., 0., 1.), Vec3::new(0., 0., 1.), 10.);
+    let crossings = navigator.helix_crossings(&helix, 20., 0.5);
+
+    assert_eq!(crossings.len(), 10);
+
+    crossings.iter()
+        .enumerate()
+        .for_each(|(i, crossing)| {
+            assert_eq!(crossing.geometry_id.layer() as usize, i + 1);
+            assert!((crossing.global.z - ((i + 1) as Real)).abs() < 1e-9);
+            assert!((helix.point(crossing.path_length) - crossing.global).norm() < 1e-9);
+        });
+
+    let candidates = geometry.bvh().helix_candidates(&helix, 20., 0.5, 0.);
+    assert!(candidates.len() < geometry.len());
+    crossings.iter().for_each(|crossing| assert!(candidates.contains(&crossing.geometry_id)));
+}
+
+#[test]
+fn geometry_truth_simulation() {
+    let geometry = initialize_geometry();
+    let navigator = Navigator::new(&geometry);
+    let mut rng = SmallRng::seed_from_u64(0);
+
+    let (truth, measurements) = setup::generate_geometry_track(
+        &navigator, &P3::new(0.5, 0.5, 0.), &Vec3::new(0.1, 0., 1.), &mut rng, 0.001
+    );
+
+    assert_eq!(truth.len(), 10);
+    assert_eq!(measurements.len(), 10);
+
+    truth.iter()
+        .zip(measurements.iter())
+        .for_each(|(crossing, measurement)| {
+            assert_eq!(crossing.geometry_id, measurement.geometry_id);
+            assert!((crossing.local.coords - measurement.local).norm() < 0.01);
+        });
+}
+
+#[test]
+fn empty_hierarchy() {
+    let bvh = SurfaceBvh::new(Vec::new());
+
+    assert!(bvh.is_empty());
+    assert!(bvh.ray_candidates(&P3::origin(), &Vec3::new(0., 0., 1.), 0.).is_empty());
+}
+
+#[test]
+fn annulus_outline() {
+    let bounds = AnnulusBounds::new(1., 2., -0.5, 0.5).unwrap();
+    let outline = bounds.outline(8);
+
+    outline.iter().for_each(|point| assert!(bounds.distance_to_boundary(point).abs() < 1e-9));
+}
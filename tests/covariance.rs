@@ -0,0 +1,176 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::SensorError;
+use krs::filter::covariance;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::intersection::{Intersect, NavigationDirection};
+use krs::geometry::traits::*;
+use krs::geometry::Surface;
+
+/*
+
+    Tests for kalman_rs::filter::covariance
+
+*/
+
+const STEP: Real = 1e-6;
+
+fn tilted_plane(center: P3, normal: Vec3) -> Surface {
+    let placement = Placement::from_normal(center, &normal, Some(&Vec3::x())).unwrap();
+    SurfaceBuilder::new(placement).plane(SurfaceBounds::Rectangle(RectangleBounds::new(10., 10.))).unwrap()
+}
+
+fn covariance() -> Mat5 {
+    let a = Mat5::from_fn(|i, j| 0.1 * ((i * 5 + j) as Real).sin());
+    a * a.transpose() + Mat5::identity() * 0.01
+}
+
+macro_rules! assert_mat_close {
+    ($left:expr, $right:expr, $tolerance:expr) => {
+        assert!(($left - $right).abs().max() < $tolerance, "{} != {}", $left, $right)
+    };
+}
+
+// bound state on `end` of the straight track of a bound state on `start`
+fn propagate_bound(start: &Surface, end: &Surface, state: &Vec5) -> Vec5 {
+    let global = start.to_global(P3::new(state[eLOC_0], state[eLOC_1], 0.));
+    let direction = Vec3::new(state[ePHI].cos() * state[eTHETA].sin(), state[ePHI].sin() * state[eTHETA].sin(), state[eTHETA].cos());
+    let local = end.intersect(&global, &direction, NavigationDirection::Forward)[0].local;
+
+    Vec5::new(local.x, local.y, state[ePHI], state[eTHETA], state[eQOP])
+}
+
+// finite difference jacobian of a map between 5 parameters
+fn numerical_jacobian<F: Fn(&Vec5) -> Vec5>(state: &Vec5, map: F) -> Mat5 {
+    let mut jacobian = Mat5::zeros();
+    for i in 0..5 {
+        let mut step = Vec5::zeros();
+        step[i] = STEP;
+        jacobian.set_column(i, &((map(&(state + step)) - map(&(state - step))) / (2. * STEP)));
+    }
+    jacobian
+}
+
+#[test]
+fn bound_free_round_trip() {
+    let sensor = tilted_plane(P3::new(1., 2., 3.), Vec3::new(0.2, -0.3, 1.));
+    let state = Vec5::new(0.5, -1., 0.4, 0.3, 0.5);
+
+    let free = covariance::bound_to_free(&sensor, &state, &covariance());
+    assert_eq!(free.row(3).norm(), 0.);
+    assert_mat_close!(&covariance::free_to_bound(&sensor, &state, &free).unwrap(), &covariance(), 1e-12);
+
+    // the free position varies in the plane of the sensor
+    let normal = sensor.plane_normal_vec().normalize();
+    assert!((normal.transpose() * free.fixed_slice::<U3, U3>(0, 0) * normal)[0].abs() < 1e-12);
+
+    // tracks parallel to the sensor can not be bound to it
+    let parallel = Vec5::new(0., 0., 0., PI / 2., 1.);
+    let flat = tilted_plane(P3::origin(), Vec3::z());
+    match covariance::free_to_bound(&flat, &parallel, &free) {
+        Err(SensorError::InvalidDirection(_)) => {},
+        _ => panic!("a track parallel to the sensor has no bound covariance")
+    }
+}
+
+#[test]
+fn bound_to_bound_at_same_point() {
+    let start = tilted_plane(P3::new(0., 0., 3.), Vec3::new(0.2, -0.3, 1.));
+    let state = Vec5::new(0.5, -1., 0.4, 0.3, 0.5);
+    let global = start.to_global(P3::new(0.5, -1., 0.));
+
+    // a differently tilted plane through the same point
+    let end = tilted_plane(global, Vec3::new(-0.4, 0.1, 1.));
+
+    let (end_state, end_covariance) = covariance::bound_to_bound(&start, &end, &state, &covariance()).unwrap();
+    assert!((end.to_global(P3::new(end_state[eLOC_0], end_state[eLOC_1], 0.)) - global).norm() < 1e-12);
+
+    // same as moving the track along its direction onto the other plane
+    let jacobian = numerical_jacobian(&state, |s| propagate_bound(&start, &end, s));
+    assert_mat_close!(&end_covariance, &(jacobian * covariance() * jacobian.transpose()), 1e-8);
+
+    // onto itself the covariance does not change
+    let (same_state, same_covariance) = covariance::bound_to_bound(&start, &start, &state, &covariance()).unwrap();
+    assert_mat_close!(&same_state, &state, 1e-12);
+    assert_mat_close!(&same_covariance, &covariance(), 1e-12);
+
+    let away = tilted_plane(P3::new(0., 0., 10.), Vec3::z());
+    match covariance::bound_to_bound(&start, &away, &state, &covariance()) {
+        Err(SensorError::NotOnSurface(point)) => assert!((point - global).norm() < 1e-12),
+        _ => panic!("the state is not on the end surface")
+    }
+}
+
+#[test]
+fn curvilinear_frame() {
+    let (phi, theta) = (2.5, 0.7);
+    let (u, v) = covariance::curvilinear_axes(phi, theta);
+    let direction = Vec3::new(phi.cos() * theta.sin(), phi.sin() * theta.sin(), theta.cos());
+
+    assert!((u.cross(&v) - direction).norm() < 1e-12);
+    assert!(u.z.abs() < 1e-12);
+
+    // a sensor spanned by the curvilinear axes has the curvilinear covariance
+    let placement = Placement::from_normal(P3::new(1., 1., 1.), &direction, Some(&u)).unwrap();
+    let sensor = SurfaceBuilder::new(placement).plane(SurfaceBounds::Rectangle(RectangleBounds::new(10., 10.))).unwrap();
+    let state = Vec5::new(0.3, 0.2, phi, theta, 1.);
+
+    assert_mat_close!(&covariance::bound_to_curvilinear(&sensor, &state, &covariance()), &covariance(), 1e-12);
+
+    let free = covariance::curvilinear_to_free(phi, theta, &covariance());
+    assert_mat_close!(&covariance::free_to_curvilinear(phi, theta, &free), &covariance(), 1e-12);
+
+    // from a tilted sensor the local position uncertainty shrinks onto the curvilinear plane
+    let tilted = tilted_plane(P3::new(0., 0., 2.), Vec3::z());
+    let state = Vec5::new(0.3, 0.2, 0., 0.6, 1.);
+    let curvilinear = covariance::bound_to_curvilinear(&tilted, &state, &Mat5::identity());
+    assert!((curvilinear[(eLOC_0, eLOC_0)] - 1.).abs() < 1e-12);
+    assert!((curvilinear[(eLOC_1, eLOC_1)] - (0.6 as Real).cos().powi(2)).abs() < 1e-12);
+}
+
+#[test]
+fn perigee_frame() {
+    let reference = P3::new(0.1, -0.2, 0.5);
+    let state = Vec5::new(0.3, -1.2, 2.1, 1.1, 0.5);
+
+    let (position, direction) = covariance::perigee_to_global(&reference, &state);
+    assert_mat_close!(&covariance::global_to_perigee(&reference, &position, &direction, 0.5).unwrap(), &state, 1e-12);
+
+    // the same track seen from another point on it
+    let later = position + (direction * 3.);
+    assert_mat_close!(&covariance::global_to_perigee(&reference, &later, &direction, 0.5).unwrap(), &state, 1e-12);
+
+    // perigee -> free matches the change of the global position and direction
+    let jacobian = covariance::perigee_to_free_jacobian(&state);
+    for i in 0..5 {
+        let mut step = Vec5::zeros();
+        step[i] = STEP;
+        let (high_position, high_direction) = covariance::perigee_to_global(&reference, &(state + step));
+        let (low_position, low_direction) = covariance::perigee_to_global(&reference, &(state - step));
+
+        let position_derivative = (high_position - low_position) / (2. * STEP);
+        let direction_derivative = (high_direction - low_direction) / (2. * STEP);
+        assert!((jacobian.fixed_slice::<U3, U1>(0, i) - position_derivative).norm() < 1e-8);
+        assert!((jacobian.fixed_slice::<U3, U1>(4, i) - direction_derivative).norm() < 1e-8);
+    }
+
+    // free -> perigee matches moving the free position
+    let inverse = covariance::free_to_perigee_jacobian(&state).unwrap();
+    for i in 0..3 {
+        let mut step = Vec3::zeros();
+        step[i] = STEP;
+        let high = covariance::global_to_perigee(&reference, &(position + step), &direction, 0.5).unwrap();
+        let low = covariance::global_to_perigee(&reference, &(position - step), &direction, 0.5).unwrap();
+        assert!((inverse.column(i) - ((high - low) / (2. * STEP))).norm() < 1e-8);
+    }
+
+    let free = covariance::perigee_to_free(&state, &covariance());
+    assert_mat_close!(&covariance::free_to_perigee(&state, &free).unwrap(), &covariance(), 1e-12);
+
+    // a track along the beamline has no perigee
+    match covariance::global_to_perigee(&reference, &position, &Vec3::z(), 0.5) {
+        Err(SensorError::InvalidDirection(_)) => {},
+        _ => panic!("a track along the beamline has no point of closest approach")
+    }
+}
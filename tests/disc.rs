@@ -0,0 +1,190 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{GeometryError, SensorError};
+use krs::geometry::Disc;
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::traits::{Plane, Transform};
+use krs::filter::{linear, prediction};
+
+/*
+
+    Tests for kalman_rs::geometry::Disc which describes endcap sensors with a
+    polar (r, phi) local frame
+
+*/
+
+fn initialize_disc(z: Real) -> Disc {
+    let translation = Trl3::new(0., 0., z).to_homogeneous();
+    Disc::new(2., 10., 0., PI, translation, Mat4::identity()).unwrap()
+}
+
+fn assert_close(left: Real, right: Real) {
+    dbg!{left}; dbg!{right};
+    assert!((left - right).abs() < DOT_PRODUCT_EPSILON)
+}
+
+#[test]
+fn disc_invalid_bounds() {
+    for (r_min, r_max) in &[(5., 2.), (3., 3.), (-1., 2.)] {
+        match Disc::new(*r_min, *r_max, 0., PI, Mat4::identity(), Mat4::identity()) {
+            Err(GeometryError::Sensor(SensorError::InvalidBounds(_))) => (),
+            other => panic!("{:?}", other)
+        }
+    }
+
+    let placement = Placement::from_normal(P3::origin(), &Vec3::z(), None).unwrap();
+    assert!(SurfaceBuilder::new(placement).disc(2., 1., 0., PI).is_err());
+}
+
+#[test]
+fn disc_to_local() {
+    let disc = initialize_disc(5.);
+
+    let local = disc.to_local(P3::new(0., 4., 5.));
+
+    assert_close(local.x, 4.);
+    assert_close(local.y, PI/2.);
+}
+
+#[test]
+fn disc_round_trip() {
+    let disc = initialize_disc(5.);
+    let local = P3::new(6., -3. * PI / 4., 0.);
+
+    let global = disc.to_global(local);
+    let back = disc.to_local(global);
+
+    assert_close(global.z, 5.);
+    assert_close(back.x, local.x);
+    assert_close(back.y, local.y);
+}
+
+#[test]
+fn disc_inside_bounds() {
+    let translation = Trl3::new(0., 0., 1.).to_homogeneous();
+    let disc = Disc::new(2., 10., PI, PI/8., translation, Mat4::identity()).unwrap();
+
+    // module straddles phi = +/- pi
+    assert!(disc.inside(&P2::new(5., PI - 0.1)));
+    assert!(disc.inside(&P2::new(5., -PI + 0.1)));
+
+    assert!(!disc.inside(&P2::new(1., PI)));
+    assert!(!disc.inside(&P2::new(11., PI)));
+    assert!(!disc.inside(&P2::new(5., 0.)));
+}
+
+#[test]
+fn disc_on_plane() {
+    let disc = initialize_disc(3.);
+
+    assert!(disc.on_plane(&P3::new(1., 1., 3.)));
+    assert!(!disc.on_plane(&P3::new(1., 1., 4.)));
+}
+
+// d(global) / d(r, phi) against a finite difference of `to_global`
+#[test]
+fn disc_local_to_global_derivative() {
+    let disc = initialize_disc(2.);
+    let local = P2::new(4., 0.3);
+    let step = 1e-6;
+
+    let derivative = disc.local_to_global_derivative(&local);
+
+    let base = disc.to_global(P3::new(local.x, local.y, 0.));
+    let dr = (disc.to_global(P3::new(local.x + step, local.y, 0.)) - base) / step;
+    let dphi = (disc.to_global(P3::new(local.x, local.y + step, 0.)) - base) / step;
+
+    for i in 0..3 {
+        assert_close(derivative[(i, 0)], dr[i]);
+        assert_close(derivative[(i, 1)], dphi[i]);
+    }
+}
+
+// global => local derivative should invert local => global on the plane
+#[test]
+fn disc_derivatives_inverse() {
+    let disc = initialize_disc(2.);
+    let local = P2::new(3., -2.);
+
+    let product = disc.global_to_local_derivative(&local) * disc.local_to_global_derivative(&local);
+    let identity = Mat2::identity();
+
+    for i in 0..4 {
+        assert_close(product[i], identity[i]);
+    }
+}
+
+#[test]
+fn disc_polar_measurement() {
+    let disc = initialize_disc(0.);
+
+    let cov = Mat2::new(0.01, 0., 0., 0.04);
+    let (hit, polar_cov) = disc.polar_measurement(&P2::new(0., 2.), &cov);
+
+    assert_close(hit.x, 2.);
+    assert_close(hit.y, PI/2.);
+    // x error becomes phi error scaled by 1/r, y error becomes radial error
+    assert_close(polar_cov[(0, 0)], 0.04);
+    assert_close(polar_cov[(1, 1)], 0.01 / 4.);
+}
+
+// full linear filter on a set of endcap discs
+#[test]
+fn disc_linear_filter() {
+    let start = initialize_disc(0.);
+    let discs = (1..6).map(|i| initialize_disc(i as Real)).collect::<Vec<_>>();
+
+    let (phi, theta) = (PI/3., PI/5.);
+    let start_state = Vec5::new(3., 0., phi, theta, 1.);
+
+    let hits = discs.iter()
+        .map(|disc| {
+            let (state, _) = prediction::linear_state_vector(&start, disc, &start_state).unwrap();
+            Vec2::new(state[eLOC_0], state[eLOC_1])
+        })
+        .collect::<Vec<_>>();
+
+    let covariance = discs.iter().map(|_| Mat2::new(0.01, 0., 0., 0.001)).collect::<Vec<_>>();
+
+    let seed = Vec5::new(hits[0].x, hits[0].y, phi, theta, 1.);
+    let result = linear::run(&P3::origin(), &covariance, &hits, &discs, Some(&seed));
+
+    result.filt.state_vec.iter()
+        .zip(hits.iter())
+        .for_each(|(state, hit)| {
+            assert_close(state[eLOC_0], hit.x);
+            assert_close(state[eLOC_1], hit.y);
+        });
+}
+
+// a track through a module spanning phi = +-pi, its hits are measured on either side of it
+#[test]
+fn disc_residuals_across_pi() {
+    let discs = (1..6).map(|i| {
+            let translation = Trl3::new(0., 0., i as Real).to_homogeneous();
+            Disc::new(2., 10., PI, 0.5, translation, Mat4::identity()).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    // radial track along phi = pi
+    let theta = PI / 5.;
+    let hits = (1..6)
+        .map(|i| {
+            let r = 3. + theta.tan() * (i - 1) as Real;
+            let phi = if i % 2 == 0 {PI - 0.001} else {-PI + 0.001};
+            Vec2::new(r, phi)
+        })
+        .collect::<Vec<_>>();
+    let covariance = discs.iter().map(|_| Mat2::new(0.01, 0., 0., 0.0001)).collect::<Vec<_>>();
+
+    let seed = Vec5::new(hits[0].x, hits[0].y, PI, theta, 1.);
+    let result = linear::run(&P3::origin(), &covariance, &hits, &discs, Some(&seed));
+
+    for residual in result.pred.res_vec.iter().chain(&result.filt.res_vec).chain(&result.smth.res_vec) {
+        assert!(residual.norm() < 0.01, "{}", residual);
+    }
+    for state in result.filt.state_vec.iter().chain(&result.smth.state_vec) {
+        assert!((state[eLOC_1].cos() + 1.).abs() < 1e-4, "{}", state);
+        assert!((state[ePHI].cos() + 1.).abs() < 1e-4, "{}", state);
+    }
+}
@@ -0,0 +1,144 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{Error, SensorError};
+use krs::filter::extrapolation::{self, ExtrapolationOptions};
+use krs::filter::utils::Data;
+use krs::geometry::bounds::{BoundaryCheck, RectangleBounds, SurfaceBounds};
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::intersection::{Intersect, IntersectionStatus, NavigationDirection};
+use krs::geometry::material::{Material, MaterialSlab, SurfaceMaterial};
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::*;
+use krs::geometry::{GeometryId, Surface};
+
+/*
+
+    Tests for kalman_rs::filter::extrapolation
+
+*/
+
+const STEP: Real = 1e-6;
+
+fn plane(z: Real, normal: Vec3) -> SurfaceBuilder {
+    let placement = Placement::from_normal(P3::new(0., 0., z), &normal, Some(&Vec3::x())).unwrap();
+    SurfaceBuilder::new(placement).geometry_id(GeometryId::new(1, 1, z as u32))
+}
+
+fn rectangle(builder: SurfaceBuilder, half_length: Real) -> Surface {
+    builder.plane(SurfaceBounds::Rectangle(RectangleBounds::new(half_length, half_length))).unwrap()
+}
+
+fn slab() -> MaterialSlab {
+    MaterialSlab::new(Material::new(93.7, 465.2, 28.0855, 14., 2.329), 0.3)
+}
+
+fn covariance() -> Mat5 {
+    let a = Mat5::from_fn(|i, j| 0.1 * ((i * 3 + j) as Real).sin());
+    a * a.transpose() + Mat5::identity() * 0.01
+}
+
+// bound state on `target` of the straight track of the bound state `state` on `start`
+fn propagate(start: &Surface, target: &Surface, state: &Vec5) -> Vec5 {
+    let global = start.to_global(P3::new(state[eLOC_0], state[eLOC_1], 0.));
+    let direction = Vec3::new(state[ePHI].cos() * state[eTHETA].sin(), state[ePHI].sin() * state[eTHETA].sin(), state[eTHETA].cos());
+    let local = target.intersect(&global, &direction, NavigationDirection::Forward)[0].local;
+
+    Vec5::new(local.x, local.y, state[ePHI], state[eTHETA], state[eQOP])
+}
+
+#[test]
+fn extrapolation_to_target() {
+    let start = rectangle(plane(1., Vec3::new(0.1, 0., 1.)), 5.);
+    let target = rectangle(plane(20., Vec3::new(-0.2, 0.3, 1.)), 2.);
+    let state = Vec5::new(0.2, -0.1, 1.2, 0.05, 0.5);
+
+    let result = extrapolation::extrapolate(&start, &state, &covariance(), &target, &ExtrapolationOptions::default()).unwrap();
+    assert!((result.state_vec - propagate(&start, &target, &state)).norm() < 1e-12);
+    assert!((target.to_global(P3::new(result.local().x, result.local().y, 0.)) - result.global).norm() < 1e-12);
+    assert!(result.path_length > 18.);
+    assert!(result.inside() && (result.distance_to_boundary < 0.));
+
+    let mut jacobian = Mat5::zeros();
+    for i in 0..5 {
+        let mut step = Vec5::zeros();
+        step[i] = STEP;
+        jacobian.set_column(i, &((propagate(&start, &target, &(state + step)) - propagate(&start, &target, &(state - step))) / (2. * STEP)));
+    }
+    let expected = jacobian * covariance() * jacobian.transpose();
+    assert!((result.cov_mat - expected).abs().max() < 1e-7, "{} != {}", result.cov_mat, expected);
+
+    // a track missing the target is still propagated onto its plane
+    let missing = Vec5::new(0.2, -0.1, 1.2, 0.3, 0.5);
+    let result = extrapolation::extrapolate(&start, &missing, &covariance(), &target, &ExtrapolationOptions::default()).unwrap();
+    assert_eq!(result.status, IntersectionStatus::OutsideBounds);
+    assert!(result.distance_to_boundary > 0.);
+
+    // with a tolerance the track is close enough
+    let tolerant = ExtrapolationOptions {boundary_check: BoundaryCheck::Absolute(10.), ..Default::default()};
+    assert!(extrapolation::extrapolate(&start, &missing, &covariance(), &target, &tolerant).unwrap().inside());
+}
+
+#[test]
+fn backward_extrapolation() {
+    let start = rectangle(plane(10., Vec3::z()), 5.);
+    let target = rectangle(plane(0., Vec3::z()), 5.);
+    let state = Vec5::new(0.5, 0.5, 0.3, 0.2, 1.);
+
+    match extrapolation::extrapolate(&start, &state, &covariance(), &target, &ExtrapolationOptions::default()) {
+        Err(SensorError::InvalidDirection(_)) => {},
+        _ => panic!("the target is behind the track")
+    }
+
+    let backward = ExtrapolationOptions {navigation: NavigationDirection::Backward, ..Default::default()};
+    let result = extrapolation::extrapolate(&start, &state, &covariance(), &target, &backward).unwrap();
+    assert!((result.path_length - 10. / (0.2 as Real).cos()).abs() < 1e-12);
+
+    // going back and forth gives the starting covariance
+    let forward = extrapolation::extrapolate(&target, &result.state_vec, &result.cov_mat, &start, &ExtrapolationOptions::default()).unwrap();
+    assert!((forward.state_vec - state).norm() < 1e-12);
+    assert!((forward.cov_mat - covariance()).abs().max() < 1e-12);
+}
+
+#[test]
+fn multiple_scattering() {
+    let (phi, theta) = (0., 0.1);
+    let material = SurfaceMaterial::Homogeneous(slab());
+    let surfaces = (1..=3).map(|z| rectangle(plane(z as Real, Vec3::z()).material(material.clone()), 5.)).collect();
+    let geometry = TrackingGeometry::new(surfaces).unwrap();
+    let target = rectangle(plane(10., Vec3::z()), 10.);
+
+    // a perfectly known state on the first surface
+    let mut data = Data::new(vec![Vec5::new(0., 0., phi, theta, 2.)], vec![Mat5::zeros()], vec![Mat2::zeros()], vec![Vec2::zeros()]);
+    data.geometry_ids = vec![GeometryId::new(1, 1, 1)];
+
+    let without = extrapolation::extrapolate_track(&data, 0, &geometry, &target, &ExtrapolationOptions::default()).unwrap();
+    assert!(without.cov_mat.norm() < 1e-15);
+
+    let options = ExtrapolationOptions {multiple_scattering: true, ..Default::default()};
+    let with = extrapolation::extrapolate_track(&data, 0, &geometry, &target, &options).unwrap();
+    assert_eq!(with.state_vec, without.state_vec);
+
+    // only the surfaces at z = 2 and z = 3 scatter, seen at an angle theta
+    let angle = extrapolation::highland_angle(&slab().scaled(1. / theta.cos()), 2.);
+    let lever_arms = [8. / theta.cos(), 7. / theta.cos()];
+    let lever_arm_squared = lever_arms.iter().map(|l| l * l).sum::<Real>();
+
+    assert!((with.cov_mat[(eTHETA, eTHETA)] - 2. * angle * angle).abs() < 1e-15);
+    assert!((with.cov_mat[(eLOC_1, eLOC_1)] - lever_arm_squared * angle * angle).abs() < 1e-12);
+    assert!((with.cov_mat[(eLOC_0, eLOC_0)] - lever_arm_squared * angle * angle / theta.cos().powi(2)).abs() < 1e-12);
+
+    // higher momentum scatters less
+    assert!(extrapolation::highland_angle(&slab(), 0.5) < extrapolation::highland_angle(&slab(), 2.));
+
+    data.geometry_ids = vec![GeometryId::new(9, 9, 9)];
+    match extrapolation::extrapolate_track(&data, 0, &geometry, &target, &options) {
+        Err(Error::Sensor(SensorError::UnknownGeometryId(_))) => {},
+        _ => panic!("the state is on a surface that is not in the geometry")
+    }
+
+    data.geometry_ids.clear();
+    match extrapolation::extrapolate_track(&data, 0, &geometry, &target, &options) {
+        Err(Error::Sensor(SensorError::MissingGeometryId(0))) => {},
+        _ => panic!("the state has no id")
+    }
+}
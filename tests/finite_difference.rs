@@ -0,0 +1,91 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::filter::finite_difference::{self, FiniteDifferenceOptions, JacobianComparison};
+use krs::filter::linear;
+use krs::geometry::intersection::NavigationDirection;
+use krs::geometry::Rectangle;
+
+/*
+
+    Tests for the numerical transport jacobians in kalman_rs::filter::finite_difference
+
+*/
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    Rectangle::new(10., 10., Mat4::new_translation(&Vec3::new(0., 0., z)), Mat4::identity()).unwrap()
+}
+
+#[test]
+fn numerical_linear_jacobian() {
+    // parallel planes 5 apart, loc0 moves with 5 tan(theta) cos(phi)
+    let start = initialize_rect(0.);
+    let end = initialize_rect(5.);
+    let (phi, theta) = (0.4, 0.3);
+    let state_vec = Vec5::new(0.5, -0.3, phi, theta, 1.);
+
+    let comparison = finite_difference::check_linear(&state_vec, &start, &end, NavigationDirection::Forward, &FiniteDifferenceOptions::default()).unwrap();
+    let numerical = comparison.numerical;
+
+    let mut expected = Mat5::identity();
+    expected[(eLOC_0, ePHI)] = -5. * theta.tan() * phi.sin();
+    expected[(eLOC_1, ePHI)] = 5. * theta.tan() * phi.cos();
+    expected[(eLOC_0, eTHETA)] = 5. * phi.cos() / theta.cos().powi(2);
+    expected[(eLOC_1, eTHETA)] = 5. * phi.sin() / theta.cos().powi(2);
+    assert!((numerical - expected).abs().max() < 1e-6, "{}", numerical);
+}
+
+#[test]
+fn steps_per_parameter() {
+    // loc1 only depends on q/p, with a curvature the step of q/p has to be small
+    let propagator = |state_vec: &Vec5| Ok(Vec5::new(state_vec[eLOC_0], 1000. * state_vec[eQOP].powi(2), state_vec[ePHI], state_vec[eTHETA], state_vec[eQOP]));
+    let state_vec = Vec5::new(0., 0., 0., 1., 0.5);
+
+    let jacobian = finite_difference::transport_jacobian(propagator, &state_vec, &Vec5::new(1., 1., 1., 1., 1e-6)).unwrap();
+    let mut expected = Mat5::identity();
+    expected[(eLOC_1, eLOC_1)] = 0.;
+    expected[(eLOC_1, eQOP)] = 1000.;
+    assert!((jacobian - expected).abs().max() < 1e-5, "{}", jacobian);
+
+    // phi differences across pi are wrapped
+    let wrapping = |state_vec: &Vec5| Ok(Vec5::new(state_vec[eLOC_0], state_vec[eLOC_1], krs::filter::angles::wrap_phi(state_vec[ePHI] + 0.5), state_vec[eTHETA], state_vec[eQOP]));
+    let jacobian = finite_difference::transport_jacobian(wrapping, &Vec5::new(0., 0., PI - 0.5, 1., 1.), &Vec5::repeat(1e-6)).unwrap();
+    assert!((jacobian - Mat5::identity()).abs().max() < 1e-9, "{}", jacobian);
+}
+
+#[test]
+fn report_of_wrong_jacobian() {
+    let mut analytic = Mat5::identity();
+    analytic[(eLOC_0, eTHETA)] = 2.;
+    let mut numerical = Mat5::identity();
+    numerical[(eLOC_0, eTHETA)] = 3.;
+
+    let comparison = JacobianComparison::new(analytic, numerical, 1e-5);
+    assert!(!comparison.agrees());
+    assert_eq!(comparison.worst_element(), (1. / 3., (eLOC_0, eTHETA)));
+    assert_eq!(comparison.disagreements(), vec![(eLOC_0, eTHETA, 2., 3.)]);
+
+    let report = comparison.to_string();
+    assert!(report.contains("d loc0 / d theta: analytic 2, numerical 3"), "{}", report);
+
+    assert!(JacobianComparison::new(numerical, numerical, 0.).agrees());
+}
+
+#[test]
+fn fitter_debug_mode() {
+    let sensors = (0..5).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+    let hits = (0..5).map(|i| Vec2::new(0.1 * i as Real, -0.05 * i as Real)).collect::<Vec<_>>();
+    // the seed points from the start location along the hits, not along the z axis
+    let start = P3::new(-0.1, 0.05, -1.);
+    let covariance = (0..5).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+
+    let result = linear::run_with_options(&start, &covariance, &hits, &sensors, None, &linear::FitterOptions::default()).unwrap();
+    assert!(result.jacobian_checks.is_empty());
+
+    let options = linear::FitterOptions {jacobian_check: Some(Default::default()), ..Default::default()};
+    let checked = linear::run_with_options(&start, &covariance, &hits, &sensors, None, &options).unwrap();
+
+    // one comparison per prediction, the fit itself is unchanged
+    assert_eq!(checked.jacobian_checks.len(), sensors.len() - 1);
+    assert_eq!(checked.filt.state_vec, result.filt.state_vec);
+}
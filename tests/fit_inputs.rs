@@ -0,0 +1,54 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::FilterError;
+use krs::filter::linear;
+use krs::geometry::Rectangle;
+
+/*
+
+    Tests for the input checks of the linear filter: mismatched input lengths and
+    tracks with too few measurements are returned as errors
+
+*/
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    Rectangle::new(10., 10., Mat4::new_translation(&Vec3::new(0., 0., z)), Mat4::identity()).unwrap()
+}
+
+fn inputs(count: usize) -> (Vec<Mat2>, Vec<Vec2>, Vec<Rectangle>) {
+    let covariance = (0..count).map(|_| Mat2::identity() * 1e-6).collect();
+    let hits = (0..count).map(|_| Vec2::zeros()).collect();
+    let sensors = (0..count).map(|z| initialize_rect((z + 1) as Real)).collect();
+
+    (covariance, hits, sensors)
+}
+
+#[test]
+fn mismatched_lengths() {
+    let (covariance, hits, sensors) = inputs(3);
+    let seed = Vec5::new(0., 0., 0., 0.3, 1.);
+
+    let result = linear::run_with_options(&P3::origin(), &covariance[..2].to_vec(), &hits, &sensors, Some(&seed), &Default::default());
+    assert!(matches!(result, Err(FilterError::LengthMismatch)));
+
+    let result = linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors[..2].to_vec(), Some(&seed), &Default::default());
+    assert!(matches!(result, Err(FilterError::LengthMismatch)));
+}
+
+#[test]
+fn single_measurement() {
+    let (covariance, hits, sensors) = inputs(1);
+    let seed = Vec5::new(0., 0., 0., 0.3, 1.);
+
+    let result = linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, Some(&seed), &Default::default());
+    assert!(matches!(result, Err(FilterError::NotEnoughMeasurements)));
+}
+
+#[test]
+fn no_measurements() {
+    let (covariance, hits, sensors) = inputs(0);
+
+    let result = linear::run_with_options(&P3::origin(), &covariance, &hits, &sensors, None, &Default::default());
+    assert!(matches!(result, Err(FilterError::NotEnoughMeasurements)));
+}
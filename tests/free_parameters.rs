@@ -0,0 +1,81 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::SensorError;
+use krs::filter::parameters::FreeParameters;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::traits::*;
+use krs::geometry::Surface;
+
+/*
+
+    Tests for kalman_rs::filter::parameters::FreeParameters
+
+*/
+
+fn sensor() -> Surface {
+    let placement = Placement::from_normal(P3::new(1., -2., 5.), &Vec3::new(0.3, 0.1, 1.), Some(&Vec3::x())).unwrap();
+    SurfaceBuilder::new(placement).plane(SurfaceBounds::Rectangle(RectangleBounds::new(10., 10.))).unwrap()
+}
+
+fn covariance() -> Mat5 {
+    let a = Mat5::from_fn(|i, j| 0.1 * ((i * 7 + j) as Real).cos());
+    a * a.transpose() + Mat5::identity() * 0.01
+}
+
+#[test]
+fn physical_quantities() {
+    let direction = Vec3::new(3., 4., 0.);
+    let parameters = FreeParameters::new(P3::new(1., 2., 3.), 4., &direction, 0.25, None);
+
+    assert_eq!(parameters.position(), P3::new(1., 2., 3.));
+    assert_eq!(parameters.time(), 4.);
+    assert!((parameters.direction() - Vec3::new(0.6, 0.8, 0.)).norm() < 1e-15);
+    assert_eq!(parameters.vector()[eFREE_QOP], 0.25);
+    assert!(parameters.covariance().is_none());
+
+    assert_eq!(parameters.charge(), 1.);
+    assert!((parameters.momentum() - 4.).abs() < 1e-12);
+    assert!((parameters.momentum_vector() - Vec3::new(2.4, 3.2, 0.)).norm() < 1e-12);
+    assert!((parameters.transverse_momentum() - 4.).abs() < 1e-12);
+    assert!((parameters.phi() - (4. as Real).atan2(3.)).abs() < 1e-12);
+    assert!((parameters.theta() - PI / 2.).abs() < 1e-12);
+    assert!(parameters.eta().abs() < 1e-12);
+
+    // backward tracks have negative eta, along the beam axis eta is infinite
+    let backward = FreeParameters::new(P3::origin(), 0., &Vec3::new(0.1, 0., -1.), -1., None);
+    assert!(backward.eta() < 0.);
+    assert_eq!(backward.charge(), -1.);
+    assert!(FreeParameters::new(P3::origin(), 0., &Vec3::z(), 1., None).eta().is_infinite());
+}
+
+#[test]
+fn bound_conversions() {
+    let sensor = sensor();
+    let state = Vec5::new(0.5, -1.5, -2.5, 0.4, -0.5);
+
+    let free = FreeParameters::from_bound(&sensor, &state, Some(&covariance()));
+    assert!((free.position() - sensor.to_global(P3::new(0.5, -1.5, 0.))).norm() < 1e-12);
+    assert!((free.phi() - state[ePHI]).abs() < 1e-12);
+    assert!((free.theta() - state[eTHETA]).abs() < 1e-12);
+    assert_eq!(free.time(), 0.);
+    assert_eq!(free.covariance().unwrap().row(eFREE_TIME).norm(), 0.);
+
+    let (bound, bound_covariance) = free.to_bound(&sensor).unwrap();
+    assert!((bound - state).norm() < 1e-12);
+    assert!((bound_covariance.unwrap() - covariance()).abs().max() < 1e-12);
+
+    // the same point on another surface
+    let from_vector = FreeParameters::from_vector(*free.vector(), None);
+    let placement = Placement::from_normal(free.position(), &Vec3::new(-0.2, 0.2, 1.), None).unwrap();
+    let other = SurfaceBuilder::new(placement).plane(SurfaceBounds::Rectangle(RectangleBounds::new(10., 10.))).unwrap();
+    let (other_state, other_covariance) = from_vector.to_bound(&other).unwrap();
+    assert!(other_state.fixed_rows::<U2>(eLOC_0).norm() < 1e-12);
+    assert!(other_covariance.is_none());
+
+    let away = FreeParameters::new(P3::new(0., 0., 50.), 0., &Vec3::z(), 1., None);
+    match away.to_bound(&sensor) {
+        Err(SensorError::NotOnSurface(position)) => assert_eq!(position, P3::new(0., 0., 50.)),
+        _ => panic!("the parameters are not on the sensor")
+    }
+}
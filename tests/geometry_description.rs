@@ -0,0 +1,160 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{GeometryError, SensorError};
+use krs::geometry::description::*;
+use krs::geometry::traits::{HasMaterial, Plane, Transform};
+use krs::geometry::{GeometryId, Surface};
+
+/*
+
+    Tests for loading / writing detector descriptions (kalman_rs::geometry::description)
+
+*/
+
+const DETECTOR_JSON: &str = r#"
+{
+    "surfaces": [
+        {
+            "id": {"volume": 1, "layer": 1, "sensitive": 1},
+            "placement": {"kind": "transform", "translation": [0, 0, 10], "rotation": [[1, 0, 0], [0, 1, 0], [0, 0, 1]]},
+            "bounds": {"type": "rectangle", "half_x": 2, "half_y": 1},
+            "material": {"thickness": 0.3, "radiation_length": 93.7, "interaction_length": 465.2}
+        },
+        {
+            "id": {"volume": 1, "layer": 1, "sensitive": 2},
+            "placement": {"kind": "axes", "center": [5, 0, 0], "normal": [1, 0, 0], "local_x": [0, 1, 0]},
+            "bounds": {"type": "trapezoid", "half_x_neg_y": 3, "half_x_pos_y": 1, "half_y": 2}
+        },
+        {
+            "id": {"volume": 1, "layer": 2, "sensitive": 3},
+            "placement": {"kind": "axes", "center": [0, 0, 20], "normal": [0, 0, 1], "local_x": [1, 0, 0]},
+            "bounds": {"type": "disc", "r_min": 5, "r_max": 10, "average_phi": 0, "half_phi": 0.4}
+        },
+        {
+            "id": {"volume": 1, "layer": 2, "sensitive": 4},
+            "placement": {"kind": "axes", "center": [0, 0, 30], "normal": [0, 0, 1], "local_x": [1, 0, 0]},
+            "bounds": {"type": "convex_polygon", "vertices": [[-1, -1], [1, -1], [0, 1]]}
+        },
+        {
+            "id": {"volume": 1, "layer": 3, "sensitive": 5},
+            "placement": {"kind": "axes", "center": [0, 0, 40], "normal": [0, 0, 1], "local_x": [1, 0, 0]},
+            "bounds": {"type": "diamond", "half_x_neg_y": 1, "half_x_zero_y": 3, "half_x_pos_y": 2, "half_y_neg": 2, "half_y_pos": 4}
+        }
+    ]
+}
+"#;
+
+fn id(sensitive: u32) -> GeometryId {
+    GeometryId::new(1, ((sensitive + 1) / 2) as u16, sensitive)
+}
+
+fn assert_close(left: Real, right: Real) {
+    dbg!{left}; dbg!{right};
+    assert!((left - right).abs() < DOT_PRODUCT_EPSILON)
+}
+
+#[test]
+fn build_from_json() {
+    let detector = DetectorDescription::from_json(DETECTOR_JSON).unwrap().build().unwrap();
+
+    assert_eq!(detector.elements.len(), 5);
+
+    let first = detector.element(id(1)).unwrap();
+    assert_close(first.surface.material_at(&P2::origin()).unwrap().thickness, 0.3);
+    assert_close(first.surface.global_center().z, 10.);
+    assert!(first.surface.inside(&P2::new(1.9, 0.9)));
+
+    match &detector.element(id(3)).unwrap().surface {
+        Surface::Disc(disc) => assert_close(disc.r_max(), 10.),
+        _ => panic!("surface 3 should be a disc")
+    }
+}
+
+// the local axes of an `axes` placement follow the given normal / local x
+#[test]
+fn axes_placement() {
+    let detector = DetectorDescription::from_json(DETECTOR_JSON).unwrap().build().unwrap();
+    let sensor = &detector.element(id(2)).unwrap().surface;
+
+    let normal = sensor.plane_normal_vec();
+    assert_close(normal.x, 1.);
+
+    // local x is global y, local y is normal x local x = global z
+    let global = sensor.to_global(P3::new(1., 2., 0.));
+    assert_close(global.x, 5.);
+    assert_close(global.y, 1.);
+    assert_close(global.z, 2.);
+
+    let local = sensor.to_local(global);
+    assert_close(local.x, 1.);
+    assert_close(local.y, 2.);
+}
+
+#[test]
+fn json_round_trip() {
+    let detector = DetectorDescription::from_json(DETECTOR_JSON).unwrap().build().unwrap();
+
+    let written = detector.description().to_json().unwrap();
+    let rebuilt = DetectorDescription::from_json(&written).unwrap().build().unwrap();
+
+    // writing the rebuilt detector again does not change anything
+    assert_eq!(detector.description(), rebuilt.description());
+}
+
+#[test]
+fn toml_round_trip() {
+    let description = DetectorDescription::from_json(DETECTOR_JSON).unwrap();
+
+    let toml = description.to_toml().unwrap();
+    let from_toml = DetectorDescription::from_toml(&toml).unwrap();
+
+    assert_eq!(description, from_toml);
+}
+
+#[test]
+fn save_and_load() {
+    let detector = DetectorDescription::from_json(DETECTOR_JSON).unwrap().build().unwrap();
+
+    let folder = std::env::temp_dir();
+    let path = folder.join("kalman_rs_geometry_description.toml");
+
+    detector.save(&path).unwrap();
+    let loaded = Detector::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(detector.description(), loaded.description());
+
+    match detector.save(folder.join("detector.txt")) {
+        Err(GeometryError::UnknownFormat(_)) => (),
+        _ => panic!("unknown file extensions should not be written")
+    }
+}
+
+#[test]
+fn invalid_descriptions() {
+    let mut description = DetectorDescription::from_json(DETECTOR_JSON).unwrap();
+
+    // duplicate ids
+    description.surfaces[1].id = id(1);
+    assert!(description.build().is_err());
+
+    // local x parallel to the normal
+    description.surfaces[1].id = id(2);
+    description.surfaces[1].placement = PlacementDescription::Axes{
+        center: [0., 0., 0.], normal: [1., 0., 0.], local_x: [2., 0., 0.]
+    };
+    match description.build() {
+        Err(GeometryError::InvalidPlacement(_)) => (),
+        _ => panic!("placement should be invalid")
+    }
+
+    // inner radius beyond the outer one
+    let mut description = DetectorDescription::from_json(DETECTOR_JSON).unwrap();
+    description.surfaces[2].bounds = BoundsDescription::Disc{r_min: 10., r_max: 5., average_phi: 0., half_phi: 0.4};
+    match description.build() {
+        Err(GeometryError::Sensor(SensorError::InvalidBounds(_))) => (),
+        _ => panic!("disc bounds should be invalid")
+    }
+
+    assert!(DetectorDescription::from_json("{\"surfaces\": [{\"id\": {\"volume\": 1, \"layer\": 1, \"sensitive\": 1}}]}").is_err());
+}
@@ -0,0 +1,134 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{FilterError, SensorError};
+use krs::filter::linear;
+use krs::filter::measurement::Measurement;
+use krs::generate_data::setup::generate_track;
+use krs::geometry::traits::Identified;
+use krs::geometry::{GeometryId, Rectangle, Straw};
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use rand_distr::Normal;
+
+/*
+
+    Tests for kalman_rs::geometry::GeometryId and fitting measurements that reference
+    their sensor by id
+
+*/
+
+// 2 x 2 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real, id: GeometryId) -> Rectangle {
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., z));
+    let mut sensor = Rectangle::new(2., 2., translation, Mat4::identity()).unwrap();
+    sensor.set_geometry_id(id);
+    sensor
+}
+
+#[test]
+fn id_packing() {
+    let id = GeometryId::new(65535, 12, 4_000_000_000);
+
+    assert_eq!(id.volume(), 65535);
+    assert_eq!(id.layer(), 12);
+    assert_eq!(id.sensitive(), 4_000_000_000);
+
+    let moved = id.with_layer(3).with_volume(1);
+    assert_eq!((moved.volume(), moved.layer(), moved.sensitive()), (1, 3, 4_000_000_000));
+
+    assert_eq!(id.layer_id(), GeometryId::new(65535, 12, 0));
+    assert_eq!(id.volume_id(), GeometryId::new(65535, 0, 0));
+    assert_eq!(format!("{}", GeometryId::new(1, 2, 3)), "vol=1|lay=2|sen=3");
+}
+
+// sorting groups surfaces by volume, then by layer
+#[test]
+fn id_ordering() {
+    let mut ids = vec![
+        GeometryId::new(2, 1, 1), GeometryId::new(1, 2, 0), GeometryId::new(1, 1, 7), GeometryId::new(1, 2, 3)
+    ];
+    ids.sort();
+
+    assert_eq!(ids, vec![
+        GeometryId::new(1, 1, 7), GeometryId::new(1, 2, 0), GeometryId::new(1, 2, 3), GeometryId::new(2, 1, 1)
+    ]);
+
+    assert!(ids[1].same_layer(&ids[2]));
+    assert!(!ids[0].same_layer(&ids[1]));
+}
+
+#[test]
+fn sensor_ids() {
+    let id = GeometryId::new(1, 2, 3);
+
+    let rect = initialize_rect(0., id);
+    assert_eq!(rect.geometry_id(), id);
+
+    let mut straw = Straw::new(P3::origin(), Vec3::new(0., 0., 1.), 1., 1.).unwrap();
+    assert_eq!(straw.geometry_id(), GeometryId::default());
+    straw.set_geometry_id(id);
+    assert_eq!(straw.geometry_id(), id);
+}
+
+// measurements can be matched to sensors given in any order
+#[test]
+fn fit_measurements_by_id() {
+    let ids = (1..=4).map(|layer| GeometryId::new(1, layer, 1)).collect::<Vec<_>>();
+
+    let mut sensors = ids.iter()
+        .enumerate()
+        .map(|(i, id)| initialize_rect(i as Real, *id))
+        .collect::<Vec<_>>();
+    sensors.reverse();
+
+    let slope = (0.1 as Real).tan();
+    let measurements = ids.iter()
+        .enumerate()
+        .map(|(i, id)| Measurement::new(*id, Vec2::new(slope * (i as Real), 0.), Mat2::identity() * 0.0001))
+        .collect::<Vec<_>>();
+
+    let seed = Vec5::new(0., 0., 0., 0.1, 1.);
+    let options = linear::FitterOptions::default();
+
+    let result = linear::run_measurements(&P3::origin(), &measurements, &sensors, Some(&seed), &options).unwrap();
+
+    assert_eq!(result.smth.geometry_ids, ids);
+    assert_eq!(result.filt.geometry_ids, ids);
+
+    let index = result.filt.index_of(ids[2]).unwrap();
+    assert!((result.filt.state_vec[index][eLOC_0] - (2. * slope)).abs() < 0.01);
+}
+
+#[test]
+fn fit_unknown_id() {
+    let sensors = vec![initialize_rect(0., GeometryId::new(1, 1, 1)), initialize_rect(1., GeometryId::new(1, 2, 1))];
+
+    let missing = GeometryId::new(1, 3, 1);
+    let measurements = vec![
+        Measurement::new(GeometryId::new(1, 1, 1), Vec2::zeros(), Mat2::identity()),
+        Measurement::new(missing, Vec2::zeros(), Mat2::identity())
+    ];
+
+    match linear::run_measurements(&P3::origin(), &measurements, &sensors, None, &linear::FitterOptions::default()) {
+        Err(FilterError::Sensor(SensorError::UnknownGeometryId(id))) => assert_eq!(id, missing),
+        _ => panic!("measurement on an unknown sensor should not be fitted")
+    }
+}
+
+#[test]
+fn generated_track_ids() {
+    let data = generate_track(
+        4, 1., Some((0., PI/2.)), SmallRng::seed_from_u64(31), 0.01,
+        Normal::new(3., 1.5).unwrap(), Normal::new(0., 1.).unwrap()
+    );
+
+    // every generated sensor is a layer of its own
+    let ids = data.sensors.iter().map(|sensor| sensor.geometry_id()).collect::<Vec<_>>();
+    assert_eq!(ids, (1..=4).map(|layer| GeometryId::new(1, layer, 1)).collect::<Vec<_>>());
+
+    let fit = linear::run(&data.start, &data.cov, &data.smear_hits, &data.sensors, None);
+    for (i, id) in ids.iter().enumerate() {
+        assert_eq!(fit.smth.index_of(*id), Some(i));
+    }
+}
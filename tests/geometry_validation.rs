@@ -0,0 +1,130 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::Identified;
+use krs::geometry::validation::{check_transform, validate, Finding, ValidationOptions};
+use krs::geometry::{GeometryId, PlaneSurface, Rectangle, Surface};
+
+/*
+
+    Tests for kalman_rs::geometry::validation
+
+*/
+
+fn initialize_plane(center: Vec3, rotation: Mat4, id: GeometryId) -> PlaneSurface<SurfaceBounds> {
+    let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    let mut surface = PlaneSurface::new(bounds, Mat4::new_translation(&center), rotation).unwrap();
+    surface.set_geometry_id(id);
+    surface
+}
+
+// 2 x 2 square parallel to the x-y plane
+fn initialize_square(x: Real, z: Real, id: GeometryId) -> Surface {
+    Surface::Plane(initialize_plane(Vec3::new(x, 0., z), Mat4::identity(), id))
+}
+
+fn ids(sensitive: u32) -> GeometryId {
+    GeometryId::new(1, 1, sensitive)
+}
+
+#[test]
+fn valid_geometry() {
+    // two layers of three modules touching along their edges
+    let surfaces = (1..=2)
+        .flat_map(|layer| (0..3).map(move |i| {
+            initialize_square(2. * (i as Real), layer as Real, GeometryId::new(1, layer, (i + 1) as u32))
+        }))
+        .collect::<Vec<_>>();
+
+    let geometry = TrackingGeometry::new(surfaces).unwrap();
+    let report = validate(&geometry, &ValidationOptions::default());
+
+    assert!(report.is_valid(), "{}", report);
+}
+
+#[test]
+fn coplanar_overlap() {
+    let geometry = TrackingGeometry::new(vec![initialize_square(0., 0., ids(1)), initialize_square(1.5, 0., ids(2))]).unwrap();
+    let report = validate(&geometry, &ValidationOptions::default());
+
+    assert_eq!(report.len(), 2);
+
+    match report.findings(ids(1)) {
+        [Finding::Overlap{other, depth}] => {
+            assert_eq!(*other, ids(2));
+            // the shared strip is 0.5 wide
+            assert!((depth - 0.25).abs() < 0.05);
+        },
+        findings => panic!("expected a single overlap, found {:?}", findings)
+    }
+    assert!(matches!(report.findings(ids(2)), [Finding::Overlap{..}]));
+}
+
+#[test]
+fn crossing_overlap() {
+    // perpendicular module going through the first one, shifted along y
+    let rotation = Mat4::from_axis_angle(&Vec3::y_axis(), PI / 2.);
+    let crossing = Surface::Plane(initialize_plane(Vec3::new(0., 0.5, 0.), rotation, GeometryId::new(1, 2, 1)));
+
+    let geometry = TrackingGeometry::new(vec![initialize_square(0., 0., ids(1)), crossing]).unwrap();
+    let report = validate(&geometry, &ValidationOptions::default());
+
+    match report.findings(ids(1)) {
+        [Finding::Overlap{other, depth}] => {
+            assert_eq!(*other, GeometryId::new(1, 2, 1));
+            assert!((depth - 0.5).abs() < 1e-9);
+        },
+        findings => panic!("expected a single overlap, found {:?}", findings)
+    }
+}
+
+#[test]
+fn layer_gaps() {
+    // 0.2 between the first two modules, the third one touches the second one
+    let geometry = TrackingGeometry::new(vec![
+        initialize_square(0., 0., ids(1)), initialize_square(2.2, 0., ids(2)), initialize_square(4.2, 0., ids(3))
+    ]).unwrap();
+
+    let report = validate(&geometry, &ValidationOptions::default());
+
+    assert_eq!(report.len(), 2);
+    assert!(report.findings(ids(3)).is_empty());
+
+    match report.findings(ids(1)) {
+        [Finding::Gap{other, width}] => {
+            assert_eq!(*other, ids(2));
+            assert!((width - 0.2).abs() < 1e-6);
+        },
+        findings => panic!("expected a single gap, found {:?}", findings)
+    }
+
+    // wider gaps are taken as the edge of the layer
+    let options = ValidationOptions {max_gap: 0.1, ..ValidationOptions::default()};
+    assert!(validate(&geometry, &options).is_valid());
+}
+
+#[test]
+fn broken_transforms() {
+    // to_local no longer undoes the translation
+    let mut moved = initialize_plane(Vec3::new(1., 0., 0.), Mat4::identity(), ids(1));
+    moved.to_local = Aff3::identity();
+    assert!(matches!(check_transform(&moved, 1e-9).as_slice(), [Finding::TransformNotInverse{..}]));
+
+    let scaling = Mat4::new_nonuniform_scaling(&Vec3::new(1., 2., 1.));
+    let stretched = initialize_plane(Vec3::zeros(), scaling, ids(2));
+    assert!(matches!(check_transform(&stretched, 1e-9).as_slice(), [Finding::RotationNotOrthonormal{..}]));
+
+    // the normal of a rectangle is not rotated with the sensor
+    let rotation = Mat4::from_axis_angle(&Vec3::x_axis(), 0.3);
+    let rect = Rectangle::new(2., 2., Mat4::identity(), rotation).unwrap();
+    match check_transform(&rect, 1e-9).as_slice() {
+        [Finding::NormalMismatch{angle}] => assert!((angle - 0.3).abs() < 1e-9),
+        findings => panic!("expected a normal mismatch, found {:?}", findings)
+    }
+
+    let geometry = TrackingGeometry::new(vec![Surface::Plane(moved), Surface::Plane(stretched)]).unwrap();
+    let report = validate(&geometry, &ValidationOptions::default());
+
+    assert!(format!("{}", report).contains("vol=1|lay=1|sen=1: to_local is not the inverse of to_global"));
+}
@@ -0,0 +1,99 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::SensorError;
+use krs::filter::prediction;
+use krs::geometry::bounds::{BoundaryCheck, RectangleBounds, SurfaceBounds};
+use krs::geometry::bvh::Helix;
+use krs::geometry::intersection::*;
+use krs::geometry::traits::*;
+use krs::geometry::{Disc, PlaneSurface, Rectangle};
+
+/*
+
+    Tests for kalman_rs::geometry::intersection
+
+*/
+
+fn tilted_plane() -> PlaneSurface<SurfaceBounds> {
+    let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., 2.));
+    let rotation = Mat4::from_axis_angle(&Vec3::y_axis(), 0.5);
+
+    PlaneSurface::new(bounds, translation, rotation).unwrap()
+}
+
+#[test]
+fn straight_intersections() {
+    let plane = tilted_plane();
+    let direction = Vec3::new(0.1, 0., 1.);
+
+    let intersections = plane.intersect(&P3::origin(), &direction, NavigationDirection::Forward);
+    assert_eq!(intersections.len(), 1);
+
+    let intersection = &intersections[0];
+    assert!(intersection.is_valid());
+    assert!((intersection.position - (direction.normalize() * intersection.path_length)).coords.norm() < 1e-12);
+    assert!((plane.to_local(intersection.position) - intersection.local).norm() < 1e-12);
+
+    // the same crossing seen from behind the plane
+    let start = P3::new(0.2, 0., 4.);
+    let forward = plane.intersect(&start, &direction, NavigationDirection::Forward);
+    let backward = plane.intersect(&start, &direction, NavigationDirection::Backward);
+    assert!(forward[0].path_length < 0.);
+    assert!((forward[0].path_length + backward[0].path_length).abs() < 1e-12);
+    assert!((forward[0].position - backward[0].position).norm() < 1e-12);
+    assert!(nearest_valid(&forward).is_none());
+    assert!(nearest_valid(&backward).is_some());
+
+    // the plane is hit outside of its bounds
+    let outside = plane.intersect(&P3::new(3., 0., 0.), &direction, NavigationDirection::Forward);
+    assert_eq!(outside[0].status, IntersectionStatus::OutsideBounds);
+    let tolerant = plane.intersect_with_check(&P3::new(3., 0., 0.), &direction, NavigationDirection::Forward, &BoundaryCheck::Absolute(10.));
+    assert_eq!(tolerant[0].status, IntersectionStatus::Inside);
+
+    // parallel to the plane
+    let normal = *plane.plane_normal_vec();
+    assert!(plane.intersect(&P3::origin(), &normal.cross(&Vec3::y()), NavigationDirection::Forward).is_empty());
+}
+
+#[test]
+fn helix_intersections() {
+    let disc = Disc::new(0.5, 10., 0., PI, Mat4::new_translation(&Vec3::new(0., 0., 3.)), Mat4::identity()).unwrap();
+
+    // a straight helix gives the same crossing as the line
+    let straight = Helix::new(P3::new(1., 0., 0.), Vec3::new(0.2, 0.1, 1.), Vec3::z(), Real::INFINITY);
+    let line = disc.intersect(&straight.position, &straight.direction, NavigationDirection::Forward);
+    let helix = disc.intersect_helix(&straight, 10., 0.5, NavigationDirection::Forward, &BoundaryCheck::Strict);
+    assert_eq!(helix.len(), 1);
+    assert!((helix[0].path_length - line[0].path_length).abs() < 1e-9);
+    assert!((helix[0].position - line[0].position).norm() < 1e-9);
+
+    // curling in a field along x the track crosses the plane of the disc twice
+    let curler = Helix::new(P3::new(1., 0., 0.), Vec3::z(), Vec3::x(), 4.);
+    let crossings = disc.intersect_helix(&curler, 10., 0.1, NavigationDirection::Forward, &BoundaryCheck::Strict);
+    assert_eq!(crossings.iter().filter(|i| i.path_length >= 0.).count(), 2);
+    for crossing in &crossings {
+        assert!((crossing.position.z - 3.).abs() < 1e-9);
+        assert!((curler.point(crossing.path_length) - crossing.position).norm() < 1e-9);
+    }
+
+    // moving backwards the path lengths change sign
+    let backward = disc.intersect_helix(&curler, 10., 0.1, NavigationDirection::Backward, &BoundaryCheck::Strict);
+    let first = nearest_valid(&crossings).unwrap();
+    assert!(backward.iter().any(|i| (i.path_length + first.path_length).abs() < 1e-9));
+}
+
+#[test]
+fn prediction_ignores_sensors_behind() {
+    let start = Rectangle::new(4., 4., Mat4::new_translation(&Vec3::new(0., 0., 5.)), Mat4::identity()).unwrap();
+    let behind = Rectangle::new(4., 4., Mat4::identity(), Mat4::identity()).unwrap();
+    let state = Vec5::new(0., 0., 0.3, 0.2, 1.);
+
+    match prediction::linear_state_vector(&start, &behind, &state) {
+        Err(SensorError::InvalidDirection(_)) => {},
+        other => panic!("a sensor behind the particle should not be hit: {:?}", other)
+    }
+
+    let (_, path_length) = prediction::linear_state_vector(&behind, &start, &state).unwrap();
+    assert!((path_length - 5. / (0.2 as Real).cos()).abs() < 1e-12);
+}
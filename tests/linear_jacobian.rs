@@ -0,0 +1,73 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::filter::finite_difference::{self, FiniteDifferenceOptions, JacobianComparison};
+use krs::filter::{jacobian, linear, prediction};
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::intersection::NavigationDirection;
+use krs::geometry::{Rectangle, Straw, Surface};
+
+/*
+
+    Tests for the analytic straight line transport jacobians in kalman_rs::filter::jacobian
+    against numerical ones
+
+*/
+
+// 10 x 10 plane at a given z, tilted by `normal`
+fn tilted_plane(z: Real, normal: Vec3) -> Surface {
+    let placement = Placement::from_normal(P3::new(0.2, -0.1, z), &normal, None).unwrap();
+    SurfaceBuilder::new(placement).plane(SurfaceBounds::Rectangle(RectangleBounds::new(10., 10.))).unwrap()
+}
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    Rectangle::new(10., 10., Mat4::new_translation(&Vec3::new(0., 0., z)), Mat4::identity()).unwrap()
+}
+
+#[test]
+fn linear_jacobian_agrees() {
+    let start = tilted_plane(0., Vec3::new(0.2, -0.1, 1.));
+    let end = tilted_plane(5., Vec3::new(-0.3, 0.2, 1.));
+    let options = FiniteDifferenceOptions::default();
+
+    for state_vec in &[Vec5::new(0.5, -0.3, 0.4, 0.3, 1.), Vec5::new(-1., 2., -2.9, 0.8, -0.5), Vec5::new(0., 0., PI, 0.1, 2.)] {
+        let comparison = finite_difference::check_linear(state_vec, &start, &end, NavigationDirection::Forward, &options).unwrap();
+        assert!(comparison.agrees(), "{}", comparison);
+    }
+
+    // against the momentum, from the far plane back to the first one
+    let state_vec = Vec5::new(0.5, -0.3, 0.4, 0.3, 1.);
+    let comparison = finite_difference::check_linear(&state_vec, &end, &start, NavigationDirection::Backward, &options).unwrap();
+    assert!(comparison.agrees(), "{}", comparison);
+}
+
+#[test]
+fn straw_jacobian_agrees() {
+    let start = Straw::new(P3::new(0., 0., 0.), Vec3::new(0., 0.3, 2.), 5., 100.).unwrap();
+    let end = Straw::new(P3::new(4., 0.5, 0.), Vec3::new(0.2, 0., 2.), 5., 100.).unwrap();
+    let state_vec = Vec5::new(0.3, 1., 0.2, 1.2, 1.);
+
+    let (_, distance) = prediction::linear_straw_state_vector(&start, &end, &state_vec).unwrap();
+    let analytic = jacobian::linear_straw(&state_vec, distance, &start, &end).unwrap();
+
+    let propagator = |state_vec: &Vec5| prediction::linear_straw_state_vector(&start, &end, state_vec).map(|(state_vec, _)| state_vec);
+    let numerical = finite_difference::transport_jacobian(propagator, &state_vec, &FiniteDifferenceOptions::default().steps).unwrap();
+
+    let comparison = JacobianComparison::new(analytic, numerical, 1e-5);
+    assert!(comparison.agrees(), "{}", comparison);
+}
+
+#[test]
+fn fitted_jacobians_agree() {
+    let sensors = (0..5).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+    let hits = (0..5).map(|i| Vec2::new(0.1 * i as Real, -0.05 * i as Real)).collect::<Vec<_>>();
+    // the seed points from the start location along the hits, not along the z axis
+    let start = P3::new(-0.1, 0.05, -1.);
+    let covariance = (0..5).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+
+    let options = linear::FitterOptions {jacobian_check: Some(Default::default()), ..Default::default()};
+    let result = linear::run_with_options(&start, &covariance, &hits, &sensors, None, &options).unwrap();
+
+    assert!(result.jacobian_checks.iter().all(JacobianComparison::agrees), "{:?}", result.jacobian_checks);
+}
@@ -0,0 +1,155 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::bvh::Helix;
+use krs::geometry::description::DetectorDescription;
+use krs::geometry::material::*;
+use krs::geometry::navigator::Navigator;
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::{HasMaterial, Identified};
+use krs::geometry::{GeometryId, PlaneSurface, Surface};
+
+/*
+
+    Tests for kalman_rs::geometry::material
+
+*/
+
+const MATERIAL_JSON: &str = r#"
+{
+    "surfaces": [
+        {
+            "id": {"volume": 1, "layer": 1, "sensitive": 1},
+            "placement": {"kind": "axes", "center": [0, 0, 1], "normal": [0, 0, 1], "local_x": [1, 0, 0]},
+            "bounds": {"type": "rectangle", "half_x": 1, "half_y": 1},
+            "material": {
+                "type": "homogeneous",
+                "thickness": 0.3,
+                "material": {"x0": 93.7, "l0": 465.2, "a": 28.0855, "z": 14, "density": 2.329}
+            }
+        },
+        {
+            "id": {"volume": 1, "layer": 2, "sensitive": 1},
+            "placement": {"kind": "axes", "center": [0, 0, 2], "normal": [0, 0, 1], "local_x": [1, 0, 0]},
+            "bounds": {"type": "disc", "r_min": 1, "r_max": 3, "average_phi": 0, "half_phi": 3.2},
+            "material": {
+                "type": "binned",
+                "axis_0": {"min": 1, "max": 3, "bins": 2},
+                "axis_1": {"min": -3.2, "max": 3.2, "bins": 1},
+                "slabs": [
+                    {"thickness": 0.1, "material": {"x0": 93.7, "l0": 465.2, "a": 28.0855, "z": 14, "density": 2.329}},
+                    {"thickness": 0.2, "material": {"x0": 93.7, "l0": 465.2, "a": 28.0855, "z": 14, "density": 2.329}}
+                ]
+            }
+        }
+    ]
+}
+"#;
+
+fn silicon() -> Material {
+    Material::new(93.7, 465.2, 28.0855, 14., 2.329)
+}
+
+fn assert_close(left: Real, right: Real) {
+    assert!((left - right).abs() < 1e-9, "{} != {}", left, right)
+}
+
+#[test]
+fn slab_thickness() {
+    let slab = MaterialSlab::new(silicon(), 0.3);
+
+    assert_close(slab.thickness_in_x0(), 0.3 / 93.7);
+    assert_close(slab.thickness_in_l0(), 0.3 / 465.2);
+    assert_close(slab.scaled(-2.).thickness, 0.6);
+}
+
+#[test]
+fn binned_lookup() {
+    let axis_0 = BinAxis {min: 0., max: 3., bins: 3};
+    let axis_1 = BinAxis {min: 0., max: 1., bins: 2};
+
+    assert_eq!(axis_0.bin(1.5), 1);
+    assert_eq!(axis_0.bin(-10.), 0);
+    assert_eq!(axis_0.bin(10.), 2);
+    assert_eq!(axis_0.bin(Real::NAN), 0);
+
+    let slabs = (0..6).map(|i| MaterialSlab::new(silicon(), i as Real)).collect::<Vec<_>>();
+    let map = BinnedMaterial::new(axis_0, axis_1, slabs.clone()).unwrap();
+
+    // axis 0 varies the fastest
+    assert_close(map.slab(&P2::new(2.5, 0.2)).thickness, 2.);
+    assert_close(map.slab(&P2::new(0.5, 0.7)).thickness, 3.);
+
+    assert!(BinnedMaterial::new(axis_0, axis_1, slabs[..5].to_vec()).is_err());
+    assert!(BinnedMaterial::new(BinAxis {min: 1., max: 1., bins: 1}, axis_1, slabs[..2].to_vec()).is_err());
+    assert!(BinnedMaterial::new(BinAxis {min: 0., max: 1., bins: 0}, axis_1, Vec::new()).is_err());
+}
+
+#[test]
+fn load_material() {
+    let description = DetectorDescription::from_json(MATERIAL_JSON).unwrap();
+    let detector = description.build().unwrap();
+
+    let plane = &detector.element(GeometryId::new(1, 1, 1)).unwrap().surface;
+    assert_eq!(plane.material_at(&P2::origin()), Some(&MaterialSlab::new(silicon(), 0.3)));
+
+    // the disc is binned in r
+    let disc = &detector.element(GeometryId::new(1, 2, 1)).unwrap().surface;
+    assert_close(disc.material_at(&P2::new(1.5, 0.)).unwrap().thickness, 0.1);
+    assert_close(disc.material_at(&P2::new(2.5, 3.)).unwrap().thickness, 0.2);
+
+    // written and read again without changes
+    let reloaded = DetectorDescription::from_toml(&detector.description().to_toml().unwrap()).unwrap();
+    assert_eq!(reloaded, DetectorDescription::from_json(&detector.description().to_json().unwrap()).unwrap());
+    assert_eq!(reloaded.build().unwrap().elements[1].surface.material(), disc.material());
+
+    // a map with the wrong number of slabs is not accepted
+    let broken = MATERIAL_JSON.replace(r#"{"min": 1, "max": 3, "bins": 2}"#, r#"{"min": 1, "max": 3, "bins": 3}"#);
+    assert!(DetectorDescription::from_json(&broken).is_err());
+}
+
+// material of a crossed surface is thickened by the incidence angle
+#[test]
+fn crossing_material() {
+    let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(10., 10.));
+    let mut surface = Surface::Plane(PlaneSurface::new(bounds, Mat4::new_translation(&Vec3::new(0., 0., 1.)), Mat4::identity()).unwrap());
+    surface.set_geometry_id(GeometryId::new(1, 1, 1));
+    surface.set_material(Some(SurfaceMaterial::Homogeneous(MaterialSlab::new(silicon(), 0.3))));
+
+    let bare = {
+        let mut bare = surface.clone();
+        bare.set_geometry_id(GeometryId::new(1, 2, 1));
+        bare.set_material(None);
+        bare
+    };
+
+    let geometry = TrackingGeometry::new(vec![surface, bare]).unwrap();
+    let navigator = Navigator::new(&geometry);
+
+    // 60 degrees from the normal
+    let direction = Vec3::new((PI / 3.).sin(), 0., (PI / 3.).cos());
+    let crossings = navigator.crossings(&P3::origin(), &direction);
+
+    assert_eq!(crossings.len(), 2);
+    assert_close(crossings[0].material.unwrap().thickness, 0.6);
+    assert!(crossings[1].material.is_none());
+
+    let helix = Helix::new(P3::origin(), direction, Vec3::new(0., 0., 1.), 20.);
+    let crossing = &navigator.helix_crossings(&helix, 5., 0.1)[0];
+    let cos_incidence = helix.direction(crossing.path_length).z;
+    assert_close(crossing.material.unwrap().thickness, 0.3 / cos_incidence);
+}
+
+#[test]
+fn helix_direction() {
+    let helix = Helix::new(P3::new(1., 2., 3.), Vec3::new(1., 0.5, 1.), Vec3::new(0., 0., 1.), 4.);
+    let h = 1e-6;
+
+    for s in &[0., 1., 7.5] {
+        let numerical = (helix.point(s + h) - helix.point(s - h)) / (2. * h);
+        let direction = helix.direction(*s);
+
+        assert_close(direction.norm(), 1.);
+        assert!((direction - numerical).norm() < 1e-8);
+    }
+}
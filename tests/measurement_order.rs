@@ -0,0 +1,57 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::filter::linear;
+use krs::geometry::Rectangle;
+
+/*
+
+    Regression tests for the pairing of predictions and measurements in the linear filter:
+    the prediction onto a sensor is filtered with the measurement of that sensor, and the
+    smoothed state of a sensor is compared to the prediction onto it
+
+*/
+
+const PHI: Real = 0.3;
+const THETA: Real = 0.3;
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    Rectangle::new(10., 10., Mat4::new_translation(&Vec3::new(0., 0., z)), Mat4::identity()).unwrap()
+}
+
+#[test]
+fn predictions_use_their_own_measurement() {
+    let z = (1..=5).map(|z| z as Real).collect::<Vec<_>>();
+    let sensors = z.iter().map(|z| initialize_rect(*z)).collect::<Vec<_>>();
+    let covariance = z.iter().map(|_| Mat2::identity() * 1e-6).collect::<Vec<_>>();
+
+    // the hits move by tan(theta) ~ 0.3 from one sensor to the next
+    let hits = z.iter().map(|z| Vec2::new(PHI.cos(), PHI.sin()) * THETA.tan() * (z - 1.)).collect::<Vec<_>>();
+
+    let seed = Vec5::new(0., 0., PHI, THETA, 1.);
+    let result = linear::run(&P3::origin(), &covariance, &hits, &sensors, Some(&seed));
+
+    for (state, hit) in result.filt.state_vec.iter().zip(&hits) {
+        let local = Vec2::new(state[eLOC_0], state[eLOC_1]);
+        assert!((local - hit).norm() < 1e-3, "{} != {}", local, hit);
+    }
+}
+
+#[test]
+fn smoothed_states_use_their_own_prediction() {
+    let z = (1..=5).map(|z| z as Real).collect::<Vec<_>>();
+    let sensors = z.iter().map(|z| initialize_rect(*z)).collect::<Vec<_>>();
+    let covariance = z.iter().map(|_| Mat2::identity() * 1e-6).collect::<Vec<_>>();
+    let hits = z.iter().map(|z| Vec2::new(PHI.cos(), PHI.sin()) * THETA.tan() * (z - 1.)).collect::<Vec<_>>();
+
+    let seed = Vec5::new(0., 0., PHI, THETA, 1.);
+    let result = linear::run(&P3::origin(), &covariance, &hits, &sensors, Some(&seed));
+
+    // the smoothing starts from the last filtered state
+    assert_eq!(result.smth.state_vec.last(), result.filt.state_vec.last());
+
+    for (state, hit) in result.smth.state_vec.iter().zip(&hits) {
+        let local = Vec2::new(state[eLOC_0], state[eLOC_1]);
+        assert!((local - hit).norm() < 1e-3, "{} != {}", local, hit);
+    }
+}
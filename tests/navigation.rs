@@ -0,0 +1,164 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{FilterError, GeometryError, SensorError};
+use krs::filter::linear;
+use krs::filter::measurement::Measurement;
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::navigator::Navigator;
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::Identified;
+use krs::geometry::{GeometryId, PlaneSurface, Surface};
+
+/*
+
+    Tests for kalman_rs::geometry::tracking_geometry and kalman_rs::geometry::navigator
+
+*/
+
+// 2 x 2 square parallel to the x-y plane centered at (x, 0, z)
+fn initialize_square(x: Real, z: Real, id: GeometryId) -> Surface {
+    let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(1., 1.));
+    let translation = Mat4::new_translation(&Vec3::new(x, 0., z));
+
+    let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, Mat4::identity()).unwrap());
+    surface.set_geometry_id(id);
+    surface
+}
+
+// two volumes of 3 layers along z, with two modules side by side in x on every layer
+fn initialize_geometry() -> TrackingGeometry {
+    let mut surfaces = Vec::new();
+
+    for volume in 1..=2 {
+        for layer in 1..=3 {
+            let z = ((volume - 1) * 3 + layer) as Real;
+            surfaces.push(initialize_square(-1., z, GeometryId::new(volume, layer, 1)));
+            surfaces.push(initialize_square(1., z, GeometryId::new(volume, layer, 2)));
+        }
+    }
+
+    // construction order does not matter
+    surfaces.reverse();
+
+    TrackingGeometry::new(surfaces).unwrap()
+}
+
+#[test]
+fn geometry_structure() {
+    let geometry = initialize_geometry();
+
+    assert_eq!(geometry.len(), 12);
+    assert_eq!(geometry.volumes().len(), 2);
+    assert_eq!(geometry.volumes()[0].layers().len(), 3);
+
+    let layer = geometry.layer(GeometryId::new(2, 3, 0)).unwrap();
+    assert_eq!(layer.surfaces().len(), 2);
+    assert_eq!(layer.surfaces()[0].geometry_id(), GeometryId::new(2, 3, 1));
+
+    assert!(geometry.surface(GeometryId::new(1, 2, 2)).is_some());
+    assert!(geometry.surface(GeometryId::new(3, 1, 1)).is_none());
+
+    // surfaces are visited in id order
+    let ids = geometry.surfaces().map(|s| s.geometry_id()).collect::<Vec<_>>();
+    let mut sorted = ids.clone();
+    sorted.sort();
+    assert_eq!(ids, sorted);
+}
+
+#[test]
+fn duplicate_ids() {
+    let id = GeometryId::new(1, 1, 1);
+    let surfaces = vec![initialize_square(0., 0., id), initialize_square(0., 1., id)];
+
+    match TrackingGeometry::new(surfaces) {
+        Err(GeometryError::DuplicateId(duplicate)) => assert_eq!(duplicate, id),
+        _ => panic!("duplicate ids should not be accepted")
+    }
+}
+
+#[test]
+fn crossings_in_order() {
+    let geometry = initialize_geometry();
+    let navigator = Navigator::new(&geometry);
+
+    // starts between the layers of the first volume, crosses the x > 0 modules
+    let start = P3::new(0.5, 0., 1.5);
+    let crossings = navigator.crossings(&start, &Vec3::new(0., 0., 2.));
+
+    let ids = crossings.iter().map(|c| c.geometry_id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![
+        GeometryId::new(1, 2, 2), GeometryId::new(1, 3, 2),
+        GeometryId::new(2, 1, 2), GeometryId::new(2, 2, 2), GeometryId::new(2, 3, 2)
+    ]);
+
+    assert!((crossings[0].path_length - 0.5).abs() < DOT_PRODUCT_EPSILON);
+    assert!((crossings[0].local.x + 0.5).abs() < DOT_PRODUCT_EPSILON);
+
+    // going backwards only the first layer is crossed
+    let backwards = navigator.crossings(&start, &Vec3::new(0., 0., -1.));
+    assert_eq!(backwards.len(), 1);
+    assert_eq!(backwards[0].geometry_id, GeometryId::new(1, 1, 2));
+}
+
+#[test]
+fn crossings_leave_the_geometry() {
+    let geometry = initialize_geometry();
+    let navigator = Navigator::new(&geometry);
+
+    // leaves the modules through the +x edge after crossing the first layers
+    let direction = Vec3::new(0.5, 0., 1.);
+    let crossings = navigator.crossings(&P3::new(1., 0., 0.), &direction);
+    assert_eq!(crossings.len(), 2);
+
+    // parallel to every module
+    assert!(navigator.crossings(&P3::new(0., 0., 1.5), &Vec3::new(1., 0., 0.)).is_empty());
+}
+
+#[test]
+fn fit_unordered_measurements() {
+    let geometry = initialize_geometry();
+    let navigator = Navigator::new(&geometry);
+
+    let start = P3::new(0.5, 0., 0.);
+    let direction = Vec3::new(0.01, 0., 1.);
+
+    let truth = navigator.crossings(&start, &direction);
+    assert_eq!(truth.len(), 6);
+
+    let mut measurements = truth.iter()
+        .map(|c| Measurement::new(c.geometry_id, c.local.coords, Mat2::identity() * 0.0001))
+        .collect::<Vec<_>>();
+
+    let (phi, theta) = (0., (0.01 as Real).atan());
+    let seed = Vec5::new(truth[0].local.x, truth[0].local.y, phi, theta, 1.);
+    let options = linear::FitterOptions::default();
+
+    let ordered = linear::run_navigated(&start, &direction, &measurements, &navigator, Some(&seed), &options).unwrap();
+
+    // shuffle the measurements, the fit does not change
+    measurements.swap(0, 5);
+    measurements.swap(1, 3);
+    let shuffled = linear::run_navigated(&start, &direction, &measurements, &navigator, Some(&seed), &options).unwrap();
+
+    let ids = truth.iter().map(|c| c.geometry_id).collect::<Vec<_>>();
+    assert_eq!(shuffled.smth.geometry_ids, ids);
+
+    ordered.smth.state_vec.iter()
+        .zip(shuffled.smth.state_vec.iter())
+        .for_each(|(a, b)| assert!((a - b).norm() < 1e-12));
+
+    // a measurement on a module that is not in the geometry
+    measurements.push(Measurement::new(GeometryId::new(9, 1, 1), Vec2::zeros(), Mat2::identity()));
+    match linear::run_navigated(&start, &direction, &measurements, &navigator, Some(&seed), &options) {
+        Err(FilterError::Sensor(SensorError::UnknownGeometryId(id))) => assert_eq!(id, GeometryId::new(9, 1, 1)),
+        _ => panic!("unknown module should not be fitted")
+    }
+
+    // the modules are parallel to a track along x
+    measurements.pop();
+    let along_x = Vec3::new(1., 0., 0.);
+    match linear::run_navigated(&start, &along_x, &measurements, &navigator, Some(&seed), &options) {
+        Err(FilterError::Sensor(SensorError::InvalidDirection(d))) => assert_eq!(d, along_x),
+        _ => panic!("modules that are not crossed should not be fitted")
+    }
+}
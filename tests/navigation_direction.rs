@@ -0,0 +1,97 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::SensorError;
+use krs::filter::{jacobian, linear, prediction};
+use krs::geometry::bounds::BoundaryCheck;
+use krs::geometry::intersection::NavigationDirection;
+use krs::geometry::traits::*;
+use krs::geometry::{GeometryId, Rectangle};
+
+/*
+
+    Tests for forward / backward propagation in the linear KF
+
+*/
+
+const PHI: Real = 0.3;
+const THETA: Real = 0.2;
+
+// 10 x 10 rectangle parallel to the x-y plane at a given z
+fn initialize_rect(z: Real) -> Rectangle {
+    let translation = Mat4::new_translation(&Vec3::new(0., 0., z));
+    let mut sensor = Rectangle::new(10., 10., translation, Mat4::identity()).unwrap();
+    sensor.set_geometry_id(GeometryId::new(1, 1 + z as u16, 1));
+    sensor
+}
+
+// local position of a straight track from (0.1, 0.2, 0) with angles PHI / THETA on the plane at z
+fn truth(z: Real) -> Vec2 {
+    let slope = THETA.tan();
+    Vec2::new(0.1 + slope * PHI.cos() * z, 0.2 + slope * PHI.sin() * z)
+}
+
+#[test]
+fn backward_prediction() {
+    let start = initialize_rect(5.);
+    let end = initialize_rect(0.);
+    let hit = truth(5.);
+    let state = Vec5::new(hit.x, hit.y, PHI, THETA, 1.);
+
+    // the end sensor is behind the particle
+    match prediction::linear_state_vector_in_direction(&start, &end, &state, &BoundaryCheck::Strict, NavigationDirection::Forward) {
+        Err(SensorError::InvalidDirection(_)) => {},
+        _ => panic!("the end sensor can not be reached along the momentum")
+    }
+
+    let (pred, distance) =
+        prediction::linear_state_vector_in_direction(&start, &end, &state, &BoundaryCheck::Strict, NavigationDirection::Backward).unwrap();
+
+    assert!((distance - 5. / THETA.cos()).abs() < 1e-12);
+    assert!((pred.fixed_rows::<U2>(0) - truth(0.)).norm() < 1e-12);
+
+    // the direction of the state is still the direction of the momentum
+    assert_eq!((pred[ePHI], pred[eTHETA]), (PHI, THETA));
+}
+
+#[test]
+fn backward_jacobian() {
+    let start = initialize_rect(5.);
+    let end = initialize_rect(0.);
+    let state = Vec5::new(0.5, 0.5, PHI, THETA, 1.);
+
+    let backward = jacobian::linear_in_direction(&state, 3., &start, &end, NavigationDirection::Backward);
+    let forward = jacobian::linear_in_direction(&state, -3., &start, &end, NavigationDirection::Forward);
+    assert_eq!(backward, forward);
+
+    assert_eq!(jacobian::linear(&state, 3., &start, &end), jacobian::linear_in_direction(&state, 3., &start, &end, NavigationDirection::Forward));
+}
+
+#[test]
+fn backward_fit() {
+    // inputs ordered along the momentum
+    let sensors = (0..5).map(|i| initialize_rect(i as Real)).collect::<Vec<_>>();
+    let hits = (0..5).map(|i| truth(i as Real)).collect::<Vec<_>>();
+    let covariance = (0..5).map(|_| Mat2::identity() * 0.0001).collect::<Vec<_>>();
+
+    let last = hits.last().unwrap();
+    let seed = Vec5::new(last.x, last.y, PHI, THETA, 1.);
+
+    let options = linear::FitterOptions{navigation: NavigationDirection::Backward, ..Default::default()};
+    let result = linear::run_with_options(&P3::new(0., 0., 10.), &covariance, &hits, &sensors, Some(&seed), &options).unwrap();
+
+    // the states are stored in the order they were fitted: from the last sensor to the first
+    let expected_ids = sensors.iter().rev().map(|sensor| sensor.geometry_id()).collect::<Vec<_>>();
+    assert_eq!(result.filt.geometry_ids, expected_ids);
+
+    for (i, hit) in hits.iter().rev().enumerate() {
+        for data in &[&result.pred, &result.filt] {
+            let state = data.state_vec[i];
+            assert!((state.fixed_rows::<U2>(0) - hit).norm() < 1e-9, "{} != {}", state, hit);
+            assert!(((state[ePHI] - PHI).abs() < 1e-9) && ((state[eTHETA] - THETA).abs() < 1e-9));
+        }
+    }
+
+    // without a seed the direction from the start location is turned around
+    let result = linear::run_with_options(&P3::new(-1., -1., 10.), &covariance, &hits, &sensors, None, &options).unwrap();
+    assert!(result.filt.state_vec[0][eTHETA] < PI / 2.);
+}
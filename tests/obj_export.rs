@@ -0,0 +1,89 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::export::{self, obj::ObjExporter};
+use krs::generate_data::setup::generate_track;
+use krs::geometry::traits::Identified;
+use krs::geometry::{Disc, GeometryId, Rectangle, Trapezoid};
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use rand_distr::Normal;
+
+/*
+
+    Tests for kalman_rs::export::obj
+
+*/
+
+fn count(obj: &str, keyword: &str) -> usize {
+    obj.lines().filter(|line| line.split_whitespace().next() == Some(keyword)).count()
+}
+
+#[test]
+fn surface_meshes() {
+    let mut rect = Rectangle::new(2., 4., Mat4::identity(), Mat4::identity()).unwrap();
+    rect.set_geometry_id(GeometryId::new(1, 2, 3));
+
+    let trap = Trapezoid::new(1., 2., Mat4::new_translation(&Vec3::new(0., 0., 1.)), Mat4::identity(), 2.).unwrap();
+    let disc = Disc::new(1., 2., 0., PI, Mat4::new_translation(&Vec3::new(0., 0., 2.)), Mat4::identity()).unwrap();
+
+    let mut exporter = ObjExporter::new();
+    exporter.arc_segments = 8;
+    exporter.add_surface(&rect);
+    exporter.add_surface(&trap);
+    exporter.add_surface(&disc);
+
+    let obj = exporter.to_obj();
+
+    assert_eq!(count(&obj, "f"), 3);
+    assert_eq!(count(&obj, "v"), 4 + 4 + 8);
+    assert_eq!(count(&obj, "o"), 1);
+    assert!(obj.contains("f 1 2 3 4\n"));
+    assert!(obj.contains("f 9 10 11 12 13 14 15 16\n"));
+
+    // every vertex of the rectangle is on one of its corners
+    obj.lines()
+        .take_while(|line| !line.starts_with('f'))
+        .filter(|line| line.starts_with("v "))
+        .for_each(|line| {
+            let coords = line.split_whitespace().skip(1).map(|c| c.parse::<Real>().unwrap()).collect::<Vec<_>>();
+            assert!((coords[0].abs() - 1.).abs() < 1e-12);
+            assert!((coords[1].abs() - 2.).abs() < 1e-12);
+            assert!(coords[2].abs() < 1e-12);
+        });
+
+    exporter.separate_objects = true;
+    let obj = exporter.to_obj();
+    assert_eq!(count(&obj, "o"), 3);
+    assert!(obj.contains("o surface_1_2_3\n"));
+}
+
+#[test]
+fn track_polylines() {
+    let data = generate_track(
+        5, 1., Some((0., PI/2.)), SmallRng::seed_from_u64(35), 0.01,
+        Normal::new(3., 1.5).unwrap(), Normal::new(0., 1.).unwrap()
+    );
+    let result = krs::filter::linear::run(&data.start, &data.cov, &data.smear_hits, &data.sensors, None);
+
+    let mut exporter = ObjExporter::new();
+    exporter.separate_objects = true;
+    exporter.add_surfaces(&data.sensors);
+    exporter.add_track("truth track", export::truth_points(&data));
+    exporter.add_track("fitted track", export::global_points(&data.sensors, &result.smth.state_vec));
+
+    let obj = exporter.to_obj();
+
+    assert_eq!(count(&obj, "f"), 5);
+    assert_eq!(count(&obj, "l"), 2);
+    assert!(obj.contains("o truth_track\n"));
+    assert!(obj.contains("o fitted_track\n"));
+
+    // the truth track starts at the origin, the polyline goes through all its points in order
+    assert!(obj.contains("l 21 22 23 24 25 26\n"));
+    assert!(obj.contains("l 27 28 29 30 31\n"));
+
+    let path = std::env::temp_dir().join("kalman_rs_obj_export.obj");
+    exporter.write(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), obj);
+}
@@ -0,0 +1,150 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{Error, FilterError, SensorError};
+use krs::filter::perigee::{self, PerigeeSurface};
+use krs::filter::utils::{Data, SuperData};
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::builder::{Placement, SurfaceBuilder};
+use krs::geometry::intersection::{Intersect, NavigationDirection};
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::*;
+use krs::geometry::{GeometryId, Surface};
+
+/*
+
+    Tests for kalman_rs::filter::perigee
+
+*/
+
+const STEP: Real = 1e-6;
+
+fn sensor(z: Real) -> Surface {
+    let placement = Placement::from_normal(P3::new(0., 0., z), &Vec3::new(0.1, 0.2, 1.), Some(&Vec3::x())).unwrap();
+    SurfaceBuilder::new(placement)
+        .geometry_id(GeometryId::new(1, 1, 1 + z as u32))
+        .plane(SurfaceBounds::Rectangle(RectangleBounds::new(20., 20.)))
+        .unwrap()
+}
+
+fn covariance() -> Mat5 {
+    let a = Mat5::from_fn(|i, j| 0.1 * ((i * 5 + j) as Real).cos());
+    a * a.transpose() + Mat5::identity() * 0.01
+}
+
+// bound state on `sensor` of the track with the perigee parameters `state` around `surface`
+fn bound_state(surface: &PerigeeSurface, sensor: &Surface, state: &Vec5) -> Vec5 {
+    let (position, direction) = surface.to_global(state);
+    let local = sensor.intersect(&position, &direction, NavigationDirection::Forward)[0].local;
+
+    Vec5::new(local.x, local.y, state[ePHI], state[eTHETA], state[eQOP])
+}
+
+// perigee parameters of the track of the bound state `state` on `sensor`
+fn perigee_state(surface: &PerigeeSurface, sensor: &Surface, state: &Vec5) -> Vec5 {
+    let position = sensor.to_global(P3::new(state[eLOC_0], state[eLOC_1], 0.));
+    let direction = Vec3::new(state[ePHI].cos() * state[eTHETA].sin(), state[ePHI].sin() * state[eTHETA].sin(), state[eTHETA].cos());
+
+    surface.parameters(&position, &direction, state[eQOP]).unwrap()
+}
+
+#[test]
+fn beamline_extrapolation() {
+    let beamline = PerigeeSurface::beamline();
+    let sensor = sensor(10.);
+    let truth = Vec5::new(0.05, -0.3, 0.7, 0.4, 0.5);
+
+    let state = bound_state(&beamline, &sensor, &truth);
+    let parameters = perigee::extrapolate(&sensor, &state, &covariance(), &beamline).unwrap();
+
+    assert!((parameters.state_vec - truth).norm() < 1e-12);
+    assert!((parameters.d0() - 0.05).abs() < 1e-12);
+    assert!((parameters.z0() + 0.3).abs() < 1e-12);
+    assert!((parameters.position().coords.xy().norm() - 0.05).abs() < 1e-12);
+
+    // the covariance follows the change of the perigee parameters with the bound state
+    let mut jacobian = Mat5::zeros();
+    for i in 0..5 {
+        let mut step = Vec5::zeros();
+        step[i] = STEP;
+        let difference = perigee_state(&beamline, &sensor, &(state + step)) - perigee_state(&beamline, &sensor, &(state - step));
+        jacobian.set_column(i, &(difference / (2. * STEP)));
+    }
+    let expected = jacobian * covariance() * jacobian.transpose();
+    assert!((parameters.cov_mat - expected).abs().max() < 1e-7, "{} != {}", parameters.cov_mat, expected);
+
+    // the impact parameters are less certain than the position on the sensor
+    assert!(parameters.cov_mat[(eLOC_0, eLOC_0)] > covariance()[(eLOC_0, eLOC_0)]);
+}
+
+#[test]
+fn vertex_extrapolation() {
+    let vertex = P3::new(0.2, -0.1, 3.);
+    let surface = PerigeeSurface::new(vertex);
+    assert_eq!(surface.reference(), &vertex);
+
+    // a track coming from the vertex has no impact parameters around it
+    let direction = Vec3::new(0.3, -0.2, 1.);
+    let sensor = sensor(10.);
+    let local = sensor.intersect(&vertex, &direction, NavigationDirection::Forward)[0].local;
+    let state = Vec5::new(local.x, local.y, direction.y.atan2(direction.x), (direction.xy().norm() / direction.z).atan(), 1.);
+
+    let parameters = perigee::extrapolate(&sensor, &state, &covariance(), &surface).unwrap();
+    assert!(parameters.d0().abs() < 1e-12);
+    assert!(parameters.z0().abs() < 1e-12);
+    assert!((parameters.position() - vertex).norm() < 1e-12);
+    assert!((parameters.direction() - direction.normalize()).norm() < 1e-12);
+
+    // the vertex is behind the sensor
+    let distance = surface.path_length(&sensor.to_global(P3::new(local.x, local.y, 0.)), &direction).unwrap();
+    assert!(distance < 0.);
+
+    // tracks along the line have no point of closest approach
+    let along_z = Vec5::new(0., 0., 0., 0., 1.);
+    match perigee::extrapolate(&sensor, &along_z, &covariance(), &surface) {
+        Err(SensorError::InvalidDirection(_)) => {},
+        _ => panic!("a track parallel to the beamline has no perigee")
+    }
+}
+
+#[test]
+fn fitted_track_extrapolation() {
+    let beamline = PerigeeSurface::beamline();
+    let sensors = vec![sensor(5.), sensor(10.)];
+    let geometry = TrackingGeometry::new(sensors.clone()).unwrap();
+    let truth = Vec5::new(-0.02, 0.1, 2.5, 0.3, 1.);
+
+    let states = sensors.iter().map(|sensor| bound_state(&beamline, sensor, &truth)).collect::<Vec<_>>();
+    let smth = Data::new(states, vec![covariance(); 2], vec![Mat2::zeros(); 2], vec![Vec2::zeros(); 2]);
+    let mut track = SuperData::new(smth, Data::new(Vec::new(), Vec::new(), Vec::new(), Vec::new()), Data::new(Vec::new(), Vec::new(), Vec::new(), Vec::new()));
+    track.set_geometry_ids(sensors.iter().map(|sensor| sensor.geometry_id()).collect());
+
+    // the state on the innermost sensor is extrapolated
+    let parameters = perigee::extrapolate_track(&track, &geometry, &beamline).unwrap();
+    let expected = perigee::extrapolate(&sensors[0], &track.smth.state_vec[0], &covariance(), &beamline).unwrap();
+    assert_eq!(parameters, expected);
+    assert!((parameters.state_vec - truth).norm() < 1e-12);
+
+    // a backward fit stores the states from the outermost sensor
+    track.smth.state_vec.reverse();
+    track.set_geometry_ids(sensors.iter().rev().map(|sensor| sensor.geometry_id()).collect());
+    assert_eq!(perigee::extrapolate_track(&track, &geometry, &beamline).unwrap(), expected);
+
+    track.set_geometry_ids(vec![GeometryId::new(9, 9, 9); 2]);
+    match perigee::extrapolate_track(&track, &geometry, &beamline) {
+        Err(Error::Sensor(SensorError::UnknownGeometryId(id))) => assert_eq!(id, GeometryId::new(9, 9, 9)),
+        _ => panic!("the first surface of the track is not in the geometry")
+    }
+
+    track.set_geometry_ids(vec![sensors[1].geometry_id()]);
+    match perigee::extrapolate_track(&track, &geometry, &beamline) {
+        Err(Error::Sensor(SensorError::MissingGeometryId(index))) => assert_eq!(index, 1),
+        _ => panic!("the second state has no id")
+    }
+
+    let empty = || Data::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let track = SuperData::new(empty(), empty(), empty());
+    match perigee::extrapolate_track(&track, &geometry, &beamline) {
+        Err(Error::Filter(FilterError::NotEnoughMeasurements)) => {},
+        _ => panic!("an empty track has no state to extrapolate")
+    }
+}
@@ -0,0 +1,103 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::export::phoenix::PhoenixExporter;
+use krs::filter::linear;
+use krs::generate_data::setup::generate_track;
+use krs::geometry::intersection::NavigationDirection;
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use rand_distr::Normal;
+use serde_json::Value;
+
+/*
+
+    Tests for kalman_rs::export::phoenix
+
+*/
+
+#[test]
+fn event_data() {
+    let data = generate_track(
+        4, 1., Some((0., PI/2.)), SmallRng::seed_from_u64(36), 0.01,
+        Normal::new(3., 1.5).unwrap(), Normal::new(0., 1.).unwrap()
+    );
+
+    let fit = linear::run(&data.start, &data.cov, &data.smear_hits, &data.sensors, None);
+
+    let mut exporter = PhoenixExporter::new();
+    exporter.run_number = 7;
+    exporter.add_event("event_3", 3, &data, &fit);
+
+    let json: Value = serde_json::from_str(&exporter.to_json().unwrap()).unwrap();
+    let event = &json["event_3"];
+
+    assert_eq!(event["event number"], 3);
+    assert_eq!(event["run number"], 7);
+
+    let hits = event["Hits"]["Measurements"].as_array().unwrap();
+    assert_eq!(hits.len(), 4);
+    assert_eq!(hits[1]["type"], "Point");
+    assert_eq!(hits[1]["geometry_id"]["layer"], 2);
+
+    // the truth track starts at the origin and crosses every sensor
+    let truth = &event["Tracks"]["Truth"][0];
+    assert_eq!(truth["pos"].as_array().unwrap().len(), 5);
+    assert_eq!(truth["pos"][0], serde_json::json!([0., 0., 0.]));
+    assert!(truth.get("states").is_none());
+
+    let fitted = &event["Tracks"]["Fitted"][0];
+    let states = fitted["states"].as_array().unwrap();
+    assert_eq!(states.len(), 4);
+    assert_eq!(fitted["pos"][2], states[2]["pos"]);
+    assert_eq!(states[3]["geometry_id"]["layer"], 4);
+
+    let theta = states[0]["params"][eTHETA].as_f64().unwrap();
+    assert!((theta - fit.smth.state_vec[0][eTHETA]).abs() < 1e-12);
+    assert_eq!(states[0]["covariance"].as_array().unwrap().len(), 5);
+
+    // fitted positions are close to the truth hits
+    let hit = hits[0]["pos"].as_array().unwrap();
+    let fit_pos = fitted["pos"][0].as_array().unwrap();
+    let distance = hit.iter().zip(fit_pos.iter())
+        .map(|(a, b)| (a.as_f64().unwrap() - b.as_f64().unwrap()).powi(2))
+        .sum::<Real>()
+        .sqrt();
+    assert!(distance < 0.1);
+
+    let path = std::env::temp_dir().join("kalman_rs_phoenix_export.json");
+    exporter.write(&path).unwrap();
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written, json);
+}
+
+#[test]
+fn backward_fit_states() {
+    let data = generate_track(
+        4, 1., Some((0., PI/2.)), SmallRng::seed_from_u64(36), 0.01,
+        Normal::new(3., 1.5).unwrap(), Normal::new(0., 1.).unwrap()
+    );
+
+    // seeded with the truth on the last sensor
+    let last_hit = data.truth_hits[3];
+    let seed = Vec5::new(last_hit.x, last_hit.y, 0., PI/2., 1.);
+
+    let options = linear::FitterOptions {navigation: NavigationDirection::Backward, ..Default::default()};
+    let fit = linear::run_with_options(&data.start, &data.cov, &data.smear_hits, &data.sensors, Some(&seed), &options).unwrap();
+
+    let mut exporter = PhoenixExporter::new();
+    exporter.add_event("backward", 0, &data, &fit);
+    let json: Value = serde_json::from_str(&exporter.to_json().unwrap()).unwrap();
+
+    // the states follow the sensors of the event, the fit stores them from the last sensor
+    let states = json["backward"]["Tracks"]["Fitted"][0]["states"].as_array().unwrap();
+    assert_eq!(states.len(), 4);
+
+    for (i, state) in states.iter().enumerate() {
+        assert_eq!(state["geometry_id"]["layer"], i + 1);
+
+        let fitted = &fit.smth.state_vec[3 - i];
+        assert!((state["params"][eLOC_0].as_f64().unwrap() - fitted[eLOC_0]).abs() < 1e-12);
+        assert!((state["params"][eLOC_1].as_f64().unwrap() - fitted[eLOC_1]).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,23 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::filter::{prediction, smoothing};
+
+/*
+
+    Tests for the sign of the smoothed residuals in kalman_rs::filter::smoothing
+
+*/
+
+#[test]
+fn smoothed_residual_is_measurement_minus_state() {
+    let map = Mat2x5::new(1., 0., 0., 0., 0.,
+                          0., 1., 0., 0., 0.);
+    let measurement = Vec2::new(1., 2.);
+    let state_vec = Vec5::new(0.5, 0.5, 0.1, 0.2, 1.);
+
+    let residual = smoothing::residual_vec(&measurement, &map, &state_vec);
+    assert_eq!(residual, Vec2::new(0.5, 1.5));
+
+    // same convention as the predicted residuals
+    assert_eq!(residual, prediction::residual_vec(&measurement, &map, &state_vec));
+}
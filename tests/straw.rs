@@ -0,0 +1,169 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::geometry::Straw;
+use krs::filter::{prediction, straw};
+use krs::filter::angles::Angles;
+use krs::error::FilterError;
+
+/*
+
+    Tests for kalman_rs::geometry::Straw (drift tubes) and the 1D drift radius
+    filter in kalman_rs::filter::straw
+
+*/
+
+// wire along the z axis at a given x / y
+fn initialize_straw(x: Real, y: Real) -> Straw {
+    Straw::new(P3::new(x, y, 0.), Vec3::new(0., 0., 2.), 0.5, 100.).unwrap()
+}
+
+fn assert_close(left: Real, right: Real) {
+    dbg!{left}; dbg!{right};
+    assert!((left - right).abs() < DOT_PRODUCT_EPSILON)
+}
+
+#[test]
+fn straw_point_of_closest_approach() {
+    let straw = initialize_straw(5., 0.);
+
+    let start = P3::new(0., 0.2, 3.);
+    let direction = Vec3::new(1., 0., 0.);
+
+    let (pca, path_length) = straw.point_of_closest_approach(&start, &direction).unwrap();
+
+    assert_close(path_length, 5.);
+    assert_close(pca.x, 5.);
+    assert_close(pca.y, 0.2);
+    assert_close(pca.z, 3.);
+}
+
+#[test]
+fn straw_signed_distance() {
+    let straw = initialize_straw(5., 0.);
+    let direction = Vec3::new(1., 0., 0.);
+
+    // z cross x = y, so passing above the wire in y is a positive distance
+    let above = straw.to_local(&P3::new(5., 0.2, 1.), &direction);
+    let below = straw.to_local(&P3::new(5., -0.2, 1.), &direction);
+
+    assert_close(above.x, 0.2);
+    assert_close(below.x, -0.2);
+    assert_close(above.y, 1.);
+
+    // reversing the track direction flips the sign
+    let reversed = straw.to_local(&P3::new(5., 0.2, 1.), &(-direction));
+    assert_close(reversed.x, -0.2);
+}
+
+#[test]
+fn straw_round_trip() {
+    let straw = initialize_straw(2., 1.);
+    let direction = Angles::new_from_angles(0.3, 1.2).direction;
+
+    let local = P2::new(-0.3, 4.);
+    let global = straw.to_global(&local, &direction).unwrap();
+    let back = straw.to_local(&global, &direction);
+
+    assert_close(back.x, local.x);
+    assert_close(back.y, local.y);
+}
+
+#[test]
+fn straw_parallel_track() {
+    let straw = initialize_straw(0., 0.);
+    let direction = Vec3::new(0., 0., 1.);
+
+    assert!(straw.point_of_closest_approach(&P3::new(1., 0., 0.), &direction).is_none());
+    assert!(straw.to_global(&P2::new(0.1, 0.), &direction).is_none());
+}
+
+#[test]
+fn straw_invalid_direction() {
+    assert!(Straw::new(P3::origin(), Vec3::zeros(), 1., 1.).is_err());
+}
+
+#[test]
+fn straw_prediction() {
+    let start = initialize_straw(0., 0.);
+    let end = initialize_straw(4., 0.1);
+
+    // along x, 0.2 above the first wire
+    let state = Vec5::new(0.2, 1., 0., PI/2., 1.);
+    let (pred, distance) = prediction::linear_straw_state_vector(&start, &end, &state).unwrap();
+
+    assert_close(distance, 4.);
+    assert_close(pred[eLOC_0], 0.1);
+    assert_close(pred[eLOC_1], 1.);
+}
+
+// the side of the wire closest to the prediction is chosen
+#[test]
+fn straw_resolve_ambiguity() {
+    let cov = Mat5::identity() * 0.01;
+
+    let pred_above = Vec5::new(0.15, 0., 0., PI/2., 1.);
+    let pred_below = Vec5::new(-0.15, 0., 0., PI/2., 1.);
+
+    let above = straw::resolve_ambiguity(&pred_above, &cov, 0.2, 0.0001);
+    let below = straw::resolve_ambiguity(&pred_below, &cov, 0.2, 0.0001);
+
+    assert_eq!(above.sign, 1.);
+    assert_eq!(below.sign, -1.);
+    assert!(above.state_vec[eLOC_0] > 0.);
+    assert!(below.state_vec[eLOC_0] < 0.);
+}
+
+// full fit of a track crossing straws on alternating sides of the wires
+#[test]
+fn straw_linear_filter() {
+    let straws = (0..8)
+        .map(|i| {
+            let offset = if i % 2 == 0 {0.25} else {-0.15};
+            initialize_straw(i as Real, offset)
+        })
+        .collect::<Vec<_>>();
+
+    let (phi, theta) = (0.02, 1.4);
+    let direction = Angles::new_from_angles(phi, theta).direction;
+    let start = P3::new(0., 0., 0.);
+
+    // truth signed distances of the track to each wire
+    let truth = straws.iter()
+        .map(|s| {
+            let (pca, _) = s.point_of_closest_approach(&start, &direction).unwrap();
+            s.to_local(&pca, &direction)
+        })
+        .collect::<Vec<_>>();
+
+    let radii = truth.iter().map(|local| local.x.abs()).collect::<Vec<_>>();
+    let variances = truth.iter().map(|_| 0.0001).collect::<Vec<_>>();
+
+    let seed = Vec5::new(truth[0].x, truth[0].y, phi, theta, 1.);
+
+    let result = straw::run(&variances, &radii, &straws, &seed).unwrap();
+
+    truth.iter()
+        .zip(result.signs.iter())
+        .zip(result.smth.state_vec.iter())
+        .for_each(|((truth, sign), smth)| {
+            assert_eq!(truth.x.signum(), *sign);
+            assert!((smth[eLOC_0] - truth.x).abs() < 0.01);
+        });
+}
+
+#[test]
+fn straw_filter_mismatched_lengths() {
+    let straws = (0..3).map(|i| initialize_straw(i as Real, 0.)).collect::<Vec<_>>();
+    let seed = Vec5::new(0., 0., 0., 1.4, 1.);
+
+    let result = straw::run(&[0.0001; 2], &[0.1; 3], &straws, &seed);
+    assert!(matches!(result, Err(FilterError::LengthMismatch)));
+}
+
+#[test]
+fn straw_filter_no_straws() {
+    let seed = Vec5::new(0., 0., 0., 1.4, 1.);
+
+    let result = straw::run(&[], &[], &[], &seed);
+    assert!(matches!(result, Err(FilterError::NotEnoughMeasurements)));
+}
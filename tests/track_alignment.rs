@@ -0,0 +1,254 @@
+use kalman_rs as krs;
+use krs::config::*;
+use krs::error::{AlignmentError, Error, MatrixError, SensorError};
+use krs::filter::alignment::GlobalAlignment;
+use krs::filter::utils::{Data, SuperData};
+use krs::geometry::alignment::{AlignmentDelta, AlignmentStore};
+use krs::geometry::bounds::{RectangleBounds, SurfaceBounds};
+use krs::geometry::navigator::Navigator;
+use krs::geometry::tracking_geometry::TrackingGeometry;
+use krs::geometry::traits::*;
+use krs::geometry::{Disc, GeometryId, PlaneSurface, Surface};
+
+/*
+
+    Tests for kalman_rs::filter::alignment
+
+*/
+
+const SIGMA: Real = 0.01;
+
+fn telescope() -> TrackingGeometry {
+    let surfaces = (1..=6)
+        .map(|layer| {
+            let bounds = SurfaceBounds::Rectangle(RectangleBounds::new(5., 5.));
+            let translation = Mat4::new_translation(&Vec3::new(0., 0., layer as Real));
+            let rotation = Mat4::from_axis_angle(&Vec3::z_axis(), 0.2);
+
+            let mut surface = Surface::Plane(PlaneSurface::new(bounds, translation, rotation).unwrap());
+            surface.set_geometry_id(GeometryId::new(1, layer, 1));
+            surface
+        })
+        .collect();
+
+    TrackingGeometry::new(surfaces).unwrap()
+}
+
+// discs around the z axis whose modules straddle phi = +/- pi
+fn disc_telescope() -> TrackingGeometry {
+    let surfaces = (1..=6)
+        .map(|layer| {
+            let translation = Mat4::new_translation(&Vec3::new(0., 0., layer as Real));
+
+            let mut surface = Surface::Disc(Disc::new(2., 10., PI, PI/4., translation, Mat4::identity()).unwrap());
+            surface.set_geometry_id(GeometryId::new(1, layer, 1));
+            surface
+        })
+        .collect();
+
+    TrackingGeometry::new(surfaces).unwrap()
+}
+
+fn misalignment() -> AlignmentStore {
+    let mut store = AlignmentStore::new();
+    store.insert(GeometryId::new(1, 2, 1), AlignmentDelta::new(Vec3::new(0.05, -0.02, 0.01), Vec3::new(0.002, 0., 0.001)));
+    store.insert(GeometryId::new(1, 3, 1), AlignmentDelta::new(Vec3::new(-0.03, 0.04, -0.02), Vec3::new(0., -0.002, 0.)));
+    store.insert(GeometryId::new(1, 4, 1), AlignmentDelta::new(Vec3::new(0., 0.01, 0.), Vec3::new(-0.001, 0.001, -0.002)));
+    store.insert(GeometryId::new(1, 5, 1), AlignmentDelta::new(Vec3::new(0.02, 0., 0.03), Vec3::zeros()));
+    store
+}
+
+// tracks starting around `centre` with hits simulated on `simulated` and states from the
+// (nominal) `reconstructed` geometry
+fn tracks(simulated: &TrackingGeometry, reconstructed: &TrackingGeometry, centre: &P3) -> Vec<SuperData> {
+    let mut tracks = Vec::new();
+
+    for i in 0..10 {
+        for j in 0..10 {
+            let start = centre + Vec3::new(-1. + 0.2 * (i as Real), -1. + 0.2 * (j as Real), 0.);
+            let direction = Vec3::new(0.3 * ((i * 7 % 10) as Real / 10. - 0.5), 0.3 * ((j * 3 % 10) as Real / 10. - 0.5), 1.);
+            let (phi, theta) = (direction.y.atan2(direction.x), (direction.xy().norm() / direction.z).atan());
+
+            let hits = Navigator::new(simulated).crossings(&start, &direction);
+            let states = Navigator::new(reconstructed).crossings(&start, &direction);
+            assert_eq!(hits.len(), 6);
+
+            let state_vec = states.iter().map(|s| Vec5::new(s.local.x, s.local.y, phi, theta, 1.)).collect::<Vec<_>>();
+            let res_vec = hits.iter().zip(&states).map(|(h, s)| h.local - s.local).collect();
+
+            let smth = Data::new(state_vec, vec![Mat5::zeros(); 6], vec![Mat2::zeros(); 6], res_vec);
+            let filt = Data::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+            let pred = Data::new(Vec::new(), Vec::new(), Vec::new(), Vec::new());
+
+            let mut track = SuperData::new(smth, filt, pred);
+            track.set_geometry_ids(hits.iter().map(|h| h.geometry_id).collect());
+            tracks.push(track);
+        }
+    }
+
+    tracks
+}
+
+fn covariances() -> Vec<Mat2> {
+    vec![Mat2::identity() * (SIGMA * SIGMA); 6]
+}
+
+#[test]
+fn recovers_misalignment() {
+    let nominal = telescope();
+    let truth = misalignment();
+    let misaligned = truth.align_geometry(&nominal).unwrap();
+
+    let mut alignment = GlobalAlignment::new(&nominal);
+    alignment.fix(GeometryId::new(1, 1, 1));
+    alignment.fix(GeometryId::new(1, 6, 1));
+
+    for track in tracks(&misaligned, &nominal, &P3::origin()) {
+        alignment.add_track(&track, &covariances()).unwrap();
+    }
+    assert_eq!(alignment.tracks(), 100);
+
+    let solution = alignment.solve().unwrap();
+    assert_eq!(solution.len(), 4);
+    assert!(solution.get(GeometryId::new(1, 1, 1)).is_none());
+
+    for (id, expected) in truth.iter() {
+        let correction = solution.get(id).unwrap();
+        let uncertainty = correction.uncertainty();
+
+        assert!((correction.delta.translation - expected.translation).norm() < 1e-3, "{:?}", correction.delta);
+        assert!((correction.delta.rotation - expected.rotation).norm() < 1e-4, "{:?}", correction.delta);
+
+        // the parameters in the plane of the sensor are known to about SIGMA / sqrt(tracks),
+        // the others are only constrained by the slopes of the tracks
+        assert!(uncertainty.translation.iter().chain(uncertainty.rotation.iter()).all(|sigma| sigma.is_finite() && (*sigma > 0.)));
+        assert!((uncertainty.translation.x < 0.2 * SIGMA) && (uncertainty.translation.y < 0.2 * SIGMA));
+        assert!(uncertainty.rotation.z < 0.2 * SIGMA);
+        assert!(uncertainty.translation.z > uncertainty.translation.x);
+    }
+
+    // the tracks fit well after the correction
+    assert!(solution.chi_squared_before > 5. * (solution.ndf as Real));
+    assert!(solution.chi_squared_after < 1e-6 * solution.chi_squared_before);
+    assert_eq!(solution.ndf, (100 * 12) - (100 * 4) - (4 * 6));
+
+    // reconstructing with the corrections brings the states onto the hits
+    let corrected = solution.to_store().align_geometry(&nominal).unwrap();
+    for track in tracks(&misaligned, &corrected, &P3::origin()) {
+        assert!(track.smth.res_vec.iter().all(|res| res.norm() < 1e-3));
+    }
+}
+
+#[test]
+fn recovers_misalignment_across_phi_boundary() {
+    let nominal = disc_telescope();
+    let truth = misalignment();
+    let misaligned = truth.align_geometry(&nominal).unwrap();
+
+    let mut alignment = GlobalAlignment::new(&nominal);
+    alignment.fix(GeometryId::new(1, 1, 1));
+    alignment.fix(GeometryId::new(1, 6, 1));
+
+    // about half of the hits are at phi just below pi, the others just above -pi
+    let tracks = tracks(&misaligned, &nominal, &P3::new(-5., 0., 0.));
+    assert!(tracks.iter().any(|track| track.smth.state_vec.iter().any(|state| state[eLOC_1] < 0.)));
+
+    for track in &tracks {
+        alignment.add_track(track, &covariances()).unwrap();
+    }
+
+    let solution = alignment.solve().unwrap();
+    for (id, expected) in truth.iter() {
+        let correction = solution.get(id).unwrap();
+
+        assert!((correction.delta.translation - expected.translation).norm() < 1e-3, "{:?}", correction.delta);
+        assert!((correction.delta.rotation - expected.rotation).norm() < 1e-4, "{:?}", correction.delta);
+    }
+    assert!(solution.chi_squared_after < 1e-6 * solution.chi_squared_before);
+}
+
+#[test]
+fn aligned_detector_needs_no_correction() {
+    let nominal = telescope();
+
+    let mut alignment = GlobalAlignment::new(&nominal);
+    alignment.fix(GeometryId::new(1, 1, 1));
+    alignment.fix(GeometryId::new(1, 6, 1));
+    assert!(alignment.is_fixed(GeometryId::new(1, 6, 1)));
+
+    for track in tracks(&nominal, &nominal, &P3::origin()) {
+        alignment.add_track(&track, &covariances()).unwrap();
+    }
+
+    let solution = alignment.solve().unwrap();
+    for (_, correction) in solution.iter() {
+        assert!(correction.delta.translation.norm() < 1e-9);
+        assert!(correction.delta.rotation.norm() < 1e-9);
+    }
+}
+
+#[test]
+fn weak_modes_and_bad_input() {
+    let nominal = telescope();
+
+    // without reference surfaces the whole telescope can move with the tracks
+    let mut alignment = GlobalAlignment::new(&nominal);
+    let mut track = tracks(&nominal, &nominal, &P3::origin()).remove(0);
+    match alignment.add_track(&track, &covariances()) {
+        Err(Error::Alignment(AlignmentError::NoFixedSurface)) => {},
+        _ => panic!("tracks should not be added before fixing a surface")
+    }
+    assert_eq!(alignment.tracks(), 0);
+
+    // with a single reference surface the telescope can still shear with the slopes
+    alignment.fix(GeometryId::new(1, 1, 1));
+    for track in tracks(&nominal, &nominal, &P3::origin()) {
+        alignment.add_track(&track, &covariances()).unwrap();
+    }
+    match alignment.solve() {
+        Err(MatrixError::NonInvertible) => {},
+        _ => panic!("the system should not be solvable with one fixed surface")
+    }
+
+    match alignment.add_track(&track, &covariances()[..5]) {
+        Err(Error::Alignment(AlignmentError::LengthMismatch)) => {},
+        _ => panic!("covariances of the wrong length should not be accepted")
+    }
+
+    track.set_geometry_ids(vec![GeometryId::new(9, 9, 9); 6]);
+    match alignment.add_track(&track, &covariances()) {
+        Err(Error::Sensor(SensorError::UnknownGeometryId(id))) => assert_eq!(id, GeometryId::new(9, 9, 9)),
+        _ => panic!("tracks on unknown surfaces should not be accepted")
+    }
+}
+
+#[test]
+fn rejected_tracks_leave_system_unchanged() {
+    let nominal = telescope();
+    let misaligned = misalignment().align_geometry(&nominal).unwrap();
+
+    let mut alignment = GlobalAlignment::new(&nominal);
+    alignment.fix(GeometryId::new(1, 1, 1));
+    alignment.fix(GeometryId::new(1, 6, 1));
+
+    let tracks = tracks(&misaligned, &nominal, &P3::origin());
+    for track in &tracks {
+        alignment.add_track(track, &covariances()).unwrap();
+    }
+    let before = alignment.solve().unwrap();
+
+    // the last measurement can not be weighted after the other hits were already used
+    let mut singular = covariances();
+    singular[5] = Mat2::zeros();
+    match alignment.add_track(&tracks[0], &singular) {
+        Err(Error::Matrix(MatrixError::NonInvertible)) => {},
+        _ => panic!("a singular measurement covariance should not be accepted")
+    }
+    assert_eq!(alignment.tracks(), 100);
+
+    let after = alignment.solve().unwrap();
+    assert_eq!(after.chi_squared_before, before.chi_squared_before);
+    for (id, correction) in before.iter() {
+        assert_eq!(after.get(id), Some(correction));
+    }
+}